@@ -67,7 +67,9 @@ if (is_watch) {
       queued_build = true;
     };
 
-    await watch([`src/projects/${project}`], handle_change, {
+    // Every project depends on the shared crate, so changes there rebuild everything
+    const watch_paths = [`src/projects/${project}`, "src/graphics-core"];
+    await watch(watch_paths, handle_change, {
       ignore: [
         /package\.json$/,
         /\.gitignore/,
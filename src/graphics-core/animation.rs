@@ -0,0 +1,546 @@
+use std::collections::BTreeMap;
+
+use nalgebra::{Quaternion, UnitQuaternion, Vector3, Vector4};
+use wasm_bindgen::prelude::*;
+
+use crate::transform::Transform;
+
+/// How a track gets from one keyframe to the next
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Holds the keyframe's value until the next keyframe
+    Step,
+    /// Moves at a steady rate (turning along the shortest arc for rotations)
+    Linear,
+    /// Eases through the keyframes along a Catmull-Rom spline
+    Cubic,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackMode {
+    /// Plays to the end and holds the last pose
+    Once,
+    /// Jumps back to the start after the end
+    Loop,
+    /// Plays forwards, then backwards, then forwards...
+    PingPong,
+}
+
+/// Values that keyframes can hold
+pub trait Keyable: Copy {
+    fn linear(from: &Self, to: &Self, t: f64) -> Self;
+    /// The cubic Hermite curve from `from` to `to`,
+    /// with tangents (per unit of `t`) taken from the keyframes either side
+    fn hermite(from: &Self, to: &Self, from_tangent: &Self, to_tangent: &Self, t: f64) -> Self;
+    /// How fast the value changes between two keyframes `dt` apart
+    fn slope(from: &Self, to: &Self, dt: f64) -> Self;
+    /// A slope multiplied by a length of time
+    fn scale_slope(slope: &Self, dt: f64) -> Self;
+}
+
+fn hermite_weights(t: f64) -> [f64; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        2.0 * t3 - 3.0 * t2 + 1.0,
+        t3 - 2.0 * t2 + t,
+        -2.0 * t3 + 3.0 * t2,
+        t3 - t2,
+    ]
+}
+
+impl Keyable for Vector3<f64> {
+    fn linear(from: &Self, to: &Self, t: f64) -> Self {
+        from.lerp(to, t)
+    }
+    fn hermite(from: &Self, to: &Self, from_tangent: &Self, to_tangent: &Self, t: f64) -> Self {
+        let [w_from, w_from_tangent, w_to, w_to_tangent] = hermite_weights(t);
+        from * w_from + from_tangent * w_from_tangent + to * w_to + to_tangent * w_to_tangent
+    }
+    fn slope(from: &Self, to: &Self, dt: f64) -> Self {
+        (to - from) / dt
+    }
+    fn scale_slope(slope: &Self, dt: f64) -> Self {
+        slope * dt
+    }
+}
+
+impl Keyable for UnitQuaternion<f64> {
+    fn linear(from: &Self, to: &Self, t: f64) -> Self {
+        from.try_slerp(to, t, 1e-12).unwrap_or(*to)
+    }
+    /// Splined component by component and then normalized, the same way glTF does it
+    fn hermite(from: &Self, to: &Self, from_tangent: &Self, to_tangent: &Self, t: f64) -> Self {
+        let [w_from, w_from_tangent, w_to, w_to_tangent] = hermite_weights(t);
+        let to = same_hemisphere(from, to);
+        let coords: Vector4<f64> = from.coords * w_from
+            + from_tangent.coords * w_from_tangent
+            + to * w_to
+            + to_tangent.coords * w_to_tangent;
+        UnitQuaternion::from_quaternion(Quaternion::from(coords))
+    }
+    /// Not a rotation, but the rate each component changes at (not normalized)
+    fn slope(from: &Self, to: &Self, dt: f64) -> Self {
+        let to = same_hemisphere(from, to);
+        UnitQuaternion::new_unchecked(Quaternion::from((to - from.coords) / dt))
+    }
+    fn scale_slope(slope: &Self, dt: f64) -> Self {
+        UnitQuaternion::new_unchecked(Quaternion::from(slope.coords * dt))
+    }
+}
+
+/// `to`'s components, negated if that puts them closer to `from` (it's the same rotation)
+fn same_hemisphere(from: &UnitQuaternion<f64>, to: &UnitQuaternion<f64>) -> Vector4<f64> {
+    if from.coords.dot(&to.coords) < 0.0 {
+        -to.coords
+    } else {
+        to.coords
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe<T> {
+    /// In seconds from the start of the animation
+    pub time: f64,
+    pub value: T,
+    /// How to get from this keyframe to the next one
+    pub interpolation: Interpolation,
+}
+
+/// One value changing over time, e.g. the rotation of one node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+    /// Sorted by time, with no two at the same time
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self { keyframes: vec![] }
+    }
+}
+
+impl<T: Keyable> Track<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a keyframe, replacing any keyframe already at that time
+    pub fn add(&mut self, time: f64, value: T, interpolation: Interpolation) {
+        let keyframe = Keyframe {
+            time,
+            value,
+            interpolation,
+        };
+        let index = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time < time);
+        match self.keyframes.get_mut(index) {
+            Some(existing) if existing.time == time => *existing = keyframe,
+            _ => self.keyframes.insert(index, keyframe),
+        }
+    }
+
+    #[inline]
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// The time of the last keyframe
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// The value at a time, holding the first and last values before and after the keyframes.
+    /// Nothing if there are no keyframes.
+    pub fn sample(&self, time: f64) -> Option<T> {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        if next == 0 {
+            return self.keyframes.first().map(|keyframe| keyframe.value);
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes.last().map(|keyframe| keyframe.value);
+        }
+        let from = &self.keyframes[next - 1];
+        let to = &self.keyframes[next];
+        let dt = to.time - from.time;
+        let t = (time - from.time) / dt;
+        Some(match from.interpolation {
+            Interpolation::Step => from.value,
+            Interpolation::Linear => T::linear(&from.value, &to.value, t),
+            Interpolation::Cubic => {
+                // Tangents are per second, the curve wants them per segment
+                let from_tangent = T::scale_slope(&self.tangent(next - 1), dt);
+                let to_tangent = T::scale_slope(&self.tangent(next), dt);
+                T::hermite(&from.value, &to.value, &from_tangent, &to_tangent, t)
+            }
+        })
+    }
+
+    /// The Catmull-Rom tangent at a keyframe, from the keyframes either side of it
+    fn tangent(&self, index: usize) -> T {
+        let before = &self.keyframes[index.saturating_sub(1)];
+        let after = &self.keyframes[(index + 1).min(self.keyframes.len() - 1)];
+        T::slope(&before.value, &after.value, after.time - before.time)
+    }
+}
+
+/// The tracks that move one node
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeTracks {
+    pub translation: Track<Vector3<f64>>,
+    pub rotation: Track<UnitQuaternion<f64>>,
+    pub scale: Track<Vector3<f64>>,
+}
+
+impl NodeTracks {
+    /// The node's transform at a time, using `rest` for anything without keyframes
+    pub fn sample(&self, time: f64, rest: &Transform) -> Transform {
+        Transform {
+            translation: self.translation.sample(time).unwrap_or(rest.translation),
+            rotation: self.rotation.sample(time).unwrap_or(rest.rotation),
+            scale: self.scale.sample(time).unwrap_or(rest.scale),
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.translation
+            .duration()
+            .max(self.rotation.duration())
+            .max(self.scale.duration())
+    }
+}
+
+/// Keyframes for any number of nodes, found by name
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnimationClip {
+    nodes: BTreeMap<String, NodeTracks>,
+}
+
+#[wasm_bindgen]
+impl AnimationClip {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_translation_keyframe(
+        &mut self,
+        node: &str,
+        time: f64,
+        x: f64,
+        y: f64,
+        z: f64,
+        interpolation: Interpolation,
+    ) {
+        self.node_mut(node)
+            .translation
+            .add(time, Vector3::new(x, y, z), interpolation);
+    }
+
+    /// The rotation is a quaternion, which doesn't need to be normalized
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_rotation_keyframe(
+        &mut self,
+        node: &str,
+        time: f64,
+        x: f64,
+        y: f64,
+        z: f64,
+        w: f64,
+        interpolation: Interpolation,
+    ) {
+        let rotation = UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z));
+        self.node_mut(node)
+            .rotation
+            .add(time, rotation, interpolation);
+    }
+
+    pub fn add_scale_keyframe(
+        &mut self,
+        node: &str,
+        time: f64,
+        x: f64,
+        y: f64,
+        z: f64,
+        interpolation: Interpolation,
+    ) {
+        self.node_mut(node)
+            .scale
+            .add(time, Vector3::new(x, y, z), interpolation);
+    }
+
+    /// The time of the last keyframe, in seconds
+    pub fn duration(&self) -> f64 {
+        self.nodes
+            .values()
+            .map(NodeTracks::duration)
+            .fold(0.0, f64::max)
+    }
+}
+
+impl AnimationClip {
+    pub fn node(&self, name: &str) -> Option<&NodeTracks> {
+        self.nodes.get(name)
+    }
+
+    /// The tracks for a node, added if there aren't any yet
+    pub fn node_mut(&mut self, name: &str) -> &mut NodeTracks {
+        self.nodes.entry(name.to_string()).or_default()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (&str, &NodeTracks)> {
+        self.nodes
+            .iter()
+            .map(|(name, tracks)| (name.as_str(), tracks))
+    }
+
+    /// Every animated node's transform at a time, starting from the identity
+    pub fn sample(&self, time: f64) -> impl Iterator<Item = (&str, Transform)> {
+        self.nodes()
+            .map(move |(name, tracks)| (name, tracks.sample(time, &Transform::identity())))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Playback {
+    name: String,
+    clip: AnimationClip,
+    mode: PlaybackMode,
+    /// Seconds since the clip started, scaled by the speed
+    time: f64,
+    speed: f64,
+    weight: f64,
+}
+
+impl Playback {
+    /// Where in the clip the playback is, after looping
+    fn clip_time(&self) -> f64 {
+        let duration = self.clip.duration();
+        if duration <= 0.0 {
+            return 0.0;
+        }
+        match self.mode {
+            PlaybackMode::Once => self.time.clamp(0.0, duration),
+            PlaybackMode::Loop => self.time.rem_euclid(duration),
+            PlaybackMode::PingPong => {
+                let time = self.time.rem_euclid(2.0 * duration);
+                if time > duration {
+                    2.0 * duration - time
+                } else {
+                    time
+                }
+            }
+        }
+    }
+}
+
+/// Plays any number of clips at once.
+/// Nodes animated by more than one clip get a blend of them, by weight.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationPlayer {
+    playing: Vec<Playback>,
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts playing a clip from the beginning, replacing any clip playing under the same name
+    pub fn play(&mut self, name: &str, clip: AnimationClip, mode: PlaybackMode, weight: f64) {
+        self.stop(name);
+        self.playing.push(Playback {
+            name: name.to_string(),
+            clip,
+            mode,
+            time: 0.0,
+            speed: 1.0,
+            weight,
+        });
+    }
+
+    /// Returns whether there was a clip playing under that name
+    pub fn stop(&mut self, name: &str) -> bool {
+        let count = self.playing.len();
+        self.playing.retain(|playback| playback.name != name);
+        self.playing.len() != count
+    }
+
+    /// Returns whether there is a clip playing under that name
+    pub fn set_weight(&mut self, name: &str, weight: f64) -> bool {
+        self.playback_mut(name)
+            .map(|playback| playback.weight = weight)
+            .is_some()
+    }
+
+    /// Returns whether there is a clip playing under that name.
+    /// Negative speeds play backwards.
+    pub fn set_speed(&mut self, name: &str, speed: f64) -> bool {
+        self.playback_mut(name)
+            .map(|playback| playback.speed = speed)
+            .is_some()
+    }
+
+    /// Whether a clip played once has reached its end (or isn't playing at all)
+    pub fn is_finished(&self, name: &str) -> bool {
+        self.playing
+            .iter()
+            .find(|playback| playback.name == name)
+            .is_none_or(|playback| {
+                playback.mode == PlaybackMode::Once && playback.time >= playback.clip.duration()
+            })
+    }
+
+    /// Moves every clip forwards by `dt` seconds
+    pub fn advance(&mut self, dt: f64) {
+        for playback in &mut self.playing {
+            playback.time += dt * playback.speed;
+        }
+    }
+
+    /// The blended transform of every node any of the clips animate
+    pub fn sample(&self) -> BTreeMap<String, Transform> {
+        let mut blended: BTreeMap<String, (Transform, f64)> = BTreeMap::new();
+        for playback in &self.playing {
+            if playback.weight <= 0.0 {
+                continue;
+            }
+            for (name, transform) in playback.clip.sample(playback.clip_time()) {
+                match blended.get_mut(name) {
+                    Some((blend, total_weight)) => {
+                        *total_weight += playback.weight;
+                        *blend = blend.interpolate(&transform, playback.weight / *total_weight);
+                    }
+                    None => {
+                        blended.insert(name.to_string(), (transform, playback.weight));
+                    }
+                }
+            }
+        }
+        blended
+            .into_iter()
+            .map(|(name, (transform, _))| (name, transform))
+            .collect()
+    }
+
+    fn playback_mut(&mut self, name: &str) -> Option<&mut Playback> {
+        self.playing
+            .iter_mut()
+            .find(|playback| playback.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::vector;
+
+    fn track(interpolation: Interpolation) -> Track<Vector3<f64>> {
+        let mut track = Track::new();
+        track.add(0.0, vector![0.0, 0.0, 0.0], interpolation);
+        track.add(2.0, vector![2.0, 4.0, 0.0], interpolation);
+        track.add(1.0, vector![1.0, 1.0, 0.0], interpolation);
+        track
+    }
+
+    #[test]
+    fn test_track_interpolation() {
+        let step = track(Interpolation::Step);
+        assert_eq!(step.keyframes()[1].time, 1.0);
+        assert_eq!(step.sample(-1.0), Some(vector![0.0, 0.0, 0.0]));
+        assert_eq!(step.sample(0.5), Some(vector![0.0, 0.0, 0.0]));
+        assert_eq!(step.sample(1.5), Some(vector![1.0, 1.0, 0.0]));
+        assert_eq!(step.sample(3.0), Some(vector![2.0, 4.0, 0.0]));
+
+        let linear = track(Interpolation::Linear);
+        assert_eq!(linear.sample(0.5), Some(vector![0.5, 0.5, 0.0]));
+        assert_eq!(linear.sample(1.5), Some(vector![1.5, 2.5, 0.0]));
+
+        let cubic = track(Interpolation::Cubic);
+        assert_eq!(cubic.sample(1.0), Some(vector![1.0, 1.0, 0.0]));
+        // x moves in a straight line, so the spline keeps it straight
+        let halfway = cubic.sample(1.5).unwrap();
+        assert!((halfway.x - 1.5).abs() < 1e-12);
+        // y speeds up, so the spline bows below the straight line
+        assert!(halfway.y < 2.5);
+
+        assert_eq!(Track::<Vector3<f64>>::new().sample(1.0), None);
+    }
+
+    #[test]
+    fn test_rotation_track() {
+        for interpolation in [Interpolation::Linear, Interpolation::Cubic] {
+            let mut track = Track::new();
+            for (time, angle) in [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)] {
+                track.add(
+                    time,
+                    UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle),
+                    interpolation,
+                );
+            }
+            let rotation = track.sample(0.25).unwrap();
+            assert!(
+                (rotation.angle() - 0.25).abs() < 1e-3,
+                "{:?}",
+                interpolation
+            );
+            assert!((rotation.axis().unwrap().into_inner() - Vector3::z()).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_playback_modes() {
+        let mut clip = AnimationClip::new();
+        clip.add_translation_keyframe("door", 0.0, 0.0, 0.0, 0.0, Interpolation::Linear);
+        clip.add_translation_keyframe("door", 2.0, 2.0, 0.0, 0.0, Interpolation::Linear);
+        assert_eq!(clip.duration(), 2.0);
+
+        let door_x = |player: &AnimationPlayer| player.sample()["door"].translation.x;
+        let mut player = AnimationPlayer::new();
+        for (mode, expected) in [
+            (PlaybackMode::Once, 2.0),
+            (PlaybackMode::Loop, 0.5),
+            (PlaybackMode::PingPong, 1.5),
+        ] {
+            player.play("open", clip.clone(), mode, 1.0);
+            player.advance(1.0);
+            assert_eq!(door_x(&player), 1.0);
+            assert!(!player.is_finished("open"));
+            player.advance(1.5);
+            assert_eq!(door_x(&player), expected, "{:?}", mode);
+            assert_eq!(player.is_finished("open"), mode == PlaybackMode::Once);
+        }
+
+        assert!(player.stop("open"));
+        assert!(player.sample().is_empty());
+    }
+
+    #[test]
+    fn test_blending() {
+        let still = |x: f64| {
+            let mut clip = AnimationClip::new();
+            clip.add_translation_keyframe("node", 0.0, x, 0.0, 0.0, Interpolation::Step);
+            clip.add_rotation_keyframe("node", 0.0, 0.0, 0.0, 0.0, 1.0, Interpolation::Step);
+            clip
+        };
+        let mut player = AnimationPlayer::new();
+        player.play("a", still(0.0), PlaybackMode::Loop, 1.0);
+        player.play("b", still(4.0), PlaybackMode::Loop, 3.0);
+        assert_eq!(player.sample()["node"].translation.x, 3.0);
+
+        player.set_weight("b", 0.0);
+        assert_eq!(player.sample()["node"].translation.x, 0.0);
+        assert!(!player.set_weight("c", 1.0));
+    }
+}
@@ -2,13 +2,13 @@ use crate::Number;
 use nalgebra::Point;
 
 #[derive(Debug, Clone)]
-pub(crate) struct BoundingBox<T: Number, const DIM: usize> {
-    pub(crate) min_pt: Point<T, { DIM }>,
-    pub(crate) max_pt: Point<T, { DIM }>,
+pub struct BoundingBox<T: Number, const DIM: usize> {
+    pub min_pt: Point<T, { DIM }>,
+    pub max_pt: Point<T, { DIM }>,
 }
 
 impl<T: Number, const DIM: usize> BoundingBox<T, { DIM }> {
-    pub(crate) fn from_points(points: &[Point<T, DIM>]) -> Self {
+    pub fn from_points(points: &[Point<T, DIM>]) -> Self {
         assert!(!points.is_empty());
         let mut min_pt = points[0];
         let mut max_pt = points[0];
@@ -25,7 +25,26 @@ impl<T: Number, const DIM: usize> BoundingBox<T, { DIM }> {
         Self { min_pt, max_pt }
     }
 
-    pub(crate) fn intersects_with(&self, other: &BoundingBox<T, { DIM }>) -> bool {
+    /// The smallest bounding box around both of them
+    pub fn union(&self, other: &BoundingBox<T, { DIM }>) -> Self {
+        let mut union = self.clone();
+        for dim in 0..DIM {
+            if other.min_pt[dim] < union.min_pt[dim] {
+                union.min_pt[dim] = other.min_pt[dim];
+            }
+            if other.max_pt[dim] > union.max_pt[dim] {
+                union.max_pt[dim] = other.max_pt[dim];
+            }
+        }
+        union
+    }
+
+    #[inline]
+    pub fn center(&self) -> Point<T, { DIM }> {
+        self.min_pt + (self.max_pt - self.min_pt) * <T as From<f32>>::from(0.5)
+    }
+
+    pub fn intersects_with(&self, other: &BoundingBox<T, { DIM }>) -> bool {
         /// Check whether two (min, max) ranges overlap with each other (inclusive)
         #[inline]
         fn range_overlaps<T: PartialOrd + Copy>(range_1: (T, T), range_2: (T, T)) -> bool {
@@ -50,7 +69,7 @@ impl<T: Number, const DIM: usize> BoundingBox<T, { DIM }> {
         true
     }
 
-    pub(crate) fn includes_point(&self, point: &Point<T, { DIM }>) -> bool {
+    pub fn includes_point(&self, point: &Point<T, { DIM }>) -> bool {
         // Every dimension/axis (x, y, z...) must include the point
         for dim in 0..DIM {
             if point[dim] < self.min_pt[dim] || point[dim] > self.max_pt[dim] {
@@ -87,6 +106,18 @@ mod tests {
         assert_eq!(bb.max_pt, Point3::new(1.0, 1.0, 1.0));
     }
 
+    #[test]
+    fn test_union() {
+        let bb1 =
+            BoundingBox::from_points(&[Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0)]);
+        let bb2 =
+            BoundingBox::from_points(&[Point3::new(2.0, -1.0, 0.5), Point3::new(3.0, 0.0, 0.5)]);
+        let union = bb1.union(&bb2);
+        assert_eq!(union.min_pt, Point3::new(0.0, -1.0, 0.0));
+        assert_eq!(union.max_pt, Point3::new(3.0, 1.0, 1.0));
+        assert_eq!(union.center(), Point3::new(1.5, 0.0, 0.5));
+    }
+
     #[test]
     fn test_intersection() {
         // The corners of these cubes touch
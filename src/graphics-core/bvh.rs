@@ -0,0 +1,427 @@
+//! A bounding volume hierarchy over faces, so ray, segment and sphere queries
+//! only have to look at the faces near them instead of every face in the scene.
+//! It's built once from the faces' bounding boxes, and queries check the faces' triangles exactly.
+
+use nalgebra::{Isometry3, Point3};
+use parry3d::{
+    query::{PointQuery, RayCast},
+    shape::{Segment, Triangle},
+};
+
+use crate::{bounding_box::BoundingBox, face::Face, ray::Ray};
+
+/// Leaves are split until they have at most this many faces
+const MAX_LEAF_FACES: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    faces: Vec<Face<f64>>,
+    /// Each face's triangles, built once since the queries go through them over and over
+    triangles: Vec<Vec<Triangle>>,
+    /// The root is the first node, if there are any faces
+    nodes: Vec<Node>,
+    /// Indices into `faces`, grouped so each leaf's faces are next to each other
+    order: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounding_box: BoundingBox<f64, 3>,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    /// A range of `order`
+    Leaf { start: usize, end: usize },
+    /// Indices into `nodes`
+    Branch { left: usize, right: usize },
+}
+
+/// Where a ray first hits a face
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayHit {
+    /// The index of the face that was hit
+    pub face: usize,
+    pub point: Point3<f64>,
+    /// How far along the ray the hit is, from 0 at the start to 1 at the end
+    pub fraction: f64,
+}
+
+impl Bvh {
+    pub fn new(faces: Vec<Face<f64>>) -> Self {
+        let mut bvh = Self {
+            order: (0..faces.len()).collect(),
+            triangles: faces.iter().map(Face::to_triangles).collect(),
+            faces,
+            nodes: vec![],
+        };
+        if !bvh.faces.is_empty() {
+            bvh.build(0, bvh.faces.len());
+        }
+        bvh
+    }
+
+    #[inline]
+    pub fn faces(&self) -> &[Face<f64>] {
+        &self.faces
+    }
+
+    /// The triangles of one of the faces, as parry shapes, leaving out any with no area
+    #[inline]
+    pub fn triangles(&self, face: usize) -> &[Triangle] {
+        &self.triangles[face]
+    }
+
+    /// The first face the ray hits going from its start to its end
+    pub fn cast_ray(&self, ray: &Ray<f64, 3>) -> Option<RayHit> {
+        let parry_ray = parry3d::query::Ray::new(*ray.start(), ray.to_vector());
+        let mut closest: Option<RayHit> = None;
+        self.traverse(
+            &mut closest,
+            |closest, bounding_box| {
+                segment_entry(bounding_box, ray)
+                    .is_some_and(|entry| closest.as_ref().is_none_or(|hit| entry <= hit.fraction))
+            },
+            |closest, face_index, triangles| {
+                for triangle in triangles {
+                    let Some(fraction) = triangle.cast_local_ray(&parry_ray, 1.0, true) else {
+                        continue;
+                    };
+                    if closest.as_ref().is_none_or(|hit| fraction < hit.fraction) {
+                        *closest = Some(RayHit {
+                            face: face_index,
+                            point: parry_ray.point_at(fraction),
+                            fraction,
+                        });
+                    }
+                }
+                false
+            },
+        );
+        closest
+    }
+
+    /// A face that comes within `margin` of the segment between the ray's start and end, if any do
+    pub fn intersects_segment(&self, segment: &Ray<f64, 3>, margin: f64) -> Option<usize> {
+        let parry_segment = segment.to_segment();
+        let mut found = None;
+        self.traverse(
+            &mut found,
+            |_, bounding_box| segment_entry(&expanded(bounding_box, margin), segment).is_some(),
+            |found, face_index, triangles| {
+                let near = is_near_segment(triangles, &parry_segment, margin);
+                if near {
+                    *found = Some(face_index);
+                }
+                near
+            },
+        );
+        found
+    }
+
+    /// Every face that comes within `margin` of the segment, in no particular order,
+    /// e.g. the faces a sphere with a radius of `margin` could hit moving along it
+    pub fn faces_near_segment(&self, segment: &Ray<f64, 3>, margin: f64) -> Vec<usize> {
+        let parry_segment = segment.to_segment();
+        let mut near = vec![];
+        self.traverse(
+            &mut near,
+            |_, bounding_box| segment_entry(&expanded(bounding_box, margin), segment).is_some(),
+            |near, face_index, triangles| {
+                if is_near_segment(triangles, &parry_segment, margin) {
+                    near.push(face_index);
+                }
+                false
+            },
+        );
+        near
+    }
+
+    /// The faces that touch or are inside of the sphere, in no particular order
+    pub fn overlaps_sphere(&self, center: &Point3<f64>, radius: f64) -> Vec<usize> {
+        let mut overlapping = vec![];
+        self.traverse(
+            &mut overlapping,
+            |_, bounding_box| distance_squared(bounding_box, center) <= radius * radius,
+            |overlapping, face_index, triangles| {
+                if triangles
+                    .iter()
+                    .any(|triangle| triangle.distance_to_local_point(center, true) <= radius)
+                {
+                    overlapping.push(face_index);
+                }
+                false
+            },
+        );
+        overlapping
+    }
+
+    /// Visits the faces' triangles in every leaf whose bounding box (and whose ancestors' bounding boxes)
+    /// `enter` accepts, until `visit` returns true.
+    /// Both of them can see what has been found so far in `state`, e.g. to skip nodes that are further away.
+    fn traverse<S>(
+        &self,
+        state: &mut S,
+        enter: impl Fn(&S, &BoundingBox<f64, 3>) -> bool,
+        mut visit: impl FnMut(&mut S, usize, &[Triangle]) -> bool,
+    ) {
+        let mut stack = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !enter(state, &node.bounding_box) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for &face_index in &self.order[start..end] {
+                        if visit(state, face_index, &self.triangles[face_index]) {
+                            return;
+                        }
+                    }
+                }
+                NodeKind::Branch { left, right } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+    }
+
+    /// Adds the node for `order[start..end]` (and the nodes under it), returning its index.
+    /// Faces are split in half by where their centers are along the longest side of the node.
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let faces = &self.faces;
+        let order = &mut self.order[start..end];
+        let bounding_box = order
+            .iter()
+            .map(|&i| faces[i].bounding_box().clone())
+            .reduce(|union, bounding_box| union.union(&bounding_box))
+            .unwrap();
+        let node_index = self.nodes.len();
+        if order.len() <= MAX_LEAF_FACES {
+            self.nodes.push(Node {
+                bounding_box,
+                kind: NodeKind::Leaf { start, end },
+            });
+            return node_index;
+        }
+
+        let size = bounding_box.max_pt - bounding_box.min_pt;
+        let axis = size.imax();
+        let middle = order.len() / 2;
+        order.select_nth_unstable_by(middle, |&a, &b| {
+            let a = faces[a].bounding_box().center()[axis];
+            let b = faces[b].bounding_box().center()[axis];
+            a.total_cmp(&b)
+        });
+        // Filled in once the children have been added
+        self.nodes.push(Node {
+            bounding_box,
+            kind: NodeKind::Leaf { start, end },
+        });
+        let left = self.build(start, start + middle);
+        let right = self.build(start + middle, end);
+        self.nodes[node_index].kind = NodeKind::Branch { left, right };
+        node_index
+    }
+}
+
+fn is_near_segment(triangles: &[Triangle], segment: &Segment, margin: f64) -> bool {
+    triangles.iter().any(|triangle| {
+        parry3d::query::distance(
+            &Isometry3::identity(),
+            triangle,
+            &Isometry3::identity(),
+            segment,
+        )
+        .unwrap()
+            <= margin
+    })
+}
+
+fn expanded(bounding_box: &BoundingBox<f64, 3>, margin: f64) -> BoundingBox<f64, 3> {
+    BoundingBox {
+        min_pt: bounding_box.min_pt.map(|value| value - margin),
+        max_pt: bounding_box.max_pt.map(|value| value + margin),
+    }
+}
+
+/// How far along the ray (from 0 to 1) it goes into the bounding box, if it ever does
+fn segment_entry(bounding_box: &BoundingBox<f64, 3>, ray: &Ray<f64, 3>) -> Option<f64> {
+    let direction = ray.to_vector();
+    let mut entry: f64 = 0.0;
+    let mut exit: f64 = 1.0;
+    for dim in 0..3 {
+        let start = ray.start()[dim];
+        let (min, max) = (bounding_box.min_pt[dim], bounding_box.max_pt[dim]);
+        if direction[dim] == 0.0 {
+            // Parallel to this pair of sides, so it's either always between them or never
+            if start < min || start > max {
+                return None;
+            }
+            continue;
+        }
+        let to_min = (min - start) / direction[dim];
+        let to_max = (max - start) / direction[dim];
+        entry = entry.max(to_min.min(to_max));
+        exit = exit.min(to_min.max(to_max));
+        if entry > exit {
+            return None;
+        }
+    }
+    Some(entry)
+}
+
+/// How far the point is from the closest point in the bounding box, squared
+fn distance_squared(bounding_box: &BoundingBox<f64, 3>, point: &Point3<f64>) -> f64 {
+    let closest = point.coords.zip_zip_map(
+        &bounding_box.min_pt.coords,
+        &bounding_box.max_pt.coords,
+        |value, min, max| value.clamp(min, max),
+    );
+    (point.coords - closest).norm_squared()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{point, vector};
+
+    use super::*;
+
+    /// A grid of small quads at different heights and tilts, enough to go a few levels deep
+    fn faces() -> Vec<Face<f64>> {
+        let mut faces = vec![];
+        for x in 0..10 {
+            for z in 0..10 {
+                let (x, z) = (x as f64, z as f64);
+                let y = ((x * 3.0 + z * 7.0) % 5.0) * 0.1;
+                let tilt = (x - z) * 0.05;
+                faces.push(Face::new(vec![
+                    point![x, y, z],
+                    point![x, y + tilt, z + 0.8],
+                    point![x + 0.8, y + tilt, z + 0.8],
+                    point![x + 0.8, y, z],
+                ]));
+            }
+        }
+        faces
+    }
+
+    /// Rays in every direction from across the grid, some of which miss
+    fn rays() -> Vec<Ray<f64, 3>> {
+        let mut rays = vec![];
+        for i in 0..50 {
+            let i = i as f64;
+            let start = point![
+                (i * 1.7) % 11.0 - 0.5,
+                2.0 - (i * 0.3) % 3.0,
+                (i * 2.3) % 11.0 - 0.5
+            ];
+            let offset = vector![
+                (i * 0.9).sin() * 4.0,
+                -3.0 + (i * 0.4).cos(),
+                (i * 1.3).cos() * 4.0
+            ];
+            rays.push(Ray::new(start, start + offset));
+        }
+        rays
+    }
+
+    #[test]
+    fn test_cast_ray() {
+        let faces = faces();
+        let bvh = Bvh::new(faces.clone());
+        assert!(bvh.nodes.len() > 1);
+        let mut hits = 0;
+        for ray in rays() {
+            let parry_ray = parry3d::query::Ray::new(*ray.start(), ray.to_vector());
+            let expected = faces
+                .iter()
+                .flat_map(Face::to_triangles)
+                .filter_map(|triangle| triangle.cast_local_ray(&parry_ray, 1.0, true))
+                .reduce(f64::min);
+            let hit = bvh.cast_ray(&ray);
+            assert_eq!(hit.as_ref().map(|hit| hit.fraction), expected, "{:?}", ray);
+            if let Some(hit) = hit {
+                hits += 1;
+                assert!((hit.point - parry_ray.point_at(hit.fraction)).norm() < 1e-12);
+                assert!(faces[hit.face]
+                    .to_triangles()
+                    .iter()
+                    .any(|triangle| triangle.distance_to_local_point(&hit.point, true) < 1e-9));
+            }
+        }
+        assert!(hits > 10);
+
+        let straight_down = Ray::new(point![0.4, 5.0, 0.4], point![0.4, -5.0, 0.4]);
+        let hit = bvh.cast_ray(&straight_down).unwrap();
+        assert_eq!(hit.face, 0);
+        assert!((hit.point - point![0.4, 0.0, 0.4]).norm() < 1e-12);
+        assert_eq!(bvh.cast_ray(&straight_down.invert()).unwrap().face, 0);
+        assert_eq!(Bvh::new(vec![]).cast_ray(&straight_down), None);
+    }
+
+    #[test]
+    fn test_intersects_segment() {
+        let faces = faces();
+        let bvh = Bvh::new(faces.clone());
+        for margin in [0.0, 0.1, 0.5] {
+            for ray in rays() {
+                let near = |face: &Face<f64>| {
+                    face.to_triangles().iter().any(|triangle| {
+                        parry3d::query::distance(
+                            &Isometry3::identity(),
+                            triangle,
+                            &Isometry3::identity(),
+                            &ray.to_segment(),
+                        )
+                        .unwrap()
+                            <= margin
+                    })
+                };
+                let found = bvh.intersects_segment(&ray, margin);
+                assert_eq!(found.is_some(), faces.iter().any(near), "{:?}", ray);
+                let mut all_near = bvh.faces_near_segment(&ray, margin);
+                all_near.sort();
+                let expected: Vec<usize> = (0..faces.len()).filter(|&i| near(&faces[i])).collect();
+                assert_eq!(all_near, expected);
+                if let Some(face) = found {
+                    assert!(near(&faces[face]));
+                }
+            }
+        }
+        // Stopping short of the grid
+        let segment = Ray::new(point![5.0, 3.0, 5.0], point![5.0, 1.0, 5.0]);
+        assert_eq!(bvh.intersects_segment(&segment, 0.1), None);
+        assert!(bvh.intersects_segment(&segment, 1.0).is_some());
+    }
+
+    #[test]
+    fn test_overlaps_sphere() {
+        let faces = faces();
+        let bvh = Bvh::new(faces.clone());
+        for (center, radius) in [
+            (point![4.5, 0.0, 4.5], 1.0),
+            (point![0.0, 0.0, 0.0], 0.1),
+            (point![-2.0, 0.0, -2.0], 1.0),
+            (point![5.0, 0.0, 5.0], 20.0),
+        ] {
+            let mut overlapping = bvh.overlaps_sphere(&center, radius);
+            overlapping.sort();
+            let expected: Vec<usize> = (0..faces.len())
+                .filter(|&i| {
+                    faces[i]
+                        .to_triangles()
+                        .iter()
+                        .any(|triangle| triangle.distance_to_local_point(&center, true) <= radius)
+                })
+                .collect();
+            assert_eq!(overlapping, expected);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use nalgebra::{Matrix4, Point3, Scale3, UnitQuaternion, UnitVector3, Vector3};
+
+use crate::transform_matrix::TransformMatrix;
+
+/// The keyboard and mouse input for one frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CameraInput {
+    pub w: bool,
+    pub a: bool,
+    pub s: bool,
+    pub d: bool,
+    pub cursor_movement_x: f64,
+    pub cursor_movement_y: f64,
+}
+
+/// A camera that flies around, accelerating with WASD and turning with the mouse
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub position: Point3<f64>,
+    /// Where the camera was before its last step,
+    /// so it can be drawn partway between the two (see `interpolated`)
+    previous_position: Point3<f64>,
+    pub direction: UnitVector3<f64>,
+    pub velocity: Vector3<f64>,
+    pub aspect_ratio: f64,
+}
+
+impl Camera {
+    pub fn new(position: Point3<f64>, direction: UnitVector3<f64>) -> Self {
+        Self {
+            position,
+            previous_position: position,
+            direction,
+            velocity: Vector3::zeros(),
+            aspect_ratio: 1.0,
+        }
+    }
+
+    /// Turns, moves, and then accelerates the camera.
+    /// `dt` is in seconds, the time since last render.
+    pub fn update(&mut self, up: &UnitVector3<f64>, input: &CameraInput, dt: f64) {
+        self.rotate(up, input);
+        self.step(up, input, dt);
+    }
+
+    /// Moves and then accelerates the camera, without turning it.
+    /// For running in fixed steps, where the mouse movement is only used once per frame.
+    pub fn step(&mut self, up: &UnitVector3<f64>, input: &CameraInput, dt: f64) {
+        self.begin_step();
+        self.position += self.velocity * dt;
+        self.accelerate(up, input, dt);
+    }
+
+    /// Remembers where the camera is before a step moves it, for `interpolated`.
+    /// Steps that move the camera some other way than `step` need to call this first.
+    pub fn begin_step(&mut self) {
+        self.previous_position = self.position;
+    }
+
+    /// The camera as it is `alpha` (from 0 to 1) of the way from its previous position to its current one
+    pub fn interpolated(&self, alpha: f64) -> Self {
+        Self {
+            position: self.previous_position + (self.position - self.previous_position) * alpha,
+            ..self.clone()
+        }
+    }
+
+    /// Turns the camera to follow the mouse movement
+    pub fn rotate(&mut self, up: &UnitVector3<f64>, input: &CameraInput) {
+        let up = up.into_inner();
+        let rotation_scale = 0.01;
+        let rotation_x = rotation_scale * input.cursor_movement_x;
+        let rotation_y = rotation_scale * input.cursor_movement_y;
+        let forwards = self.direction.into_inner();
+        let right = forwards.cross(&up);
+        self.direction = UnitVector3::new_normalize(
+            UnitQuaternion::new(up * rotation_x + right * rotation_y)
+                .transform_vector(&self.direction),
+        );
+    }
+
+    /// Speeds up in the direction of the pressed keys,
+    /// and slows down in the directions where no keys are pressed
+    pub fn accelerate(&mut self, up: &UnitVector3<f64>, input: &CameraInput, dt: f64) {
+        let forwards = self.direction.into_inner();
+        let right = forwards.cross(up);
+        self.accelerate_along(&forwards, &right, input, dt);
+        // There was a bug where when turning the camera it could continue drifting
+        // Since the third component of the "camera directions" didn't have code to handle deceleration
+        // So I added this
+        let camera_up = forwards.cross(&right);
+        self.velocity -= decel(dt) * (self.velocity.dot(&camera_up)) * camera_up;
+    }
+
+    /// Like `accelerate`, but only along the ground (perpendicular to `up`),
+    /// up to walking speed, leaving the velocity along `up` alone for gravity and jumping
+    pub fn accelerate_walking(&mut self, up: &UnitVector3<f64>, input: &CameraInput, dt: f64) {
+        let max_speed = 4.0;
+        // Looking straight up or down, there's no telling which way is forwards
+        let Some(forwards) = (self.direction.into_inner()
+            - up.into_inner() * self.direction.dot(up))
+        .try_normalize(1e-9) else {
+            return;
+        };
+        let right = forwards.cross(up);
+        self.accelerate_along(&forwards, &right, input, dt);
+        let vertical = up.into_inner() * self.velocity.dot(up);
+        let horizontal = self.velocity - vertical;
+        self.velocity = vertical + horizontal.cap_magnitude(max_speed);
+    }
+
+    fn accelerate_along(
+        &mut self,
+        forwards: &Vector3<f64>,
+        right: &Vector3<f64>,
+        input: &CameraInput,
+        dt: f64,
+    ) {
+        let accel = 15.0;
+        let decel = decel(dt);
+        if input.w {
+            self.velocity += accel * dt * forwards;
+        } else if input.s {
+            self.velocity -= accel * dt * forwards;
+        } else {
+            self.velocity -= decel * (self.velocity.dot(forwards)) * forwards;
+        }
+        if input.a {
+            self.velocity += accel * dt * right;
+        } else if input.d {
+            self.velocity -= accel * dt * right;
+        } else {
+            self.velocity -= decel * (self.velocity.dot(right)) * right;
+        }
+    }
+
+    pub fn world_to_camera(&self, up: &UnitVector3<f64>) -> TransformMatrix {
+        self.world_to_camera_without_camera_translation(up)
+            .times(&TransformMatrix::from(Matrix4::new_translation(
+                &Vector3::new(-self.position.x, -self.position.y, -self.position.z),
+            )))
+    }
+
+    pub fn world_to_camera_without_camera_translation(
+        &self,
+        up: &UnitVector3<f64>,
+    ) -> TransformMatrix {
+        TransformMatrix::from(
+            // Scale everything in the z direction down
+            // (does not affect the positions of any vertices)
+            // This just reduces the scope of z values to reduce clipping
+            Scale3::new(1.0, 1.0, 0.01).to_homogeneous()
+                * Matrix4::new_perspective(self.aspect_ratio, 30.0, 0.01, 100.0)
+                * Matrix4::look_at_rh(
+                    &Point3::origin(),
+                    &Point3::new(self.direction.x, self.direction.y, self.direction.z),
+                    up,
+                ),
+        )
+    }
+}
+
+/// How much of the velocity to take away in `dt` seconds, when slowing down.
+/// It was 15% per frame at 60 frames per second,
+/// so it's worked out to be the same at any frame rate.
+pub fn decel(dt: f64) -> f64 {
+    1.0 - 0.85_f64.powf(dt * 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{point, vector};
+
+    fn up() -> UnitVector3<f64> {
+        UnitVector3::new_normalize(vector![0.0, 1.0, 0.0])
+    }
+
+    fn camera() -> Camera {
+        Camera::new(
+            point![0.0, 0.0, 0.0],
+            UnitVector3::new_normalize(vector![0.0, 0.0, -1.0]),
+        )
+    }
+
+    #[test]
+    fn test_moves_forwards() {
+        let mut camera = camera();
+        let input = CameraInput {
+            w: true,
+            ..Default::default()
+        };
+        for _ in 0..60 {
+            camera.update(&up(), &input, 1.0 / 60.0);
+        }
+        assert!(camera.position.z < -5.0);
+        assert!(camera.position.x.abs() < 1e-10);
+        assert!(camera.position.y.abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_strafes() {
+        let mut camera = camera();
+        let input = CameraInput {
+            a: true,
+            ..Default::default()
+        };
+        camera.update(&up(), &input, 0.5);
+        camera.update(&up(), &input, 0.5);
+        // Facing -z with +y up, "a" moves along forwards x up, which is +x
+        assert!(camera.position.x > 0.0);
+        assert!(camera.position.z.abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_decelerates() {
+        let mut camera = camera();
+        camera.velocity = vector![1.0, 2.0, 3.0];
+        for _ in 0..200 {
+            camera.update(&up(), &CameraInput::default(), 1.0 / 60.0);
+        }
+        assert!(camera.velocity.norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_walks_along_ground() {
+        let mut camera = camera();
+        // Looking down at an angle, walking still only moves along the ground
+        camera.direction = UnitVector3::new_normalize(vector![0.0, -1.0, -1.0]);
+        camera.velocity = vector![0.0, -2.0, 0.0];
+        let input = CameraInput {
+            w: true,
+            ..Default::default()
+        };
+        for _ in 0..120 {
+            camera.accelerate_walking(&up(), &input, 1.0 / 60.0);
+        }
+        assert!((camera.velocity - vector![0.0, -2.0, -4.0]).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_interpolated() {
+        let mut camera = camera();
+        camera.velocity = vector![0.0, 0.0, -1.0];
+        camera.step(&up(), &CameraInput::default(), 1.0);
+        assert_eq!(camera.interpolated(0.0).position, point![0.0, 0.0, 0.0]);
+        assert_eq!(camera.interpolated(0.5).position, point![0.0, 0.0, -0.5]);
+        assert_eq!(camera.interpolated(1.0).position, camera.position);
+
+        // Moved some other way, after starting a step
+        camera.begin_step();
+        camera.position.x += 2.0;
+        assert_eq!(camera.interpolated(0.5).position, point![1.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn test_turns_with_cursor() {
+        let mut camera = camera();
+        let input = CameraInput {
+            cursor_movement_x: 100.0 * std::f64::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        camera.rotate(&up(), &input);
+        // A quarter turn about up
+        assert!((camera.direction.into_inner() - vector![-1.0, 0.0, 0.0]).norm() < 1e-10);
+        assert_eq!(camera.position, Point3::origin());
+    }
+
+    #[test]
+    fn test_world_to_camera_centers_camera() {
+        let mut camera = Camera::new(
+            point![1.0, 2.0, 3.0],
+            UnitVector3::new_normalize(vector![0.0, 0.0, -1.0]),
+        );
+        camera.aspect_ratio = 2.0;
+        let in_front = camera
+            .world_to_camera(&up())
+            .matrix()
+            .transform_point(&point![1.0, 2.0, -7.0]);
+        assert!(in_front.x.abs() < 1e-10);
+        assert!(in_front.y.abs() < 1e-10);
+    }
+}
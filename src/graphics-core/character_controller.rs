@@ -0,0 +1,450 @@
+//! Moves a sphere (e.g. the camera) through a BVH's faces without going through them:
+//! when it runs into a face it slides along it instead of stopping dead,
+//! and it can step up onto ledges that are low enough.
+
+use nalgebra::{Isometry3, Point3, Unit, UnitVector3, Vector3};
+use parry3d::{query::PointQuery, shape::Ball};
+
+use crate::{bvh::Bvh, ray::Ray};
+
+/// Faces whose normals are at least this close to up (the cosine of the angle between them)
+/// are floors that can be stood on, the rest are walls
+const WALKABLE: f64 = 0.7;
+
+/// Movements shorter than this are treated as not moving at all
+const EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharacterController {
+    pub radius: f64,
+    /// How many times in one move the sphere can run into something and slide along it
+    pub max_iterations: usize,
+    /// The tallest ledge that the sphere steps up onto instead of being blocked by
+    pub step_height: f64,
+    /// How far away from faces the sphere stops,
+    /// so it doesn't start the next move already touching them
+    pub skin: f64,
+}
+
+/// Something the sphere ran into
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contact {
+    /// The index of the face in the BVH
+    pub face: usize,
+    /// The closest point on the face to the sphere's center
+    pub point: Point3<f64>,
+    /// Pointing away from the face, towards the sphere's center
+    pub normal: UnitVector3<f64>,
+}
+
+impl Contact {
+    /// Whether the face is flat enough to stand on
+    pub fn is_floor(&self, up: &UnitVector3<f64>) -> bool {
+        self.normal.dot(up) >= WALKABLE
+    }
+}
+
+/// Where the sphere ended up after a move
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movement {
+    pub position: Point3<f64>,
+    /// The velocity with the parts going into the faces it ran into taken out
+    pub velocity: Vector3<f64>,
+    /// In the order it ran into them
+    pub contacts: Vec<Contact>,
+    /// How long into the move it first ran into something, in seconds
+    pub time_of_impact: Option<f64>,
+}
+
+impl CharacterController {
+    /// A controller that slides up to 4 times per move and doesn't step up onto anything
+    pub fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            max_iterations: 4,
+            step_height: 0.0,
+            skin: radius * 0.01,
+        }
+    }
+
+    pub fn with_step_height(mut self, step_height: f64) -> Self {
+        self.step_height = step_height;
+        self
+    }
+
+    /// Moves the sphere by `velocity` for `dt` seconds, sliding along whatever it runs into.
+    /// `up` decides which faces are floors and which way is up for stepping onto ledges.
+    /// If it starts out overlapping a face, it's pushed out first.
+    pub fn move_and_slide(
+        &self,
+        bvh: &Bvh,
+        position: Point3<f64>,
+        velocity: Vector3<f64>,
+        dt: f64,
+        up: &UnitVector3<f64>,
+    ) -> Movement {
+        let (mut position, mut contacts) = self.depenetrate(bvh, position);
+        let mut time_of_impact = if contacts.is_empty() { None } else { Some(0.0) };
+        let mut velocity = velocity;
+        let mut remaining = velocity * dt;
+        for _ in 0..self.max_iterations {
+            if remaining.norm() < EPSILON {
+                break;
+            }
+            let Some((fraction, contact)) = self.sweep(bvh, &position, &remaining) else {
+                position += remaining;
+                break;
+            };
+            position = self.rest_against(&contact);
+            remaining *= 1.0 - fraction;
+            // It only gets to slide after the first impact
+            time_of_impact.get_or_insert(fraction * dt);
+
+            if !contact.is_floor(up) {
+                if let Some(stepped) = self.step_up(bvh, &position, &remaining, up) {
+                    position = stepped;
+                    remaining = Vector3::zeros();
+                    continue;
+                }
+            }
+            remaining = slide(&remaining, &contact.normal);
+            velocity = slide(&velocity, &contact.normal);
+            contacts.push(contact);
+        }
+        Movement {
+            position,
+            velocity,
+            contacts,
+            time_of_impact,
+        }
+    }
+
+    /// How far along `displacement` (from 0 to 1) the sphere can go before it touches a face,
+    /// and what it touches, if it touches anything
+    pub fn sweep(
+        &self,
+        bvh: &Bvh,
+        position: &Point3<f64>,
+        displacement: &Vector3<f64>,
+    ) -> Option<(f64, Contact)> {
+        let ball = Ball::new(self.radius);
+        let path = Ray::new(*position, position + displacement);
+        let mut closest: Option<(f64, Contact)> = None;
+        for face in bvh.faces_near_segment(&path, self.radius + self.skin) {
+            for triangle in bvh.triangles(face) {
+                let Some(impact) = parry3d::query::time_of_impact(
+                    &Isometry3::translation(position.x, position.y, position.z),
+                    displacement,
+                    &ball,
+                    &Isometry3::identity(),
+                    &Vector3::zeros(),
+                    triangle,
+                    1.0,
+                    true,
+                )
+                .unwrap() else {
+                    continue;
+                };
+                if closest
+                    .as_ref()
+                    .is_some_and(|(fraction, _)| *fraction <= impact.toi)
+                {
+                    continue;
+                }
+                let center = position + displacement * impact.toi;
+                let point = triangle.project_local_point(&center, true).point;
+                let normal = Unit::try_new(center - point, EPSILON)
+                    .unwrap_or_else(|| -Unit::new_normalize(*displacement));
+                closest = Some((
+                    impact.toi,
+                    Contact {
+                        face,
+                        point,
+                        normal,
+                    },
+                ));
+            }
+        }
+        closest
+    }
+
+    /// The floor the sphere is standing on, if it's within a skin's width or so of one.
+    /// Short moves can end up resting just above the floor without touching it,
+    /// so this checks for one instead of relying on the move's contacts.
+    pub fn ground(
+        &self,
+        bvh: &Bvh,
+        position: &Point3<f64>,
+        up: &UnitVector3<f64>,
+    ) -> Option<Contact> {
+        self.sweep(bvh, position, &(-up.into_inner() * self.skin * 2.0))
+            .map(|(_, contact)| contact)
+            .filter(|contact| contact.is_floor(up))
+    }
+
+    /// Pushes the sphere out of any faces it overlaps, one at a time, deepest first
+    fn depenetrate(&self, bvh: &Bvh, position: Point3<f64>) -> (Point3<f64>, Vec<Contact>) {
+        let mut position = position;
+        let mut contacts = vec![];
+        for _ in 0..self.max_iterations {
+            let deepest = bvh
+                .overlaps_sphere(&position, self.radius)
+                .into_iter()
+                .flat_map(|face| {
+                    bvh.triangles(face).iter().map(move |triangle| {
+                        (face, triangle.project_local_point(&position, true).point)
+                    })
+                })
+                .map(|(face, point)| (face, point, (position - point).norm()))
+                .filter(|(_, _, distance)| *distance < self.radius)
+                .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+            let Some((face, point, distance)) = deepest else {
+                break;
+            };
+            // Right on the face, there's no telling which side it should be on
+            let Some(normal) = Unit::try_new(position - point, EPSILON) else {
+                break;
+            };
+            position += normal.into_inner() * (self.radius - distance + self.skin);
+            contacts.push(Contact {
+                face,
+                point,
+                normal,
+            });
+        }
+        (position, contacts)
+    }
+
+    /// Lifts the sphere up, moves it sideways, and puts it back down,
+    /// which only works if there's room above it and it lands on a floor.
+    /// Returns where it lands.
+    fn step_up(
+        &self,
+        bvh: &Bvh,
+        position: &Point3<f64>,
+        remaining: &Vector3<f64>,
+        up: &UnitVector3<f64>,
+    ) -> Option<Point3<f64>> {
+        if self.step_height <= 0.0 {
+            return None;
+        }
+        let sideways = remaining - up.into_inner() * remaining.dot(up);
+        if sideways.norm() < EPSILON {
+            return None;
+        }
+        let lift = up.into_inner() * self.step_height;
+        if self.sweep(bvh, position, &lift).is_some() {
+            return None;
+        }
+        let raised = position + lift;
+        // Still blocked once it's been lifted, so it's a wall and not a ledge
+        if self.sweep(bvh, &raised, &sideways).is_some() {
+            return None;
+        }
+        let moved = raised + sideways;
+        match self.sweep(bvh, &moved, &-lift) {
+            Some((_, contact)) if contact.is_floor(up) => Some(self.rest_against(&contact)),
+            _ => None,
+        }
+    }
+
+    /// Where the sphere stops when it runs into something, just off of it.
+    /// This goes by where it touched rather than how far it got,
+    /// so it ends up outside no matter how far it was going.
+    fn rest_against(&self, contact: &Contact) -> Point3<f64> {
+        contact.point + contact.normal.into_inner() * (self.radius + self.skin)
+    }
+}
+
+/// Takes out the part of `vector` going into the face, keeping the part along it
+fn slide(vector: &Vector3<f64>, normal: &UnitVector3<f64>) -> Vector3<f64> {
+    let into_face = vector.dot(normal);
+    if into_face < 0.0 {
+        vector - normal.into_inner() * into_face
+    } else {
+        *vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{point, vector};
+
+    use super::*;
+    use crate::face::Face;
+
+    fn up() -> UnitVector3<f64> {
+        Vector3::y_axis()
+    }
+
+    /// A square on the plane x = `x`, facing -x
+    fn wall(x: f64, height: f64) -> Face<f64> {
+        Face::new(vec![
+            point![x, 0.0, -10.0],
+            point![x, height, -10.0],
+            point![x, height, 10.0],
+            point![x, 0.0, 10.0],
+        ])
+    }
+
+    /// A square on the plane y = `y` from x = `start` to x = `end`, facing up
+    fn floor(y: f64, start: f64, end: f64) -> Face<f64> {
+        Face::new(vec![
+            point![start, y, -10.0],
+            point![start, y, 10.0],
+            point![end, y, 10.0],
+            point![end, y, -10.0],
+        ])
+    }
+
+    #[test]
+    fn test_slides_along_wall() {
+        let bvh = Bvh::new(vec![wall(1.0, 10.0)]);
+        let controller = CharacterController::new(0.25);
+        let movement = controller.move_and_slide(
+            &bvh,
+            point![0.0, 1.0, 0.0],
+            vector![2.0, 0.0, 2.0],
+            1.0,
+            &up(),
+        );
+        assert_eq!(movement.contacts.len(), 1);
+        assert!((movement.contacts[0].normal.into_inner() - vector![-1.0, 0.0, 0.0]).norm() < 1e-6);
+        // Stopped at the wall, and kept going along it
+        assert!((movement.position.x - 0.75).abs() < 0.01);
+        assert!(movement.position.x < 0.75);
+        assert!((movement.position.z - 2.0).abs() < 0.01);
+        assert!(movement.velocity.x.abs() < 1e-6);
+        assert!((movement.velocity.z - 2.0).abs() < 1e-6);
+
+        // Moving away from the wall doesn't touch it
+        let movement = controller.move_and_slide(
+            &bvh,
+            point![0.0, 1.0, 0.0],
+            vector![-1.0, 0.0, 0.0],
+            1.0,
+            &up(),
+        );
+        assert!(movement.contacts.is_empty());
+        assert_eq!(movement.position, point![-1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_never_passes_through_walls() {
+        // Just one face, with nothing behind it to catch the sphere if it goes through
+        let bvh = Bvh::new(vec![wall(1.0, 10.0)]);
+        let controller = CharacterController::new(0.25);
+        for speed in [1e3, 1e6, 1e9] {
+            for dt in [1.0 / 60.0, 1.0, 100.0] {
+                let movement = controller.move_and_slide(
+                    &bvh,
+                    point![0.0, 1.0, 0.0],
+                    vector![speed, 0.0, speed * 0.1],
+                    dt,
+                    &up(),
+                );
+                let time_of_impact = movement.time_of_impact.unwrap();
+                assert!((time_of_impact - 0.75 / speed).abs() < 1e-6 * dt);
+                assert!(movement.position.x < 0.75);
+                assert!(movement.position.x > 0.74);
+            }
+        }
+        // Doesn't get there before it stops
+        let movement = controller.move_and_slide(
+            &bvh,
+            point![0.0, 1.0, 0.0],
+            vector![1.0, 0.0, 0.0],
+            0.5,
+            &up(),
+        );
+        assert_eq!(movement.time_of_impact, None);
+    }
+
+    #[test]
+    fn test_stops_in_corner() {
+        let corner = Face::new(vec![
+            point![-10.0, 0.0, 1.0],
+            point![-10.0, 10.0, 1.0],
+            point![10.0, 10.0, 1.0],
+            point![10.0, 0.0, 1.0],
+        ]);
+        let bvh = Bvh::new(vec![wall(1.0, 10.0), corner]);
+        let controller = CharacterController::new(0.25);
+        let movement = controller.move_and_slide(
+            &bvh,
+            point![0.0, 1.0, 0.0],
+            vector![3.0, 0.0, 2.0],
+            1.0,
+            &up(),
+        );
+        assert_eq!(movement.contacts.len(), 2);
+        assert!((movement.position - point![0.75, 1.0, 0.75]).norm() < 0.01);
+        assert!(movement.velocity.norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_pushed_out_of_wall() {
+        let bvh = Bvh::new(vec![wall(1.0, 10.0)]);
+        let controller = CharacterController::new(0.25);
+        let movement =
+            controller.move_and_slide(&bvh, point![0.9, 1.0, 0.0], Vector3::zeros(), 1.0, &up());
+        assert!(movement.position.x <= 0.75);
+        assert!(movement.position.x > 0.74);
+    }
+
+    #[test]
+    fn test_steps_up_ledges() {
+        let radius = 0.25;
+        // The ground, then a step up 0.2 at x = 1
+        let faces = vec![
+            floor(0.0, -10.0, 1.0),
+            wall(1.0, 0.2),
+            floor(0.2, 1.0, 10.0),
+        ];
+        let bvh = Bvh::new(faces);
+        let controller = CharacterController::new(radius).with_step_height(0.3);
+        let start = point![0.0, radius + 0.01, 0.0];
+        let movement = controller.move_and_slide(&bvh, start, vector![2.0, 0.0, 0.0], 1.0, &up());
+        assert!(movement.position.x > 1.5, "{}", movement.position);
+        assert!((movement.position.y - (0.2 + radius)).abs() < 0.01);
+
+        // Too tall to step up onto
+        let faces = vec![
+            floor(0.0, -10.0, 1.0),
+            wall(1.0, 0.5),
+            floor(0.5, 1.0, 10.0),
+        ];
+        let bvh = Bvh::new(faces);
+        let movement = controller.move_and_slide(&bvh, start, vector![2.0, 0.0, 0.0], 1.0, &up());
+        assert!(movement.position.x < 1.0 - radius + 0.01);
+        assert!(movement.velocity.x.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ground() {
+        let radius = 0.25;
+        let bvh = Bvh::new(vec![floor(0.0, -10.0, 10.0), wall(1.0, 10.0)]);
+        let controller = CharacterController::new(radius);
+        // Falling a tiny bit, not far enough to touch the floor
+        let movement = controller.move_and_slide(
+            &bvh,
+            point![0.0, radius + controller.skin, 0.0],
+            vector![0.0, -0.1, 0.0],
+            0.001,
+            &up(),
+        );
+        assert!(movement.contacts.is_empty());
+        assert!(controller.ground(&bvh, &movement.position, &up()).is_some());
+        assert!(controller
+            .ground(&bvh, &point![0.0, radius + 0.1, 0.0], &up())
+            .is_none());
+        // Against the wall, but in the air
+        assert!(controller
+            .ground(
+                &bvh,
+                &point![1.0 - radius - controller.skin, 1.0, 0.0],
+                &up()
+            )
+            .is_none());
+    }
+}
@@ -0,0 +1,675 @@
+use std::fmt;
+
+use nalgebra::{Matrix3, Point2, Point3, Unit, UnitVector3, Vector2, Vector3};
+use num_traits::Float;
+
+use parry3d::shape::{ConvexPolyhedron, SharedShape, Triangle};
+
+use crate::{bounding_box::BoundingBox, triangulate, Number};
+
+#[derive(Debug, Clone)]
+pub struct Face<T: Number> {
+    points: Vec<Point3<T>>,
+    uvs: Vec<Vector2<T>>,
+    /// Polygons cut out of the face (e.g. a window in a wall), all inside the outline of `points`
+    holes: Vec<Vec<Point3<T>>>,
+    /// The UV at each point of each hole
+    hole_uvs: Vec<Vec<Vector2<T>>>,
+    bounding_box: BoundingBox<T, 3>,
+    /// Unit vector in the "outwards" direction of the face
+    normal: UnitVector3<T>,
+    /// Unit vector along the first edge with any length, flattened onto the face plane
+    x: UnitVector3<T>,
+    /// Unit vector in the direction orthogonal to the x axis, on the face plane
+    y: UnitVector3<T>,
+    /// Matrix that converts a vector from absolute to relative (face-coordinates-based)
+    absolute_to_relative: Matrix3<T>,
+    /// The coordinates of each point, in terms of the face-plane-defined x and y axis
+    points_relative: Vec<Point2<T>>,
+    /// The index of each point in the list of vertices it was loaded from (e.g. from an OBJ file)
+    point_ids: Option<Vec<usize>>,
+    /// Indices into `points` (and `uvs`) for each triangle the polygon is broken into,
+    /// where indices past the end of `points` continue on into each hole in turn
+    triangles: Vec<[usize; 3]>,
+    /// The normal at each point, for smooth shading, pointing the same way as `normal`
+    point_normals: Option<Vec<UnitVector3<T>>>,
+}
+
+/// Reasons a list of points can't be made into a face
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaceError {
+    /// Faces need at least 3 points
+    TooFewPoints(usize),
+    /// Each point needs a UV (the lengths are the number of points and UVs)
+    MismatchedUvs(usize, usize),
+    /// The points have (nearly) zero area, e.g. they are all collinear,
+    /// so there is no direction the face could be facing
+    Degenerate,
+}
+
+impl fmt::Display for FaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaceError::TooFewPoints(count) => {
+                write!(f, "points must be 3 or more, received {}", count)
+            }
+            FaceError::MismatchedUvs(points, uvs) => {
+                write!(f, "received {} points but {} UVs", points, uvs)
+            }
+            FaceError::Degenerate => write!(f, "face has no area, so it has no normal"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct UVPair<T: Number> {
+    pub point: Point3<T>,
+    pub uv: Vector2<T>,
+}
+
+impl<T: Number> UVPair<T> {
+    fn unzip(uv_pairs: Vec<UVPair<T>>) -> (Vec<Point3<T>>, Vec<Vector2<T>>) {
+        uv_pairs
+            .into_iter()
+            .map(|uv_pair| (uv_pair.point, uv_pair.uv))
+            .unzip()
+    }
+}
+
+impl<T: Number> Face<T> {
+    pub fn new_with_point_ids(points: Vec<(Point3<T>, usize)>) -> Self {
+        let (points, point_ids) = points.into_iter().unzip();
+        Self::new(points).with_point_ids(point_ids)
+    }
+    /// Attaches the index of each point in the list of vertices it was loaded from
+    /// (the points of the outline, then the points of each hole)
+    pub fn with_point_ids(mut self, point_ids: Vec<usize>) -> Self {
+        assert_eq!(self.corner_count(), point_ids.len());
+        self.point_ids = Some(point_ids);
+        self
+    }
+    /// Attaches a normal to each point, e.g. from a mesh that is meant to look smooth
+    /// (the points of the outline, then the points of each hole)
+    pub fn with_point_normals(mut self, point_normals: Vec<UnitVector3<T>>) -> Self {
+        assert_eq!(self.corner_count(), point_normals.len());
+        self.point_normals = Some(point_normals);
+        self
+    }
+    /// Cuts holes out of the face, which have to be entirely inside its outline
+    /// and can't overlap each other. Either winding works.
+    /// Point ids and point normals have to be attached afterwards, since they cover the holes too.
+    pub fn with_holes(mut self, holes: Vec<Vec<UVPair<T>>>) -> Self {
+        assert!(
+            self.point_ids.is_none() && self.point_normals.is_none(),
+            "Holes have to be cut out before attaching point ids or normals"
+        );
+        for hole in holes {
+            assert!(hole.len() >= 3, "Holes need at least 3 points");
+            let (points, uvs) = UVPair::unzip(hole);
+            self.holes.push(points);
+            self.hole_uvs.push(uvs);
+        }
+        self.triangles = Self::triangulate(&self.points, &self.holes, &self.normal);
+        self
+    }
+    pub fn from_uv_pairs(uvs_and_points: Vec<UVPair<T>>) -> Self {
+        let (points, uvs) = UVPair::unzip(uvs_and_points);
+        Self::new_with_uvs(points, uvs)
+    }
+    pub fn try_from_uv_pairs(uvs_and_points: Vec<UVPair<T>>) -> Result<Self, FaceError> {
+        let (points, uvs) = UVPair::unzip(uvs_and_points);
+        Self::try_new_with_uvs(points, uvs)
+    }
+    pub fn new(points: Vec<Point3<T>>) -> Self {
+        let uvs = points.iter().map(|_p| Vector2::<T>::zeros()).collect();
+        Self::new_with_uvs(points, uvs)
+    }
+    /// Panics if the points can't be made into a face,
+    /// so anything loaded from a file should go through `try_new_with_uvs` instead
+    pub fn new_with_uvs(points: Vec<Point3<T>>, uvs: Vec<Vector2<T>>) -> Self {
+        match Self::try_new_with_uvs(points, uvs) {
+            Ok(face) => face,
+            Err(err) => panic!("Invalid face: {}", err),
+        }
+    }
+    pub fn try_new_with_uvs(
+        points: Vec<Point3<T>>,
+        uvs: Vec<Vector2<T>>,
+    ) -> Result<Self, FaceError> {
+        if points.len() < 3 {
+            return Err(FaceError::TooFewPoints(points.len()));
+        }
+        if points.len() != uvs.len() {
+            return Err(FaceError::MismatchedUvs(points.len(), uvs.len()));
+        }
+        let bounding_box = BoundingBox::from_points(&points);
+        let normal = Self::newell_normal(&points, &bounding_box)?;
+        let x = Self::x_axis(&points, &normal, &bounding_box)?;
+        let y: UnitVector3<T> = Unit::new_normalize(normal.cross(&x));
+        let absolute_to_relative =
+            Matrix3::from_columns(&[x.into_inner(), y.into_inner(), normal.into_inner()]);
+
+        let points_relative = points
+            .iter()
+            .map(|point| {
+                let p = absolute_to_relative * point;
+                Point2::new(p.x, p.y)
+            })
+            .collect();
+
+        Ok(Self {
+            triangles: Self::triangulate(&points, &[], &normal),
+            bounding_box,
+            points,
+            normal,
+            x,
+            y,
+            absolute_to_relative,
+            points_relative,
+            point_ids: None,
+            uvs,
+            holes: vec![],
+            hole_uvs: vec![],
+            point_normals: None,
+        })
+    }
+
+    /// The same face scaled up or down around the origin, e.g. to change the units it's in.
+    /// The normal and the UVs stay the same, so the factor has to be positive.
+    pub fn scaled(&self, factor: T) -> Self {
+        assert!(
+            factor > T::zero(),
+            "Faces can only be scaled by a positive factor"
+        );
+        let mut face = self.clone();
+        for point in face
+            .points
+            .iter_mut()
+            .chain(face.holes.iter_mut().flatten())
+        {
+            *point *= factor;
+        }
+        for point in &mut face.points_relative {
+            *point *= factor;
+        }
+        face.bounding_box = BoundingBox::from_points(&face.points);
+        face
+    }
+
+    /// Finds the normal using Newell's method, which sums up the contribution of every edge,
+    /// so it doesn't matter if some points are collinear,
+    /// and non-planar faces get the normal of the "average" plane
+    fn newell_normal(
+        points: &[Point3<T>],
+        bounding_box: &BoundingBox<T, 3>,
+    ) -> Result<UnitVector3<T>, FaceError> {
+        let mut sum = Vector3::<T>::zeros();
+        for (i, current) in points.iter().enumerate() {
+            let next = points[(i + 1) % points.len()];
+            sum += Vector3::new(
+                (current.y - next.y) * (current.z + next.z),
+                (current.z - next.z) * (current.x + next.x),
+                (current.x - next.x) * (current.y + next.y),
+            );
+        }
+        // The sum is twice the area of the face, so compare it against the size of the face
+        // to decide whether it is too small to be anything other than rounding error
+        let size = (bounding_box.max_pt - bounding_box.min_pt).norm();
+        let tolerance = T::epsilon() * <T as From<f32>>::from(16.0) * size * size;
+        // Newell's method follows the right-hand rule (counterclockwise points face towards you),
+        // but faces in this project are wound the other way
+        let length = sum.norm();
+        if length > tolerance {
+            Ok(Unit::new_unchecked(-sum / length))
+        } else {
+            Err(FaceError::Degenerate)
+        }
+    }
+
+    /// Finds the x axis of the face's own coordinates: the first edge that isn't (nearly)
+    /// zero length, flattened onto the plane of the face so the axes stay perpendicular
+    /// even when the points aren't quite planar
+    fn x_axis(
+        points: &[Point3<T>],
+        normal: &UnitVector3<T>,
+        bounding_box: &BoundingBox<T, 3>,
+    ) -> Result<UnitVector3<T>, FaceError> {
+        let size = (bounding_box.max_pt - bounding_box.min_pt).norm();
+        let tolerance = T::epsilon() * <T as From<f32>>::from(16.0) * size;
+        points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(current, next)| {
+                let edge = next - current;
+                edge - normal.scale(normal.dot(&edge))
+            })
+            .find(|edge| edge.norm() > tolerance)
+            .map(Unit::new_normalize)
+            .ok_or(FaceError::Degenerate)
+    }
+
+    /// Finds the triangles that make up the polygon.
+    /// Convex polygons are split into a fan around the first point,
+    /// concave polygons and polygons with holes go through ear clipping.
+    fn triangulate(
+        points: &[Point3<T>],
+        holes: &[Vec<Point3<T>>],
+        normal: &UnitVector3<T>,
+    ) -> Vec<[usize; 3]> {
+        if points.len() == 3 && holes.is_empty() {
+            return vec![[0, 1, 2]];
+        }
+        // Flatten onto whichever axis-aligned plane the face is "most facing",
+        // dropping the coordinate that the normal points along the most.
+        // The winding might end up flipped but both triangulations handle either direction.
+        let (x_axis, y_axis) =
+            if normal.x.abs() >= normal.y.abs() && normal.x.abs() >= normal.z.abs() {
+                (1, 2)
+            } else if normal.y.abs() >= normal.z.abs() {
+                (2, 0)
+            } else {
+                (0, 1)
+            };
+        let flatten = |points: &[Point3<T>]| -> Vec<Point2<T>> {
+            points
+                .iter()
+                .map(|point| Point2::new(point[x_axis], point[y_axis]))
+                .collect()
+        };
+        let flattened = flatten(points);
+        if holes.is_empty() && triangulate::is_convex(&flattened) {
+            triangulate::fan(&flattened)
+        } else {
+            let flattened_holes: Vec<Vec<Point2<T>>> =
+                holes.iter().map(|hole| flatten(hole)).collect();
+            triangulate::ear_clip(&flattened, &flattened_holes)
+        }
+    }
+
+    /// The number of points in the outline and all the holes
+    fn corner_count(&self) -> usize {
+        self.points.len() + self.holes.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// The point and UV at an index from `triangles`
+    fn corner(&self, mut i: usize) -> (Point3<T>, Vector2<T>) {
+        if i < self.points.len() {
+            return (self.points[i], self.uvs[i]);
+        }
+        i -= self.points.len();
+        for (hole, hole_uvs) in self.holes.iter().zip(&self.hole_uvs) {
+            if i < hole.len() {
+                return (hole[i], hole_uvs[i]);
+            }
+            i -= hole.len();
+        }
+        panic!("Triangle corner is past the end of the face's points");
+    }
+
+    /// Breaks a polygon into a bunch of triangle points (so they can be passed directly into webgl)
+    pub fn break_into_triangles(&self) -> Vec<Point3<T>> {
+        self.triangles
+            .iter()
+            .flat_map(|triangle| triangle.map(|i| self.corner(i).0))
+            .collect()
+    }
+
+    /// The corners of each triangle the polygon is broken into
+    pub fn triangle_points(&self) -> impl Iterator<Item = [Point3<T>; 3]> + '_ {
+        self.triangles
+            .iter()
+            .map(move |triangle| triangle.map(|i| self.corner(i).0))
+    }
+
+    /// Breaks a polygon into a bunch of triangle points, returning the corresponding UVs
+    /// (in the same order as `break_into_triangles`)
+    pub fn break_into_uv_triangles(&self) -> Vec<Vector2<T>> {
+        self.triangles
+            .iter()
+            .flat_map(|triangle| triangle.map(|i| self.corner(i).1))
+            .collect()
+    }
+
+    /// Breaks a polygon into a bunch of triangles, returning the normal at each corner
+    /// (in the same order as `break_into_triangles`).
+    /// Without point normals, every corner gets the face's normal.
+    pub fn break_into_normal_triangles(&self) -> Vec<UnitVector3<T>> {
+        self.triangles
+            .iter()
+            .flat_map(|triangle| {
+                triangle.map(|i| match &self.point_normals {
+                    Some(point_normals) => point_normals[i],
+                    None => self.normal,
+                })
+            })
+            .collect()
+    }
+
+    /// Breaks a polygon into a bunch of triangles, returning the point ids of each corner
+    /// (in the same order as `break_into_triangles`)
+    pub fn break_into_triangles_with_ids(&self) -> Vec<usize> {
+        let point_ids = self.point_ids.as_ref().unwrap();
+        self.triangles
+            .iter()
+            .flat_map(|triangle| triangle.map(|i| point_ids[i]))
+            .collect()
+    }
+
+    #[inline]
+    pub fn absolute_to_relative(&self, point: Vector3<T>) -> Vector3<T> {
+        self.absolute_to_relative * point
+    }
+
+    #[inline]
+    pub fn bounding_box(&self) -> &BoundingBox<T, 3> {
+        &self.bounding_box
+    }
+
+    #[inline]
+    pub fn normal(&self) -> &UnitVector3<T> {
+        &self.normal
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[Point3<T>] {
+        &self.points
+    }
+
+    #[inline]
+    pub fn uvs(&self) -> &[Vector2<T>] {
+        &self.uvs
+    }
+
+    #[inline]
+    pub fn holes(&self) -> &[Vec<Point3<T>>] {
+        &self.holes
+    }
+
+    #[inline]
+    pub fn point_normals(&self) -> Option<&[UnitVector3<T>]> {
+        self.point_normals.as_deref()
+    }
+
+    #[inline]
+    pub fn point_ids(&self) -> Option<&Vec<usize>> {
+        self.point_ids.as_ref()
+    }
+
+    #[inline]
+    pub fn points_relative(&self) -> &[Point2<T>] {
+        &self.points_relative
+    }
+
+    #[inline]
+    pub fn origin(&self) -> &Point3<T> {
+        &self.points[0]
+    }
+
+    #[inline]
+    pub fn x(&self) -> &UnitVector3<T> {
+        &self.x
+    }
+
+    #[inline]
+    pub fn y(&self) -> &UnitVector3<T> {
+        &self.y
+    }
+
+    /// The furthest distance any point is from the plane of the face
+    /// (the plane through the average of the points, perpendicular to the normal).
+    /// Zero for triangles and perfectly flat faces, useful for finding bad geometry in meshes.
+    pub fn planarity_error(&self) -> T {
+        let count = <T as From<f32>>::from(self.points.len() as f32);
+        let center = self
+            .points
+            .iter()
+            .fold(Vector3::zeros(), |sum, point| sum + point.coords)
+            / count;
+        self.points
+            .iter()
+            .map(|point| Float::abs((point.coords - center).dot(&self.normal)))
+            .fold(T::zero(), Float::max)
+    }
+
+    #[inline]
+    pub fn is_planar(&self, tolerance: T) -> bool {
+        self.planarity_error() <= tolerance
+    }
+}
+
+impl Face<f64> {
+    /// The convex hull of the points, or nothing if parry can't find one,
+    /// which happens when the points are (nearly) all on one plane
+    pub fn to_convex_polyhedron(&self) -> Option<ConvexPolyhedron> {
+        ConvexPolyhedron::from_convex_hull(&self.points)
+    }
+
+    /// The triangles the polygon is broken into, leaving out any that are too thin to have a normal
+    pub fn to_triangles(&self) -> Vec<Triangle> {
+        self.triangle_points()
+            .map(|[a, b, c]| Triangle::new(a, b, c))
+            .filter(|triangle| triangle.normal().is_some())
+            .collect()
+    }
+
+    /// The face as a shape for parry's queries, meant to be built once and kept:
+    /// a triangle, or a triangle mesh for anything with more points.
+    /// Nothing if none of its triangles have any area.
+    pub fn to_collision_shape(&self) -> Option<SharedShape> {
+        let triangles = self.to_triangles();
+        match triangles.as_slice() {
+            [] => None,
+            [triangle] => Some(SharedShape::new(*triangle)),
+            _ => {
+                let vertices = triangles
+                    .iter()
+                    .flat_map(|triangle| triangle.vertices())
+                    .copied()
+                    .collect();
+                let indices = (0..triangles.len() as u32)
+                    .map(|i| [i * 3, i * 3 + 1, i * 3 + 2])
+                    .collect();
+                Some(SharedShape::trimesh(vertices, indices))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{point, vector};
+
+    use super::*;
+
+    #[test]
+    fn test_normal() {
+        let face = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![1.0, 1.0, 0.0],
+        ]);
+        assert!((face.normal().into_inner() - vector![0.0, 0.0, -1.0]).norm() < 1e-9);
+
+        // The first three points are collinear, which used to produce a NaN normal
+        let face = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![2.0, 0.0, 0.0],
+            point![2.0, 1.0, 0.0],
+            point![0.0, 1.0, 0.0],
+        ]);
+        assert!((face.normal().into_inner() - vector![0.0, 0.0, -1.0]).norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_degenerate() {
+        let result = Face::try_new_with_uvs(
+            vec![
+                point![0.0, 0.0, 0.0],
+                point![1.0, 1.0, 1.0],
+                point![2.0, 2.0, 2.0],
+            ],
+            vec![Vector2::zeros(); 3],
+        );
+        assert_eq!(result.unwrap_err(), FaceError::Degenerate);
+
+        let result = Face::try_new_with_uvs(
+            vec![point![0.0, 0.0, 0.0], point![1.0, 1.0, 1.0]],
+            vec![Vector2::zeros(); 2],
+        );
+        assert_eq!(result.unwrap_err(), FaceError::TooFewPoints(2));
+    }
+
+    #[test]
+    fn test_axes() {
+        let assert_orthonormal = |face: &Face<f64>| {
+            let (x, y, normal) = (face.x(), face.y(), face.normal());
+            assert!(x.dot(y).abs() < 1e-9);
+            assert!(x.dot(normal).abs() < 1e-9);
+            assert!(y.dot(normal).abs() < 1e-9);
+            assert!(face
+                .points_relative()
+                .iter()
+                .all(|point| point.x.is_finite() && point.y.is_finite()));
+        };
+
+        // The first two points are the same, so the first edge has no direction
+        let face = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![1.0, 1.0, 0.0],
+        ]);
+        assert!((face.x().into_inner() - vector![1.0, 0.0, 0.0]).norm() < 1e-9);
+        assert_orthonormal(&face);
+
+        // The first edge leans out of the average plane of the face
+        let bent = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.4],
+            point![1.0, 1.0, 0.0],
+            point![0.0, 1.0, 0.0],
+        ]);
+        assert_orthonormal(&bent);
+    }
+
+    #[test]
+    fn test_holes() {
+        // A 4x4 wall with a 2x2 window in the middle, area 12
+        let uv_pair = |x, y| UVPair {
+            point: point![x, y, 0.0],
+            uv: vector![x / 4.0, y / 4.0],
+        };
+        let wall = Face::from_uv_pairs(vec![
+            uv_pair(0.0, 0.0),
+            uv_pair(4.0, 0.0),
+            uv_pair(4.0, 4.0),
+            uv_pair(0.0, 4.0),
+        ])
+        .with_holes(vec![vec![
+            uv_pair(1.0, 1.0),
+            uv_pair(3.0, 1.0),
+            uv_pair(3.0, 3.0),
+            uv_pair(1.0, 3.0),
+        ]]);
+        assert_eq!(wall.holes().len(), 1);
+
+        let area: f64 = wall
+            .triangle_points()
+            .map(|[a, b, c]| (b - a).cross(&(c - a)).norm() / 2.0)
+            .sum();
+        assert!((area - 12.0).abs() < 1e-9);
+        // Every triangle is still wound the same way as the face
+        assert!(wall
+            .triangle_points()
+            .all(|[a, b, c]| (b - a).cross(&(c - a)).dot(wall.normal()) < 0.0));
+        // The UVs line up with the points, including the hole's
+        let points = wall.break_into_triangles();
+        let uvs = wall.break_into_uv_triangles();
+        assert_eq!(points.len(), uvs.len());
+        assert!(points
+            .iter()
+            .zip(&uvs)
+            .all(|(point, uv)| (point.xy().coords / 4.0 - uv).norm() < 1e-9));
+
+        let shape = wall.to_collision_shape().unwrap();
+        let crosses = |x, y| {
+            parry3d::query::intersection_test(
+                &nalgebra::Isometry::identity(),
+                &*shape,
+                &nalgebra::Isometry::identity(),
+                &parry3d::shape::Segment::new(point![x, y, 1.0], point![x, y, -1.0]),
+            )
+            .unwrap()
+        };
+        assert!(crosses(0.5, 0.5));
+        assert!(!crosses(2.0, 2.0));
+
+        let scaled = wall.scaled(2.0);
+        assert_eq!(scaled.holes()[0][2], point![6.0, 6.0, 0.0]);
+    }
+
+    #[test]
+    fn test_planarity() {
+        let flat = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![1.0, 1.0, 0.0],
+            point![0.0, 1.0, 0.0],
+        ]);
+        assert!(flat.planarity_error() < 1e-9);
+        assert!(flat.is_planar(1e-9));
+
+        // One corner lifted up out of the plane of the others
+        let bent = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![1.0, 1.0, 0.4],
+            point![0.0, 1.0, 0.0],
+        ]);
+        // Each corner is roughly 0.1 away from the average plane
+        assert!((bent.planarity_error() - 0.1).abs() < 0.01);
+        assert!(!bent.is_planar(0.01));
+    }
+
+    #[test]
+    fn test_collision_shape() {
+        // A square with an extra point halfway along one side, so one of its triangles has no area
+        let square = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![0.5, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![1.0, 1.0, 0.0],
+            point![0.0, 1.0, 0.0],
+        ]);
+        assert_eq!(square.break_into_triangles().len(), 9);
+        assert_eq!(square.to_triangles().len(), 2);
+        // Flat, so there might not be a hull, but it shouldn't panic
+        let _ = square.to_convex_polyhedron();
+
+        let shape = square.to_collision_shape().unwrap();
+        let crosses = |start, end| {
+            parry3d::query::intersection_test(
+                &nalgebra::Isometry::identity(),
+                &*shape,
+                &nalgebra::Isometry::identity(),
+                &parry3d::shape::Segment::new(start, end),
+            )
+            .unwrap()
+        };
+        assert!(crosses(point![0.5, 0.5, 1.0], point![0.5, 0.5, -1.0]));
+        assert!(!crosses(point![1.5, 0.5, 1.0], point![1.5, 0.5, -1.0]));
+        assert!(!crosses(point![0.5, 0.5, 1.0], point![0.5, 0.5, 0.5]));
+
+        let triangle = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![0.0, 1.0, 0.0],
+        ]);
+        assert!(triangle
+            .to_collision_shape()
+            .unwrap()
+            .as_triangle()
+            .is_some());
+    }
+}
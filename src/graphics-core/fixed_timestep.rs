@@ -0,0 +1,104 @@
+//! Runs a simulation in steps that are all the same length, however long the frames are,
+//! so things move and collide the same way at any frame rate.
+
+/// How long each step is, in milliseconds.
+/// 125 steps per second, a whole number of milliseconds so frames add up to steps exactly.
+pub const STEP_MS: f64 = 8.0;
+
+/// Frames longer than this (like after the page was in the background) only count for this long,
+/// so it doesn't take forever catching up
+pub const MAX_FRAME_MS: f64 = 1000.0;
+
+/// Keeps track of the time that has passed but hasn't been simulated yet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedTimestep {
+    step_ms: f64,
+    accumulated_ms: f64,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(STEP_MS)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(step_ms: f64) -> Self {
+        Self {
+            step_ms,
+            accumulated_ms: 0.0,
+        }
+    }
+
+    /// How long each step is, in seconds
+    pub fn dt(&self) -> f64 {
+        self.step_ms / 1000.0
+    }
+
+    /// Adds the time since the last frame, and returns how many steps to simulate for it.
+    /// Whatever is left over, short of a whole step, is saved for the next frame.
+    pub fn advance(&mut self, delta_time_ms: f64) -> usize {
+        self.accumulated_ms += delta_time_ms.min(MAX_FRAME_MS);
+        let steps = (self.accumulated_ms / self.step_ms).floor();
+        self.accumulated_ms -= steps * self.step_ms;
+        steps as usize
+    }
+
+    /// How far it is from the last step to the next one, from 0 to 1,
+    /// for drawing things partway between where they were after the last two steps
+    pub fn alpha(&self) -> f64 {
+        self.accumulated_ms / self.step_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{point, vector, UnitVector3};
+
+    use super::*;
+    use crate::camera::{Camera, CameraInput};
+
+    #[test]
+    fn test_advance() {
+        let mut timestep = FixedTimestep::default();
+        assert_eq!(timestep.advance(5.0), 0);
+        assert_eq!(timestep.alpha(), 5.0 / 8.0);
+        assert_eq!(timestep.advance(16.0), 2);
+        assert_eq!(timestep.alpha(), 5.0 / 8.0);
+        assert_eq!(timestep.advance(3.0), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.dt(), 0.008);
+        // A whole minute in the background
+        assert_eq!(timestep.advance(60_000.0), 125);
+    }
+
+    #[test]
+    fn test_same_at_any_frame_rate() {
+        let fly = |frames: &[f64]| {
+            let mut camera = Camera::new(
+                point![0.0, 0.0, 0.0],
+                UnitVector3::new_normalize(vector![0.0, 0.0, -1.0]),
+            );
+            let mut timestep = FixedTimestep::default();
+            let up = UnitVector3::new_normalize(vector![0.0, 1.0, 0.0]);
+            let mut time_ms = 0.0;
+            for delta_time_ms in frames {
+                // Speeds up for a while, then coasts
+                let input = CameraInput {
+                    w: time_ms < 480.0,
+                    ..Default::default()
+                };
+                time_ms += delta_time_ms;
+                for _ in 0..timestep.advance(*delta_time_ms) {
+                    camera.step(&up, &input, timestep.dt());
+                }
+            }
+            camera.position
+        };
+        let at_60_fps = fly(&[16.0; 60]);
+        assert!(at_60_fps.z < -1.0);
+        assert_eq!(at_60_fps, fly(&[8.0; 120]));
+        assert_eq!(at_60_fps, fly(&[32.0; 30]));
+        assert_eq!(at_60_fps, fly(&[40.0, 8.0, 24.0, 24.0].repeat(10)));
+    }
+}
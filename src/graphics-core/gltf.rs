@@ -0,0 +1,1316 @@
+//! Loading glTF 2.0 files, either as JSON (.gltf) with their buffers in data URIs or separate files,
+//! or as binary GLB (.glb): the node hierarchy and its transforms,
+//! the meshes with their normals and UVs, and the materials.
+//!
+//! Only what's needed to draw static geometry is read;
+//! cameras, skins, animations and morph targets are skipped.
+
+use std::fmt;
+
+use nalgebra::{Matrix4, Point3, Quaternion, Translation3, Unit, UnitQuaternion, Vector2, Vector3};
+
+use crate::{
+    face::Face,
+    json::{Json, JsonError},
+    material::Material,
+    units::{scale_transform, LengthUnit},
+};
+
+/// The first four bytes of a GLB file, "glTF"
+pub(crate) const GLB_MAGIC: u32 = 0x4654_6c67;
+pub(crate) const GLB_JSON_CHUNK: u32 = 0x4e4f_534a;
+pub(crate) const GLB_BIN_CHUNK: u32 = 0x004e_4942;
+
+/// The most values an accessor without a buffer view can fill with zeros,
+/// since nothing in the file backs its count up
+const MAX_ZEROED_VALUES: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GltfError {
+    Json(JsonError),
+    /// Where in the file (e.g. `accessors[2].count`), and what should have been there
+    Invalid {
+        path: String,
+        expected: &'static str,
+    },
+    /// The binary container is cut short or put together wrong
+    Glb(&'static str),
+    /// A buffer in a separate file that wasn't provided
+    MissingBuffer(String),
+    /// Something the file uses that isn't supported, e.g. sparse accessors
+    Unsupported(String),
+    /// An accessor that reads past the end of its buffer view
+    OutOfBounds(usize),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Json(err) => write!(f, "{}", err),
+            GltfError::Invalid { path, expected } => {
+                write!(f, "expected {} at {}", expected, path)
+            }
+            GltfError::Glb(message) => write!(f, "invalid GLB: {}", message),
+            GltfError::MissingBuffer(uri) => write!(f, "the buffer {:?} wasn't provided", uri),
+            GltfError::Unsupported(feature) => write!(f, "{} aren't supported", feature),
+            GltfError::OutOfBounds(accessor) => {
+                write!(f, "accessor {} reads past the end of its data", accessor)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl From<JsonError> for GltfError {
+    fn from(err: JsonError) -> Self {
+        GltfError::Json(err)
+    }
+}
+
+/// A loaded glTF file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gltf {
+    pub nodes: Vec<Node>,
+    /// The nodes at the top of the scene that's shown first
+    pub roots: Vec<usize>,
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub images: Vec<Image>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: Option<String>,
+    /// Indices into `Gltf::nodes`
+    pub children: Vec<usize>,
+    /// Relative to the parent node
+    pub transform: Matrix4<f64>,
+    pub mesh: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+}
+
+/// A part of a mesh with a single material
+#[derive(Debug, Clone, PartialEq)]
+pub struct Primitive {
+    pub positions: Vec<Point3<f64>>,
+    pub normals: Option<Vec<Vector3<f64>>>,
+    /// With (0, 0) at the bottom left of the image, like OBJ files (glTF's is at the top left)
+    pub uvs: Option<Vec<Vector2<f64>>>,
+    /// Indices into the vertex attributes, counterclockwise from the front
+    pub triangles: Vec<[usize; 3]>,
+    /// An index into `Gltf::materials`
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub name: Option<String>,
+    pub mime_type: Option<String>,
+    pub source: ImageSource,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageSource {
+    /// A separate file, relative to the glTF file
+    Uri(String),
+    /// Stored in the file, as PNG or JPEG data
+    Bytes(Vec<u8>),
+}
+
+impl Gltf {
+    /// Loads a .gltf or .glb file whose buffers are all inside it
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GltfError> {
+        Self::load(bytes, |_| None)
+    }
+
+    /// Loads a .gltf or .glb file,
+    /// with `load_uri` called with the URI of each buffer that's in a separate file
+    pub fn load(
+        bytes: &[u8],
+        mut load_uri: impl FnMut(&str) -> Option<Vec<u8>>,
+    ) -> Result<Self, GltfError> {
+        let (json, glb_buffer) = if read_u32(bytes, 0) == Some(GLB_MAGIC) {
+            split_glb(bytes)?
+        } else {
+            (bytes, None)
+        };
+        let text = std::str::from_utf8(json).map_err(|_| GltfError::Invalid {
+            path: String::new(),
+            expected: "UTF-8 JSON",
+        })?;
+        let json = Json::parse(text)?;
+
+        let buffers = array(&json, "buffers")?
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| {
+                let path = format!("buffers[{}]", i);
+                match optional(buffer, "uri", &path, string)? {
+                    Some(uri) => match uri.strip_prefix("data:") {
+                        Some(data) => decode_data_uri(data, &path),
+                        None => load_uri(uri).ok_or_else(|| GltfError::MissingBuffer(uri.into())),
+                    },
+                    // Only the first buffer in a GLB file can be left without a URI
+                    None if i == 0 => glb_buffer
+                        .map(<[u8]>::to_vec)
+                        .ok_or(GltfError::Glb("there is no binary chunk")),
+                    None => Err(invalid(&path, "a uri")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let document = Document {
+            json: &json,
+            buffers,
+        };
+
+        let nodes = array(&json, "nodes")?
+            .iter()
+            .enumerate()
+            .map(|(i, node)| read_node(node, &format!("nodes[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let meshes = array(&json, "meshes")?
+            .iter()
+            .enumerate()
+            .map(|(i, mesh)| document.mesh(mesh, &format!("meshes[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let materials = array(&json, "materials")?
+            .iter()
+            .enumerate()
+            .map(|(i, material)| document.material(material, &format!("materials[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let images = array(&json, "images")?
+            .iter()
+            .enumerate()
+            .map(|(i, image)| document.image(image, &format!("images[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let roots = match optional(&json, "scene", "", index)? {
+            Some(scene) => Some(scene),
+            None if !array(&json, "scenes")?.is_empty() => Some(0),
+            None => None,
+        };
+        let roots = match roots {
+            Some(scene) => {
+                let path = format!("scenes[{}]", scene);
+                let scene = array(&json, "scenes")?
+                    .get(scene)
+                    .ok_or_else(|| invalid("scene", "the index of a scene"))?;
+                optional(scene, "nodes", &path, indices)?.unwrap_or_default()
+            }
+            // Without any scenes, every node that isn't under another one is at the top
+            None => (0..nodes.len())
+                .filter(|&i| !nodes.iter().any(|node| node.children.contains(&i)))
+                .collect(),
+        };
+
+        let gltf = Self {
+            nodes,
+            roots,
+            meshes,
+            materials,
+            images,
+        };
+        gltf.check_references()?;
+        Ok(gltf)
+    }
+
+    /// Writes a GLB file, with all of the vertex data in its binary chunk.
+    /// Numbers are stored as 32-bit floats, and images (and the textures using them) are left out.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut writer = GlbWriter::default();
+        let meshes: Vec<Json> = self
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let primitives = mesh
+                    .primitives
+                    .iter()
+                    .map(|primitive| writer.primitive(primitive))
+                    .collect();
+                Json::object([
+                    ("name", mesh.name.as_deref().map(Json::from)),
+                    ("primitives", Some(Json::Array(primitives))),
+                ])
+            })
+            .collect();
+        let nodes: Vec<Json> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                Json::object([
+                    ("name", node.name.as_deref().map(Json::from)),
+                    (
+                        "children",
+                        (!node.children.is_empty()).then(|| Json::from(node.children.clone())),
+                    ),
+                    (
+                        "matrix",
+                        (node.transform != Matrix4::identity())
+                            .then(|| Json::from(node.transform.as_slice().to_vec())),
+                    ),
+                    ("mesh", node.mesh.map(Json::from)),
+                ])
+            })
+            .collect();
+        let materials: Vec<Json> = self
+            .materials
+            .iter()
+            .map(|material| {
+                Json::object([
+                    ("name", material.name.as_deref().map(Json::from)),
+                    (
+                        "pbrMetallicRoughness",
+                        Some(Json::object([
+                            ("baseColorFactor", Some(Json::from(material.base_color))),
+                            ("metallicFactor", Some(Json::from(material.metallic))),
+                            ("roughnessFactor", Some(Json::from(material.roughness))),
+                        ])),
+                    ),
+                    (
+                        "doubleSided",
+                        material.double_sided.then(|| Json::from(true)),
+                    ),
+                ])
+            })
+            .collect();
+        let non_empty = |values: Vec<Json>| (!values.is_empty()).then_some(Json::Array(values));
+        let buffer_length = writer.buffer.len();
+        let json = Json::object([
+            (
+                "asset",
+                Some(Json::object([
+                    ("version", Some(Json::from("2.0"))),
+                    ("generator", Some(Json::from("graphics-core"))),
+                ])),
+            ),
+            ("scene", Some(Json::from(0))),
+            (
+                "scenes",
+                Some(Json::Array(vec![Json::object([(
+                    "nodes",
+                    Some(Json::from(self.roots.clone())),
+                )])])),
+            ),
+            ("nodes", non_empty(nodes)),
+            ("meshes", non_empty(meshes)),
+            ("materials", non_empty(materials)),
+            ("accessors", non_empty(writer.accessors)),
+            ("bufferViews", non_empty(writer.buffer_views)),
+            (
+                "buffers",
+                (buffer_length > 0).then(|| {
+                    Json::Array(vec![Json::object([(
+                        "byteLength",
+                        Some(Json::from(buffer_length)),
+                    )])])
+                }),
+            ),
+        ]);
+
+        // Chunks have to be padded to 4 bytes, JSON with spaces and binary data with zeros
+        let mut json = json.to_string().into_bytes();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let mut bin = writer.buffer;
+        bin.resize(bin.len().div_ceil(4) * 4, 0);
+        let bin_chunk_length = if bin.is_empty() { 0 } else { 8 + bin.len() };
+        let length = 12 + 8 + json.len() + bin_chunk_length;
+        let mut bytes = Vec::with_capacity(length);
+        for value in [
+            GLB_MAGIC,
+            2,
+            length as u32,
+            json.len() as u32,
+            GLB_JSON_CHUNK,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(json);
+        if !bin.is_empty() {
+            bytes.extend((bin.len() as u32).to_le_bytes());
+            bytes.extend(GLB_BIN_CHUNK.to_le_bytes());
+            bytes.extend(bin);
+        }
+        bytes
+    }
+
+    /// Scales the meshes and the nodes' translations from `source` units into `target` units.
+    /// glTF files are meant to be in metres, but not every exporter sticks to that.
+    pub fn convert_units(&mut self, source: LengthUnit, target: LengthUnit) {
+        let scale = source.scale_to(target);
+        for node in &mut self.nodes {
+            node.transform = scale_transform(&node.transform, scale);
+        }
+        for primitive in self.meshes.iter_mut().flat_map(|mesh| &mut mesh.primitives) {
+            for position in &mut primitive.positions {
+                *position *= scale;
+            }
+        }
+    }
+
+    /// The nodes under `roots`, parents before their children, with the index of each one's parent.
+    /// Nodes reached more than once (which glTF doesn't allow) are only listed the first time.
+    pub fn depth_first(&self) -> Vec<(usize, Option<usize>)> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut order = vec![];
+        let mut stack: Vec<(usize, Option<usize>)> =
+            self.roots.iter().rev().map(|&root| (root, None)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            if std::mem::replace(&mut visited[node], true) {
+                continue;
+            }
+            order.push((node, parent));
+            stack.extend(
+                self.nodes[node]
+                    .children
+                    .iter()
+                    .rev()
+                    .map(|&child| (child, Some(node))),
+            );
+        }
+        order
+    }
+
+    fn check_references(&self) -> Result<(), GltfError> {
+        let node_count = self.nodes.len();
+        if let Some(root) = self.roots.iter().find(|&&root| root >= node_count) {
+            return Err(invalid(&format!("nodes[{}]", root), "a node"));
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.children.iter().any(|&child| child >= node_count) {
+                return Err(invalid(
+                    &format!("nodes[{}].children", i),
+                    "indices of nodes",
+                ));
+            }
+            if node.mesh.is_some_and(|mesh| mesh >= self.meshes.len()) {
+                return Err(invalid(
+                    &format!("nodes[{}].mesh", i),
+                    "the index of a mesh",
+                ));
+            }
+        }
+        for (i, mesh) in self.meshes.iter().enumerate() {
+            for (j, primitive) in mesh.primitives.iter().enumerate() {
+                let path = format!("meshes[{}].primitives[{}]", i, j);
+                if primitive
+                    .material
+                    .is_some_and(|material| material >= self.materials.len())
+                {
+                    return Err(invalid(&(path + ".material"), "the index of a material"));
+                }
+                if primitive
+                    .triangles
+                    .iter()
+                    .flatten()
+                    .any(|&index| index >= primitive.positions.len())
+                {
+                    return Err(invalid(&(path + ".indices"), "indices of vertices"));
+                }
+            }
+        }
+        for (i, material) in self.materials.iter().enumerate() {
+            if material
+                .base_color_texture
+                .is_some_and(|image| image >= self.images.len())
+            {
+                return Err(invalid(
+                    &format!("materials[{}].pbrMetallicRoughness.baseColorTexture", i),
+                    "a texture with an image",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Primitive {
+    /// The triangles the faces break into, each with its own three vertices,
+    /// in the same order as `Face::break_into_triangles`
+    pub fn from_faces(faces: &[Face<f64>], material: Option<usize>) -> Self {
+        let positions: Vec<Point3<f64>> = faces
+            .iter()
+            .flat_map(|face| face.break_into_triangles())
+            .collect();
+        // Faces' normals point the opposite way to glTF's
+        let normals = faces
+            .iter()
+            .flat_map(|face| face.break_into_normal_triangles())
+            .map(|normal| -normal.into_inner())
+            .collect();
+        let uvs = faces
+            .iter()
+            .flat_map(|face| face.break_into_uv_triangles())
+            .collect();
+        let triangles = (0..positions.len() / 3)
+            .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+            .collect();
+        Self {
+            positions,
+            normals: Some(normals),
+            uvs: Some(uvs),
+            triangles,
+            material,
+        }
+    }
+
+    /// One face per triangle, with the vertices' UVs and normals.
+    /// Triangles with no area are skipped, since they have no normal.
+    pub fn faces(&self) -> Vec<Face<f64>> {
+        self.triangles
+            .iter()
+            .filter_map(|&triangle| {
+                let points = triangle.iter().map(|&i| self.positions[i]).collect();
+                let uvs = triangle
+                    .iter()
+                    .map(|&i| self.uvs.as_ref().map_or_else(Vector2::zeros, |uvs| uvs[i]))
+                    .collect();
+                let face = Face::try_new_with_uvs(points, uvs)
+                    .ok()?
+                    .with_point_ids(triangle.to_vec());
+                let normals = match &self.normals {
+                    Some(normals) => normals,
+                    None => return Some(face),
+                };
+                // Faces' normals point the opposite way to glTF's
+                let point_normals = triangle
+                    .iter()
+                    .map(|&i| Unit::try_new(-normals[i], 1e-12).unwrap_or(*face.normal()))
+                    .collect();
+                Some(face.with_point_normals(point_normals))
+            })
+            .collect()
+    }
+}
+
+/// Collects the vertex data for a GLB file's binary chunk, and the accessors that read it
+#[derive(Default)]
+struct GlbWriter {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Json>,
+    accessors: Vec<Json>,
+}
+
+impl GlbWriter {
+    const ARRAY_BUFFER: usize = 34962;
+    const ELEMENT_ARRAY_BUFFER: usize = 34963;
+    const UNSIGNED_INT: usize = 5125;
+    const FLOAT: usize = 5126;
+
+    fn primitive(&mut self, primitive: &Primitive) -> Json {
+        let position = self.floats(
+            primitive
+                .positions
+                .iter()
+                .map(|point| point.coords.as_slice()),
+            "VEC3",
+            true,
+        );
+        let normal = primitive.normals.as_ref().map(|normals| {
+            self.floats(
+                normals.iter().map(|normal| normal.as_slice()),
+                "VEC3",
+                false,
+            )
+        });
+        let uv = primitive.uvs.as_ref().map(|uvs| {
+            let flipped: Vec<[f64; 2]> = uvs.iter().map(|uv| [uv.x, 1.0 - uv.y]).collect();
+            self.floats(flipped.iter().map(|uv| uv.as_slice()), "VEC2", false)
+        });
+        let indices = self.indices(&primitive.triangles);
+        Json::object([
+            (
+                "attributes",
+                Some(Json::object([
+                    ("POSITION", Some(Json::from(position))),
+                    ("NORMAL", normal.map(Json::from)),
+                    ("TEXCOORD_0", uv.map(Json::from)),
+                ])),
+            ),
+            ("indices", Some(Json::from(indices))),
+            ("material", primitive.material.map(Json::from)),
+        ])
+    }
+
+    /// Adds an accessor for vectors of floats, returning its index.
+    /// The spec asks for the bounds of positions.
+    fn floats<'a>(
+        &mut self,
+        elements: impl Iterator<Item = &'a [f64]>,
+        accessor_type: &str,
+        bounds: bool,
+    ) -> usize {
+        let start = self.buffer.len();
+        let mut count = 0;
+        let mut min = vec![];
+        let mut max = vec![];
+        for element in elements {
+            let element: Vec<f32> = element.iter().map(|&value| value as f32).collect();
+            if count == 0 {
+                min = element.clone();
+                max = element.clone();
+            }
+            for (i, &value) in element.iter().enumerate() {
+                min[i] = min[i].min(value);
+                max[i] = max[i].max(value);
+                self.buffer.extend(value.to_le_bytes());
+            }
+            count += 1;
+        }
+        let view = self.buffer_view(start, Self::ARRAY_BUFFER);
+        let to_json =
+            |values: Vec<f32>| Json::from(values.into_iter().map(f64::from).collect::<Vec<_>>());
+        self.accessor(Json::object([
+            ("bufferView", Some(Json::from(view))),
+            ("componentType", Some(Json::from(Self::FLOAT))),
+            ("count", Some(Json::from(count))),
+            ("type", Some(Json::from(accessor_type))),
+            ("min", (bounds && count > 0).then(|| to_json(min))),
+            ("max", (bounds && count > 0).then(|| to_json(max))),
+        ]))
+    }
+
+    fn indices(&mut self, triangles: &[[usize; 3]]) -> usize {
+        let start = self.buffer.len();
+        for &index in triangles.iter().flatten() {
+            self.buffer.extend((index as u32).to_le_bytes());
+        }
+        let view = self.buffer_view(start, Self::ELEMENT_ARRAY_BUFFER);
+        self.accessor(Json::object([
+            ("bufferView", Some(Json::from(view))),
+            ("componentType", Some(Json::from(Self::UNSIGNED_INT))),
+            ("count", Some(Json::from(triangles.len() * 3))),
+            ("type", Some(Json::from("SCALAR"))),
+        ]))
+    }
+
+    /// A view of everything added to the buffer since `start`
+    fn buffer_view(&mut self, start: usize, target: usize) -> usize {
+        self.buffer_views.push(Json::object([
+            ("buffer", Some(Json::from(0))),
+            ("byteOffset", Some(Json::from(start))),
+            ("byteLength", Some(Json::from(self.buffer.len() - start))),
+            ("target", Some(Json::from(target))),
+        ]));
+        self.buffer_views.len() - 1
+    }
+
+    fn accessor(&mut self, accessor: Json) -> usize {
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+}
+
+/// Splits a GLB file into its JSON and its binary chunk (if it has one)
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    if read_u32(bytes, 4) != Some(2) {
+        return Err(GltfError::Glb("only version 2 is supported"));
+    }
+    let length = read_u32(bytes, 8).ok_or(GltfError::Glb("the header is cut short"))? as usize;
+    let bytes = bytes
+        .get(..length)
+        .ok_or(GltfError::Glb("the file is shorter than its header says"))?;
+    let mut chunks = vec![];
+    let mut offset = 12;
+    while offset < bytes.len() {
+        let chunk_length = read_u32(bytes, offset).ok_or(GltfError::Glb("a chunk is cut short"))?;
+        let chunk_type =
+            read_u32(bytes, offset + 4).ok_or(GltfError::Glb("a chunk is cut short"))?;
+        let start = offset + 8;
+        let chunk = bytes
+            .get(start..start + chunk_length as usize)
+            .ok_or(GltfError::Glb("a chunk is longer than the file"))?;
+        chunks.push((chunk_type, chunk));
+        offset = start + chunk_length as usize;
+    }
+    match chunks.as_slice() {
+        [(GLB_JSON_CHUNK, json), rest @ ..] => Ok((
+            json,
+            rest.iter()
+                .find(|(chunk_type, _)| *chunk_type == GLB_BIN_CHUNK)
+                .map(|(_, chunk)| *chunk),
+        )),
+        _ => Err(GltfError::Glb("the first chunk isn't JSON")),
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The data in a data URI, after the `data:`
+fn decode_data_uri(data: &str, path: &str) -> Result<Vec<u8>, GltfError> {
+    let (header, payload) = data
+        .split_once(',')
+        .ok_or_else(|| invalid(path, "a data URI"))?;
+    if !header.ends_with(";base64") {
+        return Err(invalid(path, "a base64 data URI"));
+    }
+    decode_base64(payload).ok_or_else(|| invalid(path, "base64 data"))
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for c in text.bytes().filter(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | u32::from(value);
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// The parsed JSON and the buffers it refers to
+struct Document<'a> {
+    json: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Document<'a> {
+    fn mesh(&self, json: &Json, path: &str) -> Result<Mesh, GltfError> {
+        let primitives = required(json, "primitives", path)?
+            .as_array()
+            .ok_or_else(|| invalid(&join(path, "primitives"), "an array"))?
+            .iter()
+            .enumerate()
+            .map(|(i, primitive)| self.primitive(primitive, &format!("{}.primitives[{}]", path, i)))
+            .collect::<Result<_, _>>()?;
+        Ok(Mesh {
+            name: optional(json, "name", path, string)?.map(String::from),
+            primitives,
+        })
+    }
+
+    fn primitive(&self, json: &Json, path: &str) -> Result<Primitive, GltfError> {
+        let attributes = required(json, "attributes", path)?;
+        let attributes_path = join(path, "attributes");
+        let position = index(
+            required(attributes, "POSITION", &attributes_path)?,
+            &join(&attributes_path, "POSITION"),
+        )?;
+        let positions: Vec<Point3<f64>> = self
+            .accessor(position, "VEC3")?
+            .as_chunks::<3>()
+            .0
+            .iter()
+            .map(|&xyz| Point3::from(xyz))
+            .collect();
+        let normals = optional(attributes, "NORMAL", &attributes_path, index)?
+            .map(|normal| self.accessor(normal, "VEC3"))
+            .transpose()?
+            .map(|values| {
+                values
+                    .as_chunks::<3>()
+                    .0
+                    .iter()
+                    .map(|&xyz| Vector3::from(xyz))
+                    .collect::<Vec<_>>()
+            });
+        let uvs = optional(attributes, "TEXCOORD_0", &attributes_path, index)?
+            .map(|uv| self.accessor(uv, "VEC2"))
+            .transpose()?
+            .map(|values| {
+                values
+                    .as_chunks::<2>()
+                    .0
+                    .iter()
+                    .map(|&[u, v]| Vector2::new(u, 1.0 - v))
+                    .collect::<Vec<_>>()
+            });
+        for (name, count) in [
+            ("NORMAL", normals.as_ref().map(Vec::len)),
+            ("TEXCOORD_0", uvs.as_ref().map(Vec::len)),
+        ] {
+            if count.is_some_and(|count| count != positions.len()) {
+                return Err(invalid(
+                    &join(&attributes_path, name),
+                    "as many values as there are positions",
+                ));
+            }
+        }
+
+        let vertices: Vec<usize> = match optional(json, "indices", path, index)? {
+            Some(indices) => self
+                .accessor(indices, "SCALAR")?
+                .into_iter()
+                .map(|index| index as usize)
+                .collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let triangles = match optional(json, "mode", path, index)?.unwrap_or(4) {
+            4 => vertices.as_chunks::<3>().0.to_vec(),
+            // Every other triangle in a strip is wound the other way, so it's flipped back
+            5 => vertices
+                .windows(3)
+                .enumerate()
+                .map(|(i, triangle)| match i % 2 {
+                    0 => [triangle[0], triangle[1], triangle[2]],
+                    _ => [triangle[1], triangle[0], triangle[2]],
+                })
+                .collect(),
+            6 => vertices
+                .windows(2)
+                .skip(1)
+                .map(|pair| [vertices[0], pair[0], pair[1]])
+                .collect(),
+            mode => {
+                return Err(GltfError::Unsupported(format!(
+                    "primitives with mode {} (points and lines)",
+                    mode
+                )))
+            }
+        };
+        Ok(Primitive {
+            positions,
+            normals,
+            uvs,
+            triangles,
+            material: optional(json, "material", path, index)?,
+        })
+    }
+
+    fn material(&self, json: &Json, path: &str) -> Result<Material, GltfError> {
+        let defaults = Material::default();
+        let pbr = json.get("pbrMetallicRoughness").unwrap_or(&Json::Null);
+        let pbr_path = join(path, "pbrMetallicRoughness");
+        let base_color_texture =
+            match optional(pbr, "baseColorTexture", &pbr_path, |json, path| {
+                index(required(json, "index", path)?, &join(path, "index"))
+            })? {
+                Some(texture) => {
+                    let path = format!("textures[{}]", texture);
+                    let texture = array(self.json, "textures")?
+                        .get(texture)
+                        .ok_or_else(|| invalid(&path, "a texture"))?;
+                    optional(texture, "source", &path, index)?
+                }
+                None => None,
+            };
+        Ok(Material {
+            name: optional(json, "name", path, string)?.map(String::from),
+            base_color: optional(pbr, "baseColorFactor", &pbr_path, |json, path| {
+                json.as_f64_array()
+                    .ok_or_else(|| invalid(path, "4 numbers"))
+            })?
+            .unwrap_or(defaults.base_color),
+            metallic: optional(pbr, "metallicFactor", &pbr_path, number)?
+                .unwrap_or(defaults.metallic),
+            roughness: optional(pbr, "roughnessFactor", &pbr_path, number)?
+                .unwrap_or(defaults.roughness),
+            base_color_texture,
+            double_sided: optional(json, "doubleSided", path, |json, path| {
+                json.as_bool().ok_or_else(|| invalid(path, "true or false"))
+            })?
+            .unwrap_or(defaults.double_sided),
+        })
+    }
+
+    fn image(&self, json: &Json, path: &str) -> Result<Image, GltfError> {
+        let source = match (
+            optional(json, "uri", path, string)?,
+            optional(json, "bufferView", path, index)?,
+        ) {
+            (Some(uri), _) => match uri.strip_prefix("data:") {
+                Some(data) => ImageSource::Bytes(decode_data_uri(data, &join(path, "uri"))?),
+                None => ImageSource::Uri(uri.to_string()),
+            },
+            (None, Some(view)) => ImageSource::Bytes(self.buffer_view(view)?.0.to_vec()),
+            (None, None) => return Err(invalid(path, "a uri or a bufferView")),
+        };
+        Ok(Image {
+            name: optional(json, "name", path, string)?.map(String::from),
+            mime_type: optional(json, "mimeType", path, string)?.map(String::from),
+            source,
+        })
+    }
+
+    /// The bytes in a buffer view, and its stride (if it has one)
+    fn buffer_view(&self, view: usize) -> Result<(&[u8], Option<usize>), GltfError> {
+        let path = format!("bufferViews[{}]", view);
+        let json = array(self.json, "bufferViews")?
+            .get(view)
+            .ok_or_else(|| invalid(&path, "a buffer view"))?;
+        let buffer = index(required(json, "buffer", &path)?, &join(&path, "buffer"))?;
+        let buffer = self
+            .buffers
+            .get(buffer)
+            .ok_or_else(|| invalid(&join(&path, "buffer"), "the index of a buffer"))?;
+        let start = optional(json, "byteOffset", &path, index)?.unwrap_or(0);
+        let length = index(
+            required(json, "byteLength", &path)?,
+            &join(&path, "byteLength"),
+        )?;
+        let bytes = start
+            .checked_add(length)
+            .and_then(|end| buffer.get(start..end))
+            .ok_or_else(|| invalid(&path, "a range inside its buffer"))?;
+        Ok((bytes, optional(json, "byteStride", &path, index)?))
+    }
+
+    /// Every component of every element an accessor reads, one after the other,
+    /// with normalized integers scaled to between 0 (or -1) and 1
+    fn accessor(
+        &self,
+        accessor: usize,
+        expected_type: &'static str,
+    ) -> Result<Vec<f64>, GltfError> {
+        let path = format!("accessors[{}]", accessor);
+        let json = array(self.json, "accessors")?
+            .get(accessor)
+            .ok_or_else(|| invalid(&path, "an accessor"))?;
+        if json.get("sparse").is_some() {
+            return Err(GltfError::Unsupported("sparse accessors".to_string()));
+        }
+        let accessor_type = string(required(json, "type", &path)?, &join(&path, "type"))?;
+        if accessor_type != expected_type {
+            return Err(invalid(&join(&path, "type"), expected_type));
+        }
+        let components = match accessor_type {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            _ => 3,
+        };
+        let count = index(required(json, "count", &path)?, &join(&path, "count"))?;
+        let component_type = index(
+            required(json, "componentType", &path)?,
+            &join(&path, "componentType"),
+        )?;
+        let normalized = optional(json, "normalized", &path, |json, path| {
+            json.as_bool().ok_or_else(|| invalid(path, "true or false"))
+        })?
+        .unwrap_or(false);
+        let component_size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(invalid(&join(&path, "componentType"), "a component type")),
+        };
+
+        // The count comes straight from the file, so nothing sized by it can be trusted to fit
+        let value_count = count
+            .checked_mul(components)
+            .ok_or(GltfError::OutOfBounds(accessor))?;
+        let view = match optional(json, "bufferView", &path, index)? {
+            Some(view) => view,
+            // Accessors without a buffer view are all zeros
+            None if value_count <= MAX_ZEROED_VALUES => return Ok(vec![0.0; value_count]),
+            None => return Err(GltfError::OutOfBounds(accessor)),
+        };
+        let (data, stride) = self.buffer_view(view)?;
+        let element_size = component_size * components;
+        let stride = stride.unwrap_or(element_size);
+        let offset = optional(json, "byteOffset", &path, index)?.unwrap_or(0);
+        if count > 0 {
+            let end = (count - 1)
+                .checked_mul(stride)
+                .and_then(|last_element| last_element.checked_add(offset))
+                .and_then(|last_element| last_element.checked_add(element_size));
+            if !end.is_some_and(|end| end <= data.len()) {
+                return Err(GltfError::OutOfBounds(accessor));
+            }
+        }
+
+        let mut values = Vec::with_capacity(value_count);
+        for element in 0..count {
+            for component in 0..components {
+                let start = offset + element * stride + component * component_size;
+                let bytes = &data[start..start + component_size];
+                let value = match component_type {
+                    5120 => f64::from(bytes[0] as i8),
+                    5121 => f64::from(bytes[0]),
+                    5122 => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+                    5123 => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+                    5125 => f64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+                    _ => f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+                };
+                values.push(match (normalized, component_type) {
+                    (true, 5120) => (value / 127.0).max(-1.0),
+                    (true, 5121) => value / 255.0,
+                    (true, 5122) => (value / 32767.0).max(-1.0),
+                    (true, 5123) => value / 65535.0,
+                    _ => value,
+                });
+            }
+        }
+        Ok(values)
+    }
+}
+
+fn read_node(json: &Json, path: &str) -> Result<Node, GltfError> {
+    let transform = match optional(json, "matrix", path, |json, path| {
+        json.as_f64_array::<16>()
+            .map(|values| Matrix4::from_column_slice(&values))
+            .ok_or_else(|| invalid(path, "16 numbers"))
+    })? {
+        Some(matrix) => matrix,
+        None => {
+            let translation =
+                optional(json, "translation", path, vector)?.unwrap_or_else(Vector3::zeros);
+            let rotation = match optional(json, "rotation", path, |json, path| {
+                json.as_f64_array::<4>()
+                    .ok_or_else(|| invalid(path, "4 numbers (x, y, z, w)"))
+            })? {
+                Some([x, y, z, w]) => UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)),
+                None => UnitQuaternion::identity(),
+            };
+            let scale =
+                optional(json, "scale", path, vector)?.unwrap_or_else(|| Vector3::repeat(1.0));
+            Translation3::from(translation).to_homogeneous()
+                * rotation.to_homogeneous()
+                * Matrix4::new_nonuniform_scaling(&scale)
+        }
+    };
+    Ok(Node {
+        name: optional(json, "name", path, string)?.map(String::from),
+        children: optional(json, "children", path, indices)?.unwrap_or_default(),
+        transform,
+        mesh: optional(json, "mesh", path, index)?,
+    })
+}
+
+fn invalid(path: &str, expected: &'static str) -> GltfError {
+    GltfError::Invalid {
+        path: path.to_string(),
+        expected,
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn required<'a>(json: &'a Json, key: &str, path: &str) -> Result<&'a Json, GltfError> {
+    json.get(key)
+        .ok_or_else(|| invalid(&join(path, key), "a value"))
+}
+
+fn optional<'a, T>(
+    json: &'a Json,
+    key: &str,
+    path: &str,
+    read: impl Fn(&'a Json, &str) -> Result<T, GltfError>,
+) -> Result<Option<T>, GltfError> {
+    match json.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(value) => read(value, &join(path, key)).map(Some),
+    }
+}
+
+/// A top-level array, which is empty if it's left out
+fn array<'a>(json: &'a Json, key: &str) -> Result<&'a [Json], GltfError> {
+    Ok(optional(json, key, "", |json, path| {
+        json.as_array().ok_or_else(|| invalid(path, "an array"))
+    })?
+    .unwrap_or_default())
+}
+
+fn index(json: &Json, path: &str) -> Result<usize, GltfError> {
+    json.as_usize().ok_or_else(|| invalid(path, "an index"))
+}
+
+fn indices(json: &Json, path: &str) -> Result<Vec<usize>, GltfError> {
+    json.as_array()
+        .and_then(|values| values.iter().map(Json::as_usize).collect())
+        .ok_or_else(|| invalid(path, "an array of indices"))
+}
+
+fn number(json: &Json, path: &str) -> Result<f64, GltfError> {
+    json.as_f64().ok_or_else(|| invalid(path, "a number"))
+}
+
+fn string<'a>(json: &'a Json, path: &str) -> Result<&'a str, GltfError> {
+    json.as_str().ok_or_else(|| invalid(path, "a string"))
+}
+
+fn vector(json: &Json, path: &str) -> Result<Vector3<f64>, GltfError> {
+    json.as_f64_array()
+        .map(Vector3::from)
+        .ok_or_else(|| invalid(path, "3 numbers"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{point, vector};
+
+    /// A GLB file with the JSON and binary chunks, padded to 4 bytes like the spec asks
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().div_ceil(4) * 4, 0);
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut bytes = vec![];
+        for value in [
+            GLB_MAGIC,
+            2,
+            length as u32,
+            json.len() as u32,
+            GLB_JSON_CHUNK,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(json);
+        bytes.extend((bin.len() as u32).to_le_bytes());
+        bytes.extend(GLB_BIN_CHUNK.to_le_bytes());
+        bytes.extend(bin);
+        bytes
+    }
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// A square made of two triangles, facing +z, under a node that moves it up
+    fn square_glb() -> Vec<u8> {
+        let mut bin = f32_bytes(&[
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, // positions
+            0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, // normals
+            0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, // UVs
+        ]);
+        bin.extend(
+            [0u16, 1, 2, 0, 2, 3]
+                .iter()
+                .flat_map(|index| index.to_le_bytes()),
+        );
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [
+                { "name": "stand", "translation": [0, 1, 0], "children": [1] },
+                { "name": "square", "mesh": 0, "rotation": [0, 0, 0.7071068, 0.7071068], "scale": [2, 2, 2] }
+            ],
+            "meshes": [{
+                "name": "square",
+                "primitives": [{
+                    "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2 },
+                    "indices": 3,
+                    "material": 0
+                }]
+            }],
+            "materials": [{
+                "name": "red",
+                "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0 }
+            }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3" },
+                { "bufferView": 0, "byteOffset": 48, "componentType": 5126, "count": 4, "type": "VEC3" },
+                { "bufferView": 1, "componentType": 5126, "count": 4, "type": "VEC2" },
+                { "bufferView": 2, "componentType": 5123, "count": 6, "type": "SCALAR" }
+            ],
+            "bufferViews": [
+                { "buffer": 0, "byteLength": 96 },
+                { "buffer": 0, "byteOffset": 96, "byteLength": 32 },
+                { "buffer": 0, "byteOffset": 128, "byteLength": 12 }
+            ],
+            "buffers": [{ "byteLength": 140 }]
+        }"#;
+        glb(json, &bin)
+    }
+
+    #[test]
+    fn test_glb() {
+        let gltf = Gltf::from_bytes(&square_glb()).unwrap();
+        assert_eq!(gltf.roots, vec![0]);
+        assert_eq!(gltf.depth_first(), vec![(0, None), (1, Some(0))]);
+        assert_eq!(gltf.nodes[0].name.as_deref(), Some("stand"));
+        let transform = gltf.nodes[0].transform * gltf.nodes[1].transform;
+        let corner = transform.transform_point(&point![1.0, 0.0, 0.0]);
+        assert!((corner - point![0.0, 3.0, 0.0]).norm() < 1e-6);
+
+        let primitive = &gltf.meshes[0].primitives[0];
+        assert_eq!(primitive.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        // Flipped so the bottom left of the image is (0, 0)
+        assert_eq!(primitive.uvs.as_ref().unwrap()[0], vector![0.0, 0.0]);
+        assert_eq!(primitive.uvs.as_ref().unwrap()[2], vector![1.0, 1.0]);
+        assert_eq!(gltf.materials[0].base_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(gltf.materials[0].metallic, 0.0);
+        assert_eq!(gltf.materials[0].roughness, 1.0);
+        assert_eq!(primitive.material, Some(0));
+
+        let faces = primitive.faces();
+        assert_eq!(faces.len(), 2);
+        // Faces point away from the way glTF winds them, and so do their point normals
+        assert!((faces[0].normal().into_inner() - vector![0.0, 0.0, -1.0]).norm() < 1e-9);
+        assert_eq!(
+            faces[0].break_into_normal_triangles()[0].into_inner(),
+            vector![0.0, 0.0, -1.0]
+        );
+        assert_eq!(faces[1].point_ids(), Some(&vec![0, 2, 3]));
+    }
+
+    #[test]
+    fn test_to_glb() {
+        let gltf = Gltf::from_bytes(&square_glb()).unwrap();
+        let faces = gltf.meshes[0].primitives[0].faces();
+        let mut exported = gltf.clone();
+        exported.meshes[0].primitives[0] = Primitive::from_faces(&faces, Some(0));
+        let glb = exported.to_glb();
+        assert_eq!(glb.len() % 4, 0);
+        let reimported = Gltf::from_bytes(&glb).unwrap();
+        assert_eq!(reimported.roots, gltf.roots);
+        assert_eq!(reimported.materials, gltf.materials);
+        for (node, original) in reimported.nodes.iter().zip(&gltf.nodes) {
+            assert_eq!(node.name, original.name);
+            assert_eq!(node.children, original.children);
+            assert!((node.transform - original.transform).norm() < 1e-6);
+        }
+
+        // Each triangle gets its own vertices, which match the faces' buffers
+        let primitive = &reimported.meshes[0].primitives[0];
+        assert_eq!(primitive.triangles, vec![[0, 1, 2], [3, 4, 5]]);
+        let reimported_faces = primitive.faces();
+        let points = |faces: &[Face<f64>]| -> Vec<Point3<f64>> {
+            faces
+                .iter()
+                .flat_map(|face| face.break_into_triangles())
+                .collect()
+        };
+        let normals = |faces: &[Face<f64>]| -> Vec<Unit<Vector3<f64>>> {
+            faces
+                .iter()
+                .flat_map(|face| face.break_into_normal_triangles())
+                .collect()
+        };
+        let uvs = |faces: &[Face<f64>]| -> Vec<Vector2<f64>> {
+            faces
+                .iter()
+                .flat_map(|face| face.break_into_uv_triangles())
+                .collect()
+        };
+        assert_eq!(points(&reimported_faces), points(&faces));
+        assert_eq!(normals(&reimported_faces), normals(&faces));
+        assert_eq!(uvs(&reimported_faces), uvs(&faces));
+    }
+
+    #[test]
+    fn test_data_uri() {
+        assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64("TQ==").unwrap(), b"M");
+        assert_eq!(decode_base64("T!"), None);
+
+        // One triangle, drawn as a fan without indices, with its buffer inline as base64
+        // (0, 0, 0), (1, 0, 0) and (0, 1, 0) as 32-bit floats
+        let json = r#"{
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "mode": 6 }] }],
+            "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+            "buffers": [{
+                "byteLength": 36,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+            }]
+        }"#;
+        let gltf = Gltf::from_bytes(json.as_bytes()).unwrap();
+        // Without any scenes, every node at the top is a root
+        assert_eq!(gltf.roots, vec![0]);
+        let primitive = &gltf.meshes[0].primitives[0];
+        assert_eq!(primitive.positions[1], point![1.0, 0.0, 0.0]);
+        assert_eq!(primitive.triangles, vec![[0, 1, 2]]);
+        assert_eq!(primitive.normals, None);
+        assert_eq!(primitive.faces().len(), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut glb = square_glb();
+        // Chops the end off the binary chunk
+        let length = glb.len() - 8;
+        glb[8..12].copy_from_slice(&(length as u32).to_le_bytes());
+        glb.truncate(length);
+        assert_eq!(
+            Gltf::from_bytes(&glb),
+            Err(GltfError::Glb("a chunk is longer than the file"))
+        );
+
+        let external = r#"{ "buffers": [{ "byteLength": 4, "uri": "square.bin" }] }"#;
+        assert_eq!(
+            Gltf::from_bytes(external.as_bytes()),
+            Err(GltfError::MissingBuffer("square.bin".to_string()))
+        );
+        assert!(Gltf::load(external.as_bytes(), |uri| (uri == "square.bin")
+            .then(|| vec![0; 4]))
+        .is_ok());
+
+        let out_of_bounds = r#"{
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+            "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3" }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 12 }],
+            "buffers": [{ "byteLength": 12, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAA" }]
+        }"#;
+        assert_eq!(
+            Gltf::from_bytes(out_of_bounds.as_bytes()),
+            Err(GltfError::OutOfBounds(0))
+        );
+
+        // Sizes big enough to overflow, or to run out of memory if they were believed
+        let accessor_file = |accessor: &str| {
+            format!(
+                r#"{{
+                    "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+                    "accessors": [{}],
+                    "bufferViews": [{{ "buffer": 0, "byteLength": 12 }}],
+                    "buffers": [{{ "byteLength": 12, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAA" }}]
+                }}"#,
+                accessor
+            )
+        };
+        for accessor in [
+            r#"{ "componentType": 5126, "count": 1e15, "type": "VEC3" }"#,
+            r#"{ "componentType": 5126, "count": 1e19, "type": "VEC3" }"#,
+            r#"{ "bufferView": 0, "componentType": 5126, "count": 1e19, "type": "VEC3" }"#,
+            r#"{ "bufferView": 0, "byteOffset": 1.8446744073709550e19, "componentType": 5126, "count": 1, "type": "VEC3" }"#,
+        ] {
+            assert_eq!(
+                Gltf::from_bytes(accessor_file(accessor).as_bytes()),
+                Err(GltfError::OutOfBounds(0)),
+                "{}",
+                accessor
+            );
+        }
+        let huge_view = accessor_file(
+            r#"{ "bufferView": 0, "componentType": 5126, "count": 1, "type": "VEC3" }"#,
+        )
+        .replace(
+            r#""byteLength": 12 }"#,
+            r#""byteOffset": 1.8446744073709550e19, "byteLength": 1.8446744073709550e19 }"#,
+        );
+        assert_eq!(
+            Gltf::from_bytes(huge_view.as_bytes()),
+            Err(invalid("bufferViews[0]", "a range inside its buffer"))
+        );
+
+        let bad_child = r#"{ "nodes": [{ "children": [3] }] }"#;
+        assert_eq!(
+            Gltf::from_bytes(bad_child.as_bytes()),
+            Err(invalid("nodes[0].children", "indices of nodes"))
+        );
+    }
+}
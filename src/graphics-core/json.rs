@@ -0,0 +1,633 @@
+//! A small JSON reader and writer, for scene files and glTF
+
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keeps the keys in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    UnexpectedEnd,
+    /// The byte offset and the character found there
+    UnexpectedCharacter(usize, char),
+    InvalidNumber(usize),
+    InvalidEscape(usize),
+    /// Arrays and objects nested deeper than `MAX_DEPTH`, at the byte offset of the one too many
+    TooDeep(usize),
+}
+
+/// How many arrays and objects can be inside each other,
+/// which keeps a malicious or corrupt file from overflowing the stack
+pub const MAX_DEPTH: usize = 128;
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::UnexpectedEnd => write!(f, "unexpected end of JSON"),
+            JsonError::UnexpectedCharacter(position, found) => {
+                write!(f, "unexpected {:?} at byte {} of JSON", found, position)
+            }
+            JsonError::InvalidNumber(position) => {
+                write!(f, "invalid number at byte {} of JSON", position)
+            }
+            JsonError::InvalidEscape(position) => {
+                write!(f, "invalid escape at byte {} of JSON", position)
+            }
+            JsonError::TooDeep(position) => write!(
+                f,
+                "more than {} levels of nesting at byte {} of JSON",
+                MAX_DEPTH, position
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            text,
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            Some(c) => Err(JsonError::UnexpectedCharacter(parser.position, c)),
+            None => Ok(value),
+        }
+    }
+
+    /// The value under a key, if this is an object with that key
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Only for whole, non-negative numbers
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(value) if *value >= 0.0 && value.fract() == 0.0 => Some(*value as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self {
+            Json::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// An array of exactly `N` numbers
+    pub fn as_f64_array<const N: usize>(&self) -> Option<[f64; N]> {
+        let values = self.as_array()?;
+        if values.len() != N {
+            return None;
+        }
+        let mut numbers = [0.0; N];
+        for (number, value) in numbers.iter_mut().zip(values) {
+            *number = value.as_f64()?;
+        }
+        Some(numbers)
+    }
+
+    /// Builds an object from key-value pairs, leaving out any `None` values
+    pub fn object<'a>(entries: impl IntoIterator<Item = (&'a str, Option<Json>)>) -> Json {
+        Json::Object(
+            entries
+                .into_iter()
+                .filter_map(|(key, value)| Some((key.to_string(), value?)))
+                .collect(),
+        )
+    }
+
+    /// Indented two spaces per level, with arrays of numbers (or other plain values) kept on one line
+    pub fn to_string_pretty(&self) -> String {
+        let mut text = String::new();
+        self.write_pretty(&mut text, 0).unwrap();
+        text.push('\n');
+        text
+    }
+
+    fn write_pretty(&self, text: &mut String, indent: usize) -> fmt::Result {
+        let is_plain = |value: &Json| !matches!(value, Json::Array(_) | Json::Object(_));
+        match self {
+            Json::Array(values) if !values.is_empty() && !values.iter().all(is_plain) => {
+                text.push_str("[\n");
+                for (i, value) in values.iter().enumerate() {
+                    write!(text, "{:1$}", "", indent + 2)?;
+                    value.write_pretty(text, indent + 2)?;
+                    text.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                write!(text, "{:1$}]", "", indent)
+            }
+            Json::Object(entries) if !entries.is_empty() => {
+                text.push_str("{\n");
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write!(text, "{:1$}", "", indent + 2)?;
+                    write_string(text, key)?;
+                    text.push_str(": ");
+                    value.write_pretty(text, indent + 2)?;
+                    text.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+                }
+                write!(text, "{:1$}}}", "", indent)
+            }
+            Json::Array(values) => {
+                text.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        text.push_str(", ");
+                    }
+                    write!(text, "{}", value)?;
+                }
+                text.push(']');
+                Ok(())
+            }
+            _ => write!(text, "{}", self),
+        }
+    }
+}
+
+/// Compact, with no whitespace
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            // JSON has no infinities or NaN
+            Json::Number(value) if !value.is_finite() => f.write_str("null"),
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+                write!(f, "{}", value)
+            }
+            Json::Number(value) => write!(f, "{:?}", value),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(entries) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut impl Write, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Self {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Json>, const N: usize> From<[T; N]> for Json {
+    fn from(values: [T; N]) -> Self {
+        Json::Array(IntoIterator::into_iter(values).map(Into::into).collect())
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    /// In bytes
+    position: usize,
+    /// How many arrays and objects the parser is currently inside
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Result<char, JsonError> {
+        let c = self.peek().ok_or(JsonError::UnexpectedEnd)?;
+        self.position += c.len_utf8();
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        let position = self.position;
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(JsonError::UnexpectedCharacter(position, c)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek().ok_or(JsonError::UnexpectedEnd)? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => Ok(Json::String(self.string()?)),
+            '[' => self.nested(Self::array),
+            '{' => self.nested(Self::object),
+            '-' | '0'..='9' => self.number(),
+            c => Err(JsonError::UnexpectedCharacter(self.position, c)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in keyword.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    /// Rust's float parsing accepts a few things JSON doesn't, like "1.", ".5" and "01",
+    /// so the number is checked against JSON's grammar on the way through:
+    /// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.position;
+        let invalid = JsonError::InvalidNumber(start);
+        self.skip_char('-');
+        match self.peek() {
+            Some('0') => self.position += 1,
+            Some('1'..='9') => {
+                self.digits();
+            }
+            _ => return Err(invalid),
+        }
+        if self.skip_char('.') && self.digits() == 0 {
+            return Err(invalid);
+        }
+        if self.skip_char('e') || self.skip_char('E') {
+            let _ = self.skip_char('+') || self.skip_char('-');
+            if self.digits() == 0 {
+                return Err(invalid);
+            }
+        }
+        // Anything number-like left over means something like "01" or "1.2.3"
+        if self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+        {
+            return Err(invalid);
+        }
+        self.text[start..self.position]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| invalid)
+    }
+
+    /// Moves past the character if it's next, returning whether it was
+    fn skip_char(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// Moves past a run of digits, returning how many there were
+    fn digits(&mut self) -> usize {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            let position = self.position;
+            match self.next()? {
+                '"' => return Ok(value),
+                '\\' => {
+                    let c = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let high = self.hex_escape(position)?;
+                            if (0xd800..0xdc00).contains(&high) {
+                                // The first half of a surrogate pair, the second half has to follow
+                                self.expect('\\')?;
+                                self.expect('u')?;
+                                let low = self.hex_escape(position)?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(JsonError::InvalidEscape(position));
+                                }
+                                let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                                char::from_u32(code).ok_or(JsonError::InvalidEscape(position))?
+                            } else {
+                                char::from_u32(high).ok_or(JsonError::InvalidEscape(position))?
+                            }
+                        }
+                        _ => return Err(JsonError::InvalidEscape(position)),
+                    };
+                    value.push(c);
+                }
+                c if (c as u32) < 0x20 => return Err(JsonError::UnexpectedCharacter(position, c)),
+                c => value.push(c),
+            }
+        }
+    }
+
+    /// The four hex digits after `\u`
+    fn hex_escape(&mut self, escape_position: usize) -> Result<u32, JsonError> {
+        let digits = self
+            .text
+            .get(self.position..self.position + 4)
+            .ok_or(JsonError::UnexpectedEnd)?;
+        let code = u32::from_str_radix(digits, 16)
+            .map_err(|_| JsonError::InvalidEscape(escape_position))?;
+        self.position += 4;
+        Ok(code)
+    }
+
+    /// Parses an array or object, keeping track of how deep they're nested
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<Json, JsonError>,
+    ) -> Result<Json, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(JsonError::TooDeep(self.position));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            let position = self.position;
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(Json::Array(values)),
+                c => return Err(JsonError::UnexpectedCharacter(position, c)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut entries = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            let position = self.position;
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Json::Object(entries)),
+                c => return Err(JsonError::UnexpectedCharacter(position, c)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let json = Json::parse(
+            r#" {"name": "arm \"1\"é😀", "size": [1, -2.5, 3e2],
+                 "joint": {"limited": true, "min": null}, "empty": [], "none": {}} "#,
+        )
+        .unwrap();
+        assert_eq!(json.get("name").unwrap().as_str(), Some("arm \"1\"é😀"));
+        assert_eq!(
+            json.get("size").unwrap().as_f64_array(),
+            Some([1.0, -2.5, 300.0])
+        );
+        assert_eq!(json.get("size").unwrap().as_f64_array::<2>(), None);
+        let joint = json.get("joint").unwrap();
+        assert_eq!(joint.get("limited").unwrap().as_bool(), Some(true));
+        assert!(joint.get("min").unwrap().is_null());
+        assert_eq!(json.get("empty"), Some(&Json::Array(vec![])));
+        assert_eq!(json.get("none"), Some(&Json::Object(vec![])));
+        assert_eq!(json.get("missing"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Json::parse(""), Err(JsonError::UnexpectedEnd));
+        assert_eq!(Json::parse("[1, 2"), Err(JsonError::UnexpectedEnd));
+        assert_eq!(
+            Json::parse("[1 2]"),
+            Err(JsonError::UnexpectedCharacter(3, '2'))
+        );
+        assert_eq!(
+            Json::parse("{} x"),
+            Err(JsonError::UnexpectedCharacter(3, 'x'))
+        );
+        assert_eq!(Json::parse("1."), Err(JsonError::InvalidNumber(0)));
+        assert_eq!(Json::parse("-.5"), Err(JsonError::InvalidNumber(0)));
+        assert_eq!(Json::parse(r#""\x""#), Err(JsonError::InvalidEscape(1)));
+        assert_eq!(Json::parse("tru"), Err(JsonError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn test_numbers() {
+        for valid in ["0", "-0", "10", "0.5", "-1.25e-3", "2E+8", "1e05"] {
+            assert_eq!(
+                Json::parse(valid),
+                Ok(Json::Number(valid.parse().unwrap())),
+                "{}",
+                valid
+            );
+        }
+        for invalid in [
+            "01", "-01", "00.5", "1.", "-", "1e", "1e+", "1.2.3", "1-2", "--1",
+        ] {
+            assert_eq!(
+                Json::parse(invalid),
+                Err(JsonError::InvalidNumber(0)),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(Json::parse("[1, 02]"), Err(JsonError::InvalidNumber(4)));
+        assert_eq!(
+            Json::parse("+1"),
+            Err(JsonError::UnexpectedCharacter(0, '+'))
+        );
+    }
+
+    #[test]
+    fn test_depth() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(MAX_DEPTH + 1)),
+            Err(JsonError::TooDeep(MAX_DEPTH))
+        );
+        // Deep enough to overflow the stack without a limit
+        let objects = r#"{"a":"#.repeat(1_000_000);
+        assert_eq!(
+            Json::parse(&objects),
+            Err(JsonError::TooDeep(MAX_DEPTH * 5))
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let json = Json::object([
+            ("name", Some("tab\there".into())),
+            ("skipped", None),
+            ("position", Some([1.0, 0.5, -1e-20].into())),
+            (
+                "children",
+                Some(Json::Array(vec![Json::object([(
+                    "flag",
+                    Some(true.into()),
+                )])])),
+            ),
+            ("infinite", Some(f64::INFINITY.into())),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r#"{"name":"tab\there","position":[1,0.5,-1e-20],"children":[{"flag":true}],"infinite":null}"#
+        );
+        assert_eq!(
+            json.to_string_pretty(),
+            "{\n  \"name\": \"tab\\there\",\n  \"position\": [1, 0.5, -1e-20],\n  \"children\": [\n    {\n      \"flag\": true\n    }\n  ],\n  \"infinite\": null\n}\n"
+        );
+        assert_eq!(
+            Json::parse(&json.to_string_pretty())
+                .unwrap()
+                .get("position"),
+            json.get("position")
+        );
+    }
+}
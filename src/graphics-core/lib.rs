@@ -0,0 +1,73 @@
+//! The pieces shared between the projects: geometry (faces, rays, bounding boxes and BVHs over them),
+//! OBJ and glTF loading (in any units), JSON, the fly-around camera and its fixed-timestep loop,
+//! the JS-facing transforms and keyframe animation, and a software rasterizer for testing what gets drawn.
+pub extern crate nalgebra;
+pub extern crate num_traits;
+pub extern crate parry3d_f64 as parry3d;
+pub extern crate wasm_bindgen;
+pub mod animation;
+pub mod bounding_box;
+pub mod bvh;
+pub mod camera;
+pub mod character_controller;
+pub mod face;
+pub mod fixed_timestep;
+pub mod gltf;
+pub mod json;
+pub mod load_obj;
+pub mod material;
+pub mod png;
+pub mod rasterizer;
+pub mod ray;
+pub mod skybox;
+pub mod transform;
+pub mod transform_matrix;
+pub mod triangulate;
+pub mod units;
+
+use nalgebra::Vector3;
+use wasm_bindgen::prelude::*;
+
+pub trait Number:
+    'static
+    + std::fmt::Debug
+    + Copy
+    + nalgebra::ClosedAdd
+    + nalgebra::ClosedSub
+    + nalgebra::ClosedMul
+    + num_traits::identities::Zero
+    + nalgebra::SimdComplexField
+    + nalgebra::SimdRealField
+    + num_traits::Float
+    + std::convert::Into<f64>
+    + std::convert::From<f32>
+{
+}
+impl Number for f64 {}
+impl Number for f32 {}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = log)]
+    pub fn log(s: String);
+}
+
+#[macro_export]
+macro_rules! console_log {
+    ($($t:tt)*) => {
+        #[cfg(target_arch = "wasm32")] {
+            $crate::log(format!($($t)*));
+        }
+        #[cfg(not(target_arch = "wasm32"))] {
+            println!($($t)*);
+        }
+    };
+}
+
+/// Flattens points into the x, y, z, w layout the vertex shaders read
+pub fn points_to_float32array(points: &[Vector3<f64>]) -> Vec<f32> {
+    points
+        .iter()
+        .flat_map(|point| [point.x as _, point.y as _, point.z as _, 1.0])
+        .collect()
+}
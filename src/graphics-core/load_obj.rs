@@ -0,0 +1,121 @@
+use nalgebra::{Point3, Vector2};
+
+use crate::console_log;
+use crate::face::{Face, UVPair};
+use crate::units::LengthUnit;
+
+pub fn load_obj(obj: &str) -> Vec<Face<f64>> {
+    load_obj_in_units(obj, LengthUnit::Metres, LengthUnit::Metres)
+}
+
+/// Loads an OBJ file whose vertices are in `source` units, scaling them into `target` units
+pub fn load_obj_in_units(obj: &str, source: LengthUnit, target: LengthUnit) -> Vec<Face<f64>> {
+    let scale = source.scale_to(target);
+    let lines = obj.split('\n');
+    let mut vertices: Vec<Point3<f64>> = vec![];
+    let mut faces: Vec<Face<f64>> = vec![];
+    let mut uvs: Vec<Vector2<f64>> = vec![];
+    for (i, line) in lines.enumerate() {
+        let line_num = i + 1;
+        if line.starts_with('#') || line.trim() == "" {
+            continue;
+        };
+        let chunks: Vec<&str> = line.split_whitespace().collect();
+        let command = chunks[0];
+        if command == "mtllib"
+            || command == "o"
+            || command == "vn"
+            || command == "s"
+            || command == "g"
+            || command == "usemtl"
+        {
+            // ignore/no need to handle (at least for now)
+        } else if command == "v" {
+            vertices.push(
+                Point3::new(
+                    chunks[1].parse().unwrap(),
+                    chunks[2].parse().unwrap(),
+                    chunks[3].parse().unwrap(),
+                ) * scale,
+            );
+        } else if command == "vt" {
+            uvs.push(Vector2::new(
+                chunks[1].parse().unwrap(),
+                chunks[2].parse().unwrap(),
+            ));
+        } else if command == "f" {
+            let face_vertices = &chunks[1..];
+            if face_vertices.len() < 3 {
+                panic!("Faces must have at least 3 points (line {})", line_num);
+            }
+            let mut point_ids: Vec<usize> = vec![];
+            let face_points: Vec<UVPair<f64>> = face_vertices
+                .iter()
+                .map(|face_vertex| {
+                    let ids: Vec<Option<usize>> =
+                        face_vertex.split('/').map(|id| id.parse().ok()).collect();
+                    let vertex_id: usize = ids[0].unwrap() - 1;
+                    point_ids.push(vertex_id);
+                    // "f 1 2 3" has no UVs at all, and "f 1//1 2//2 3//3" has an empty one
+                    let uv = if let Some(uv_id) = ids.get(1).copied().flatten() {
+                        uvs[uv_id - 1]
+                    } else {
+                        Vector2::zeros()
+                    };
+                    UVPair {
+                        point: vertices[vertex_id],
+                        uv,
+                    }
+                })
+                .collect();
+            match Face::try_from_uv_pairs(face_points) {
+                Ok(new_face) => faces.push(new_face.with_point_ids(point_ids)),
+                // Exporters sometimes leave in zero-area slivers,
+                // which can't be shaded since they have no normal
+                Err(err) => {
+                    console_log!("Skipping face on line {}: {}", line_num, err);
+                }
+            }
+        } else {
+            panic!("Unrecognized command on line {}: {}", line_num, command);
+        }
+    }
+    faces
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{point, vector};
+
+    use super::*;
+
+    const VERTICES: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.5 0.25\nvn 0 0 1\n";
+
+    #[test]
+    fn test_face_formats() {
+        for face_line in ["f 1 2 3", "f 1//1 2//1 3//1", "f 1/1/1 2/1/1 3/1/1"] {
+            let faces = load_obj(&format!("{}{}", VERTICES, face_line));
+            assert_eq!(faces.len(), 1, "{}", face_line);
+            let face = &faces[0];
+            assert_eq!(
+                face.points(),
+                &[
+                    point![0.0, 0.0, 0.0],
+                    point![1.0, 0.0, 0.0],
+                    point![0.0, 1.0, 0.0]
+                ]
+            );
+            assert_eq!(face.point_ids(), Some(&vec![0, 1, 2]));
+            let uv = if face_line.contains("/1/") {
+                vector![0.5, 0.25]
+            } else {
+                vector![0.0, 0.0]
+            };
+            assert!(
+                face.uvs().iter().all(|face_uv| *face_uv == uv),
+                "{}",
+                face_line
+            );
+        }
+    }
+}
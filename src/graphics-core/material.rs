@@ -0,0 +1,31 @@
+//! How a surface looks, following glTF's metallic-roughness model
+
+/// A surface's color and finish
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: Option<String>,
+    /// Linear red, green, blue and alpha, each from 0 to 1
+    pub base_color: [f64; 4],
+    /// From 0 (dielectric) to 1 (metal)
+    pub metallic: f64,
+    /// From 0 (smooth) to 1 (rough)
+    pub roughness: f64,
+    /// The image the base color is multiplied by, as an index into the file's images
+    pub base_color_texture: Option<usize>,
+    /// Whether the back of each face is drawn too
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    /// glTF's default material: white, fully metallic and fully rough
+    fn default() -> Self {
+        Self {
+            name: None,
+            base_color: [1.0; 4],
+            metallic: 1.0,
+            roughness: 1.0,
+            base_color_texture: None,
+            double_sided: false,
+        }
+    }
+}
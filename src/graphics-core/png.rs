@@ -0,0 +1,343 @@
+use std::convert::TryInto;
+use std::fmt;
+
+/// An 8-bit RGBA image, stored row by row starting from the top left
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PngError {
+    InvalidSignature,
+    Truncated,
+    BadChecksum([u8; 4]),
+    MissingHeader,
+    /// Only 8-bit RGBA, non-interlaced images are supported
+    UnsupportedFormat {
+        bit_depth: u8,
+        color_type: u8,
+    },
+    /// Only uncompressed (stored) deflate blocks are supported,
+    /// which is what `Image::to_png` writes
+    UnsupportedCompression,
+    UnsupportedFilter(u8),
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PngError::InvalidSignature => write!(f, "not a PNG file"),
+            PngError::Truncated => write!(f, "PNG data ends early"),
+            PngError::BadChecksum(chunk_type) => write!(
+                f,
+                "bad checksum in {} chunk",
+                String::from_utf8_lossy(chunk_type)
+            ),
+            PngError::MissingHeader => write!(f, "missing IHDR chunk"),
+            PngError::UnsupportedFormat {
+                bit_depth,
+                color_type,
+            } => write!(
+                f,
+                "unsupported format (bit depth {}, color type {})",
+                bit_depth, color_type
+            ),
+            PngError::UnsupportedCompression => {
+                write!(f, "only stored deflate blocks are supported")
+            }
+            PngError::UnsupportedFilter(filter) => write!(f, "unknown filter type {}", filter),
+        }
+    }
+}
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const COLOR_TYPE_RGBA: u8 = 6;
+const MAX_STORED_BLOCK_LEN: usize = 0xffff;
+
+impl Image {
+    pub fn new(width: usize, height: usize, color: [u8; 4]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color; width * height],
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &[[u8; 4]] {
+        &self.pixels
+    }
+
+    #[inline]
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * self.width + x]
+    }
+
+    #[inline]
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Encodes the image as a PNG.
+    /// The image data isn't compressed (it uses stored deflate blocks),
+    /// which keeps this small and dependency-free at the cost of file size.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(self.height * (1 + self.width * 4));
+        for row in self.pixels.chunks(self.width.max(1)).take(self.height) {
+            // Filter type "None"
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(MAX_STORED_BLOCK_LEN).peekable();
+        if blocks.peek().is_none() {
+            zlib.extend([1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            let is_final = blocks.peek().is_none();
+            let len = block.len() as u16;
+            zlib.push(is_final as u8);
+            zlib.extend(len.to_le_bytes());
+            zlib.extend((!len).to_le_bytes());
+            zlib.extend(block);
+        }
+        zlib.extend(adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        // Bit depth, color type, compression, filter, interlace
+        header.extend([8, COLOR_TYPE_RGBA, 0, 0, 0]);
+
+        let mut png = SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib);
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Decodes a PNG written by `to_png`.
+    /// PNGs with compressed image data are rejected rather than decoded.
+    pub fn from_png(png: &[u8]) -> Result<Self, PngError> {
+        if png.len() < SIGNATURE.len() || png[..SIGNATURE.len()] != SIGNATURE {
+            return Err(PngError::InvalidSignature);
+        }
+        let mut rest = &png[SIGNATURE.len()..];
+        let mut size = None;
+        let mut zlib = vec![];
+        while !rest.is_empty() {
+            let len = read_u32(rest, 0)? as usize;
+            // The length, type and checksum around the data (sizes from the file can overflow)
+            let chunk_end = len.checked_add(12).ok_or(PngError::Truncated)?;
+            let chunk = rest.get(4..chunk_end).ok_or(PngError::Truncated)?;
+            let (type_and_data, crc) = chunk.split_at(4 + len);
+            let chunk_type: [u8; 4] = type_and_data[..4].try_into().unwrap();
+            if crc32(type_and_data).to_be_bytes() != crc {
+                return Err(PngError::BadChecksum(chunk_type));
+            }
+            let data = &type_and_data[4..];
+            match &chunk_type {
+                b"IHDR" => {
+                    let width = read_u32(data, 0)? as usize;
+                    let height = read_u32(data, 4)? as usize;
+                    let format = data.get(8..13).ok_or(PngError::Truncated)?;
+                    if format != [8, COLOR_TYPE_RGBA, 0, 0, 0] {
+                        return Err(PngError::UnsupportedFormat {
+                            bit_depth: format[0],
+                            color_type: format[1],
+                        });
+                    }
+                    size = Some((width, height));
+                }
+                b"IDAT" => zlib.extend_from_slice(data),
+                b"IEND" => break,
+                _ => {}
+            }
+            rest = &rest[chunk_end..];
+        }
+        let (width, height) = size.ok_or(PngError::MissingHeader)?;
+
+        let raw = inflate_stored(&zlib)?;
+        // Each row is its filter type followed by its pixels
+        let stride = width.checked_mul(4).ok_or(PngError::Truncated)?;
+        let filtered_size = stride
+            .checked_add(1)
+            .and_then(|row_size| row_size.checked_mul(height))
+            .ok_or(PngError::Truncated)?;
+        if raw.len() < filtered_size {
+            return Err(PngError::Truncated);
+        }
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut previous_row = vec![0; stride];
+        for filtered_row in raw.chunks(stride + 1).take(height) {
+            let row = unfilter(filtered_row[0], &filtered_row[1..], &previous_row)?;
+            pixels.extend_from_slice(row.as_chunks::<4>().0);
+            previous_row = row;
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(chunk_type);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, PngError> {
+    let bytes = bytes.get(offset..offset + 4).ok_or(PngError::Truncated)?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn inflate_stored(zlib: &[u8]) -> Result<Vec<u8>, PngError> {
+    let mut rest = zlib.get(2..).ok_or(PngError::Truncated)?;
+    let mut raw = vec![];
+    loop {
+        let header = *rest.first().ok_or(PngError::Truncated)?;
+        // Bits 1 and 2 are the block type, stored blocks are type 0
+        if header & 0b110 != 0 {
+            return Err(PngError::UnsupportedCompression);
+        }
+        let len_bytes = rest.get(1..3).ok_or(PngError::Truncated)?;
+        let len = u16::from_le_bytes([len_bytes[0], len_bytes[1]]) as usize;
+        raw.extend_from_slice(rest.get(5..5 + len).ok_or(PngError::Truncated)?);
+        rest = &rest[5 + len..];
+        if header & 1 == 1 {
+            return Ok(raw);
+        }
+    }
+}
+
+fn unfilter(filter: u8, row: &[u8], previous_row: &[u8]) -> Result<Vec<u8>, PngError> {
+    const BYTES_PER_PIXEL: usize = 4;
+    let mut out: Vec<u8> = Vec::with_capacity(row.len());
+    for (i, &byte) in row.iter().enumerate() {
+        let left = if i >= BYTES_PER_PIXEL {
+            out[i - BYTES_PER_PIXEL]
+        } else {
+            0
+        };
+        let up = previous_row[i];
+        let up_left = if i >= BYTES_PER_PIXEL {
+            previous_row[i - BYTES_PER_PIXEL]
+        } else {
+            0
+        };
+        let predicted = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return Err(PngError::UnsupportedFilter(filter)),
+        };
+        out.push(byte.wrapping_add(predicted));
+    }
+    Ok(out)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_up = (estimate - up as i16).abs();
+    let distance_up_left = (estimate - up_left as i16).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (a, b) = bytes.iter().fold((1, 0), |(a, b), &byte| {
+        let a = (a + byte as u32) % MOD;
+        (a, (b + a) % MOD)
+    });
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Big enough to need more than one stored block
+        let mut image = Image::new(200, 100, [10, 20, 30, 255]);
+        image.set_pixel(3, 4, [255, 0, 0, 255]);
+        image.set_pixel(199, 99, [0, 0, 255, 128]);
+        let decoded = Image::from_png(&image.to_png()).unwrap();
+        assert_eq!(decoded, image);
+    }
+
+    #[test]
+    fn test_rejects_corruption() {
+        let mut png = Image::new(2, 2, [0, 0, 0, 255]).to_png();
+        assert_eq!(Image::from_png(&png[..20]), Err(PngError::Truncated));
+        let last = png.len() - 1;
+        png[last] ^= 1;
+        assert_eq!(Image::from_png(&png), Err(PngError::BadChecksum(*b"IEND")));
+    }
+
+    #[test]
+    fn test_rejects_huge_sizes() {
+        // A chunk claiming to be as long as a chunk can be
+        let mut png = SIGNATURE.to_vec();
+        png.extend(u32::MAX.to_be_bytes());
+        png.extend(b"IDAT");
+        assert_eq!(Image::from_png(&png), Err(PngError::Truncated));
+
+        // A 1x1 image whose header says it's the biggest image a header can describe
+        let mut png = Image::new(1, 1, [0; 4]).to_png();
+        png[16..24].copy_from_slice(&u32::MAX.to_be_bytes().repeat(2));
+        let crc = crc32(&png[12..29]);
+        png[29..33].copy_from_slice(&crc.to_be_bytes());
+        assert_eq!(Image::from_png(&png), Err(PngError::Truncated));
+    }
+}
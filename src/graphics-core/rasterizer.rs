@@ -0,0 +1,443 @@
+//! A small CPU rasterizer, so what the shaders draw can be checked without a GPU.
+//! It takes the same flat float buffers that get uploaded to WebGL,
+//! and runs "shaders" written as Rust closures over them.
+
+use std::{env, fs, path::Path};
+
+use nalgebra::Vector4;
+
+use crate::png::Image;
+
+/// One vertex attribute buffer, like a `vertexAttribPointer` call:
+/// `size` floats per vertex, tightly packed
+#[derive(Debug, Clone, Copy)]
+pub struct Attribute<'a> {
+    pub data: &'a [f32],
+    pub size: usize,
+}
+
+impl<'a> Attribute<'a> {
+    pub fn new(data: &'a [f32], size: usize) -> Self {
+        Self { data, size }
+    }
+}
+
+/// A clip space position and the values to interpolate across the triangle
+struct ShadedVertex {
+    position: Vector4<f64>,
+    varyings: Vec<f64>,
+}
+
+pub struct Rasterizer {
+    image: Image,
+    depth: Vec<f64>,
+}
+
+impl Rasterizer {
+    pub fn new(width: usize, height: usize, clear_color: Vector4<f64>) -> Self {
+        Self {
+            image: Image::new(width, height, to_rgba8(&clear_color)),
+            depth: vec![f64::INFINITY; width * height],
+        }
+    }
+
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    #[inline]
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Draws every three vertices as a triangle, like `gl.drawArrays(gl.TRIANGLES, ...)`.
+    ///
+    /// The vertex shader gets each attribute's values for one vertex,
+    /// and returns the clip space position and the varyings for the fragment shader.
+    /// The fragment shader returns an RGBA color, with each channel from 0 to 1.
+    /// Depth testing works like `gl.depthFunc(gl.LEQUAL)`.
+    pub fn draw_triangles<V, F>(
+        &mut self,
+        attributes: &[Attribute],
+        vertex_shader: V,
+        fragment_shader: F,
+    ) where
+        V: Fn(&[&[f32]]) -> (Vector4<f64>, Vec<f64>),
+        F: Fn(&[f64]) -> Vector4<f64>,
+    {
+        let num_vertices = attributes
+            .iter()
+            .map(|attribute| attribute.data.len() / attribute.size)
+            .min()
+            .unwrap_or(0);
+
+        let mut vertex_attributes = Vec::with_capacity(attributes.len());
+        let vertices: Vec<ShadedVertex> = (0..num_vertices)
+            .map(|i| {
+                vertex_attributes.clear();
+                vertex_attributes.extend(
+                    attributes
+                        .iter()
+                        .map(|attribute| &attribute.data[i * attribute.size..][..attribute.size]),
+                );
+                let (position, varyings) = vertex_shader(&vertex_attributes);
+                ShadedVertex { position, varyings }
+            })
+            .collect();
+
+        for triangle in vertices.as_chunks::<3>().0 {
+            let polygon = clip_polygon(triangle);
+            for i in 1..polygon.len().saturating_sub(1) {
+                self.fill_triangle(
+                    [&polygon[0], &polygon[i], &polygon[i + 1]],
+                    &fragment_shader,
+                );
+            }
+        }
+    }
+
+    fn fill_triangle<F>(&mut self, triangle: [&ShadedVertex; 3], fragment_shader: &F)
+    where
+        F: Fn(&[f64]) -> Vector4<f64>,
+    {
+        let width = self.image.width();
+        let height = self.image.height();
+        // Perspective divide, then NDC to pixel coordinates (with y pointing down)
+        let screen = triangle.map(|vertex| {
+            let w = vertex.position.w;
+            (
+                (vertex.position.x / w + 1.0) / 2.0 * width as f64,
+                (1.0 - vertex.position.y / w) / 2.0 * height as f64,
+                vertex.position.z / w,
+            )
+        });
+        let edge = |a: (f64, f64, f64), b: (f64, f64, f64), x: f64, y: f64| {
+            (b.0 - a.0) * (y - a.1) - (b.1 - a.1) * (x - a.0)
+        };
+        let area = edge(screen[0], screen[1], screen[2].0, screen[2].1);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+
+        let min_x = screen.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max_x = screen.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let min_y = screen.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_y = screen.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let start_x = min_x.floor().max(0.0) as usize;
+        let end_x = (max_x.ceil().max(0.0) as usize).min(width);
+        let start_y = min_y.floor().max(0.0) as usize;
+        let end_y = (max_y.ceil().max(0.0) as usize).min(height);
+
+        let num_varyings = triangle
+            .iter()
+            .map(|vertex| vertex.varyings.len())
+            .min()
+            .unwrap_or(0);
+        let mut varyings = vec![0.0; num_varyings];
+
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                // Sample at the pixel center
+                let sample_x = x as f64 + 0.5;
+                let sample_y = y as f64 + 0.5;
+                // Barycentric coordinates, which are all positive inside the triangle
+                // no matter which way it winds
+                let b0 = edge(screen[1], screen[2], sample_x, sample_y) / area;
+                let b1 = edge(screen[2], screen[0], sample_x, sample_y) / area;
+                let b2 = edge(screen[0], screen[1], sample_x, sample_y) / area;
+                if b0 < 0.0 || b1 < 0.0 || b2 < 0.0 {
+                    continue;
+                }
+
+                let depth = b0 * screen[0].2 + b1 * screen[1].2 + b2 * screen[2].2;
+                let pixel_index = y * width + x;
+                if depth > self.depth[pixel_index] {
+                    continue;
+                }
+
+                // Perspective correct interpolation, weighting each vertex by 1/w
+                let weights = [
+                    b0 / triangle[0].position.w,
+                    b1 / triangle[1].position.w,
+                    b2 / triangle[2].position.w,
+                ];
+                let total_weight: f64 = weights.iter().sum();
+                for (i, varying) in varyings.iter_mut().enumerate() {
+                    *varying = weights
+                        .iter()
+                        .zip(triangle)
+                        .map(|(weight, vertex)| weight * vertex.varyings[i])
+                        .sum::<f64>()
+                        / total_weight;
+                }
+
+                self.depth[pixel_index] = depth;
+                self.image
+                    .set_pixel(x, y, to_rgba8(&fragment_shader(&varyings)));
+            }
+        }
+    }
+}
+
+/// Clips a triangle against the near and far planes (-w <= z <= w),
+/// returning a convex polygon (which may be empty).
+/// The left/right/top/bottom planes don't need clipping,
+/// since `fill_triangle` only visits pixels inside the image.
+fn clip_polygon(triangle: &[ShadedVertex]) -> Vec<ShadedVertex> {
+    let mut polygon: Vec<ShadedVertex> = triangle
+        .iter()
+        .map(|vertex| ShadedVertex {
+            position: vertex.position,
+            varyings: vertex.varyings.clone(),
+        })
+        .collect();
+
+    // Positive distance is inside the plane
+    let planes: [fn(&Vector4<f64>) -> f64; 2] = [
+        |position| position.z + position.w,
+        |position| position.w - position.z,
+    ];
+    for plane in planes {
+        if polygon.is_empty() {
+            break;
+        }
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for (i, current) in polygon.iter().enumerate() {
+            let next = &polygon[(i + 1) % polygon.len()];
+            let current_distance = plane(&current.position);
+            let next_distance = plane(&next.position);
+            if current_distance >= 0.0 {
+                clipped.push(ShadedVertex {
+                    position: current.position,
+                    varyings: current.varyings.clone(),
+                });
+            }
+            if (current_distance >= 0.0) != (next_distance >= 0.0) {
+                let t = current_distance / (current_distance - next_distance);
+                clipped.push(ShadedVertex {
+                    position: current.position.lerp(&next.position, t),
+                    varyings: current
+                        .varyings
+                        .iter()
+                        .zip(&next.varyings)
+                        .map(|(a, b)| a + (b - a) * t)
+                        .collect(),
+                });
+            }
+        }
+        polygon = clipped;
+    }
+
+    polygon
+}
+
+fn to_rgba8(color: &Vector4<f64>) -> [u8; 4] {
+    color
+        .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .into()
+}
+
+/// Compares a rendered image against the golden image at `path`,
+/// panicking with a description of the difference if they don't match.
+///
+/// Each channel may be off by a couple of levels (floating point differences between platforms),
+/// and a small fraction of pixels (mostly triangle edges) may differ by more than that.
+///
+/// If the golden image doesn't exist yet, or the `UPDATE_GOLDEN_IMAGES` environment variable is set,
+/// the golden image is (re)written instead. A missing golden image still fails the test,
+/// so new images have to be looked at before they are committed.
+/// When the images differ, the rendered image is written next to the golden one as `*.actual.png`.
+pub fn assert_matches_golden(image: &Image, path: impl AsRef<Path>) {
+    const CHANNEL_TOLERANCE: u8 = 2;
+    const MAX_MISMATCHED_FRACTION: f64 = 0.005;
+
+    let path = path.as_ref();
+    let update = env::var_os("UPDATE_GOLDEN_IMAGES").is_some();
+    if update || !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, image.to_png()).unwrap();
+        assert!(
+            update,
+            "golden image {} did not exist, so it was written. Check it and commit it.",
+            path.display()
+        );
+        return;
+    }
+
+    let golden = Image::from_png(&fs::read(path).unwrap())
+        .unwrap_or_else(|err| panic!("could not read golden image {}: {}", path.display(), err));
+    let mismatch = if (golden.width(), golden.height()) != (image.width(), image.height()) {
+        Some(format!(
+            "size {}x{} does not match golden size {}x{}",
+            image.width(),
+            image.height(),
+            golden.width(),
+            golden.height()
+        ))
+    } else {
+        let mismatched_pixels = image
+            .pixels()
+            .iter()
+            .zip(golden.pixels())
+            .filter(|(pixel, golden_pixel)| {
+                pixel
+                    .iter()
+                    .zip(golden_pixel.iter())
+                    .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+            })
+            .count();
+        let fraction = mismatched_pixels as f64 / image.pixels().len().max(1) as f64;
+        if fraction > MAX_MISMATCHED_FRACTION {
+            Some(format!(
+                "{} pixels ({:.2}%) differ",
+                mismatched_pixels,
+                fraction * 100.0
+            ))
+        } else {
+            None
+        }
+    };
+
+    if let Some(mismatch) = mismatch {
+        let actual_path = path.with_extension("actual.png");
+        fs::write(&actual_path, image.to_png()).unwrap();
+        panic!(
+            "rendered image does not match golden image {}: {}. The rendered image was written to {}",
+            path.display(),
+            mismatch,
+            actual_path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::vector;
+
+    fn draw_flat(rasterizer: &mut Rasterizer, points: &[f32], color: Vector4<f64>) {
+        rasterizer.draw_triangles(
+            &[Attribute::new(points, 4)],
+            |attributes| {
+                let point = attributes[0];
+                (
+                    vector![point[0], point[1], point[2], point[3]].cast(),
+                    vec![],
+                )
+            },
+            |_| color,
+        );
+    }
+
+    #[test]
+    fn test_fills_triangle() {
+        let mut rasterizer = Rasterizer::new(4, 4, vector![0.0, 0.0, 0.0, 1.0]);
+        // Covers the bottom left half of the image
+        draw_flat(
+            &mut rasterizer,
+            &[
+                -1.0, -1.0, 0.0, 1.0, 1.0, -1.0, 0.0, 1.0, -1.0, 1.0, 0.0, 1.0,
+            ],
+            vector![1.0, 0.0, 0.0, 1.0],
+        );
+        let image = rasterizer.image();
+        assert_eq!(image.pixel(0, 3), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(3, 0), [0, 0, 0, 255]);
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(3, 3), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_depth_test() {
+        let mut rasterizer = Rasterizer::new(2, 2, vector![0.0, 0.0, 0.0, 1.0]);
+        let square = |z: f32| {
+            [
+                -1.0, -1.0, z, 1.0, 1.0, -1.0, z, 1.0, 1.0, 1.0, z, 1.0, -1.0, -1.0, z, 1.0, 1.0,
+                1.0, z, 1.0, -1.0, 1.0, z, 1.0,
+            ]
+        };
+        draw_flat(&mut rasterizer, &square(0.5), vector![1.0, 0.0, 0.0, 1.0]);
+        draw_flat(&mut rasterizer, &square(0.0), vector![0.0, 1.0, 0.0, 1.0]);
+        // Behind the green square
+        draw_flat(&mut rasterizer, &square(0.2), vector![0.0, 0.0, 1.0, 1.0]);
+        assert!(rasterizer
+            .image()
+            .pixels()
+            .iter()
+            .all(|&pixel| pixel == [0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_near_plane_clipping() {
+        let mut rasterizer = Rasterizer::new(2, 2, vector![0.0, 0.0, 0.0, 1.0]);
+        // Entirely in front of the near plane
+        draw_flat(
+            &mut rasterizer,
+            &[
+                -1.0, -1.0, -2.0, 1.0, 3.0, -1.0, -2.0, 1.0, -1.0, 3.0, -2.0, 1.0,
+            ],
+            vector![1.0, 1.0, 1.0, 1.0],
+        );
+        assert!(rasterizer
+            .image()
+            .pixels()
+            .iter()
+            .all(|&pixel| pixel == [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_interpolates_varyings() {
+        let mut rasterizer = Rasterizer::new(4, 1, vector![0.0, 0.0, 0.0, 1.0]);
+        let points = [
+            -1.0, -1.0, 0.0, 1.0, 1.0, -1.0, 0.0, 1.0, 1.0, 1.0, 0.0, 1.0, -1.0, -1.0, 0.0, 1.0,
+            1.0, 1.0, 0.0, 1.0, -1.0, 1.0, 0.0, 1.0,
+        ];
+        rasterizer.draw_triangles(
+            &[Attribute::new(&points, 4)],
+            |attributes| {
+                let point = attributes[0];
+                (
+                    vector![point[0], point[1], point[2], point[3]].cast(),
+                    // Brightness goes from 0 on the left to 1 on the right
+                    vec![(point[0] as f64 + 1.0) / 2.0],
+                )
+            },
+            |varyings| vector![varyings[0], varyings[0], varyings[0], 1.0],
+        );
+        let image = rasterizer.image();
+        // Pixel centers are at 1/8, 3/8, 5/8 and 7/8 of the way across
+        assert_eq!(image.pixel(0, 0)[0], 32);
+        assert_eq!(image.pixel(1, 0)[0], 96);
+        assert_eq!(image.pixel(2, 0)[0], 159);
+        assert_eq!(image.pixel(3, 0)[0], 223);
+    }
+
+    #[test]
+    fn test_golden_comparison() {
+        let path = env::temp_dir().join(format!("golden-{}.png", std::process::id()));
+        let mut image = Image::new(20, 20, [0, 0, 0, 255]);
+        fs::write(&path, image.to_png()).unwrap();
+
+        // Small differences in a channel are fine
+        image.set_pixel(0, 0, [2, 0, 0, 255]);
+        assert_matches_golden(&image, &path);
+
+        // A whole row being wrong is too many mismatched pixels
+        for x in 0..20 {
+            image.set_pixel(x, 1, [255, 0, 0, 255]);
+        }
+        let result = std::panic::catch_unwind(|| assert_matches_golden(&image, &path));
+        let actual_path = path.with_extension("actual.png");
+        assert!(result.is_err());
+        assert_eq!(
+            Image::from_png(&fs::read(&actual_path).unwrap()).unwrap(),
+            image
+        );
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(actual_path).unwrap();
+    }
+}
@@ -2,33 +2,51 @@ use nalgebra::{one, OVector, Point, Point2, Point3};
 
 use crate::{bounding_box::BoundingBox, face::Face, Number};
 
-#[derive(Debug)]
-pub(crate) struct Ray<T: Number, const DIM: usize> {
+#[derive(Debug, Clone)]
+pub struct Ray<T: Number, const DIM: usize> {
     start: Point<T, DIM>,
     end: Point<T, DIM>,
 }
 
 impl<T: Number, const DIM: usize> Ray<T, { DIM }> {
     #[inline]
-    pub(crate) fn new(start: Point<T, DIM>, end: Point<T, DIM>) -> Self {
+    pub fn new(start: Point<T, DIM>, end: Point<T, DIM>) -> Self {
         Self { start, end }
     }
     #[inline]
-    pub(crate) fn bounding_box(&self) -> BoundingBox<T, DIM> {
+    pub fn bounding_box(&self) -> BoundingBox<T, DIM> {
         BoundingBox::from_points(&[self.start, self.end])
     }
     #[inline]
-    pub(crate) fn invert(&self) -> Self {
+    pub fn invert(&self) -> Self {
         Self::new(self.end, self.start)
     }
     #[inline]
-    pub(crate) fn to_vector(&self) -> OVector<T, nalgebra::Const<DIM>> {
+    pub fn to_vector(&self) -> OVector<T, nalgebra::Const<DIM>> {
         self.end - self.start
     }
+    #[inline]
+    pub fn start(&self) -> &Point<T, DIM> {
+        &self.start
+    }
+    #[inline]
+    pub fn end(&self) -> &Point<T, DIM> {
+        &self.end
+    }
+}
+
+impl Ray<f64, 3> {
+    #[inline]
+    pub fn to_segment(&self) -> parry3d::shape::Segment {
+        parry3d::shape::Segment {
+            a: self.start,
+            b: self.end,
+        }
+    }
 }
 
 impl<T: Number> Ray<T, 2> {
-    pub(crate) fn ray_intersection(&self, other: &Ray<T, 2>) -> Option<Point<T, 2>> {
+    pub fn ray_intersection(&self, other: &Ray<T, 2>) -> Option<Point<T, 2>> {
         let intersection: Point2<T> = {
             // 1 is self, 2 is other
 
@@ -97,7 +115,7 @@ impl<T: Number> Ray<T, 2> {
 }
 
 impl<T: Number> Ray<T, 3> {
-    pub(crate) fn face_intersection(&self, face: &Face<T>) -> Option<Point3<T>> {
+    pub fn face_intersection(&self, face: &Face<T>) -> Option<Point3<T>> {
         // If the bounding boxes do not intersect, the ray and the plane cannot intersect
         if !face.bounding_box().intersects_with(&self.bounding_box()) {
             return None;
@@ -117,8 +135,8 @@ impl<T: Number> Ray<T, 3> {
         // (it is allowable for one of the ray ends to have a z-sign of 0,
         // meaning it just touches the plane: this counts as an intersection)
         if start_in_relative_coords.z.signum() == end_in_relative_coords.z.signum()
-            && start_in_relative_coords.z.into().abs() >= std::f64::EPSILON
-            && end_in_relative_coords.z.into().abs() >= std::f64::EPSILON
+            && start_in_relative_coords.z.into().abs() >= f64::EPSILON
+            && end_in_relative_coords.z.into().abs() >= f64::EPSILON
         {
             return None;
         }
@@ -0,0 +1,68 @@
+use nalgebra::Point3;
+
+use crate::face::Face;
+
+/// The points of a big cube around the camera, as triangles for the skybox shader
+pub fn generate_skybox_points() -> Vec<f32> {
+    let faces = {
+        let scale = 100.0;
+        let front_right_bottom = Point3::new(0.5, -0.5, 0.5) * scale;
+        let front_left_bottom = Point3::new(-0.5, -0.5, 0.5) * scale;
+        let front_right_top = Point3::new(0.5, 0.5, 0.5) * scale;
+        let front_left_top = Point3::new(-0.5, 0.5, 0.5) * scale;
+
+        let back_right_bottom = Point3::new(0.5, -0.5, -0.5) * scale;
+        let back_left_bottom = Point3::new(-0.5, -0.5, -0.5) * scale;
+        let back_right_top = Point3::new(0.5, 0.5, -0.5) * scale;
+        let back_left_top = Point3::new(-0.5, 0.5, -0.5) * scale;
+
+        vec![
+            Face::new(vec![
+                front_right_top,
+                front_right_bottom,
+                front_left_bottom,
+                front_left_top,
+            ]),
+            Face::new(vec![
+                front_right_top,
+                back_right_top,
+                back_right_bottom,
+                front_right_bottom,
+            ]),
+            Face::new(vec![
+                front_left_top,
+                front_left_bottom,
+                back_left_bottom,
+                back_left_top,
+            ]),
+            Face::new(vec![
+                front_right_top,
+                front_left_top,
+                back_left_top,
+                back_right_top,
+            ]),
+            Face::new(vec![
+                front_right_bottom,
+                back_right_bottom,
+                back_left_bottom,
+                front_left_bottom,
+            ]),
+            Face::new(vec![
+                back_right_top,
+                back_left_top,
+                back_left_bottom,
+                back_right_bottom,
+            ]),
+        ]
+    };
+
+    let points: Vec<_> = faces
+        .iter()
+        .flat_map(|face| face.break_into_triangles())
+        .collect();
+
+    points
+        .iter()
+        .flat_map(|point| [point.x as _, point.y as _, point.z as _, 1.0])
+        .collect()
+}
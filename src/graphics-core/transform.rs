@@ -0,0 +1,394 @@
+use std::{error, fmt};
+
+use nalgebra::{
+    Matrix3, Matrix4, Point3, Quaternion, Rotation3, Translation3, Unit, UnitQuaternion, Vector3,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::transform_matrix::TransformMatrix;
+
+/// Below this, a scale (or determinant) is treated as zero
+const EPSILON: f64 = 1e-12;
+
+/// A transform stored as its parts: scale first, then rotate, then translate.
+/// Unlike `TransformMatrix`, the parts can be read back out and edited separately.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    #[wasm_bindgen(skip)]
+    pub translation: Vector3<f64>,
+    #[wasm_bindgen(skip)]
+    pub rotation: UnitQuaternion<f64>,
+    /// Can be negative, to mirror along an axis
+    #[wasm_bindgen(skip)]
+    pub scale: Vector3<f64>,
+}
+
+/// Reasons a matrix can't be split into translation, rotation and scale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransformError {
+    /// The bottom row isn't (0, 0, 0, 1), e.g. it's a perspective projection
+    NotAffine,
+    /// The matrix squashes some direction down to nothing, so it has no rotation
+    Singular,
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransformError::NotAffine => write!(f, "matrix is not an affine transform"),
+            TransformError::Singular => write!(f, "matrix has a zero scale"),
+        }
+    }
+}
+
+impl error::Error for TransformError {}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+#[wasm_bindgen]
+impl Transform {
+    #[wasm_bindgen(constructor)]
+    pub fn identity() -> Self {
+        Self {
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::repeat(1.0),
+        }
+    }
+    pub fn with_translation(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.translation = Vector3::new(x, y, z);
+        self
+    }
+    /// The quaternion doesn't need to be normalized
+    pub fn with_rotation_quaternion(mut self, x: f64, y: f64, z: f64, w: f64) -> Self {
+        self.rotation = UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z));
+        self
+    }
+    /// Same angles as `TransformMatrix::rotation_euler`
+    pub fn with_rotation_euler(mut self, roll: f64, pitch: f64, yaw: f64) -> Self {
+        self.rotation = UnitQuaternion::from_euler_angles(roll, pitch, yaw);
+        self
+    }
+    pub fn with_rotation_axis_angle(mut self, x: f64, y: f64, z: f64, angle: f64) -> Self {
+        self.rotation = Unit::try_new(Vector3::new(x, y, z), EPSILON)
+            .map_or_else(UnitQuaternion::identity, |axis| {
+                UnitQuaternion::from_axis_angle(&axis, angle)
+            });
+        self
+    }
+    pub fn with_scale(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.scale = Vector3::new(x, y, z);
+        self
+    }
+    pub fn with_uniform_scale(self, scale: f64) -> Self {
+        self.with_scale(scale, scale, scale)
+    }
+
+    /// A transform at `eye` that faces `target`, in the same convention as a camera:
+    /// its local -z axis points at the target and its local +y is as close to `up` as possible.
+    /// The inverse of this is the view matrix `Matrix4::look_at_rh` builds.
+    #[allow(clippy::too_many_arguments)]
+    pub fn look_at(
+        eye_x: f64,
+        eye_y: f64,
+        eye_z: f64,
+        target_x: f64,
+        target_y: f64,
+        target_z: f64,
+        up_x: f64,
+        up_y: f64,
+        up_z: f64,
+    ) -> Self {
+        Self::look_at_points(
+            &Point3::new(eye_x, eye_y, eye_z),
+            &Point3::new(target_x, target_y, target_z),
+            &Vector3::new(up_x, up_y, up_z),
+        )
+    }
+
+    /// Splits an affine matrix into translation, rotation and scale.
+    /// A negative determinant (a mirroring) is put into the x scale.
+    /// Shear can't be represented, so for sheared matrices the rotation is the nearest one.
+    pub fn from_matrix(matrix: &TransformMatrix) -> Result<Transform, JsError> {
+        Ok(Self::decompose(matrix.matrix())?)
+    }
+    pub fn to_matrix(&self) -> TransformMatrix {
+        TransformMatrix::from(self.to_homogeneous())
+    }
+
+    /// x, y, z
+    #[wasm_bindgen(js_name = translation)]
+    pub fn translation_array(&self) -> Vec<f64> {
+        self.translation.as_slice().to_vec()
+    }
+    /// x, y, z, w (the same order as glTF)
+    #[wasm_bindgen(js_name = rotation)]
+    pub fn rotation_array(&self) -> Vec<f64> {
+        self.rotation.coords.as_slice().to_vec()
+    }
+    /// roll, pitch, yaw
+    pub fn euler_angles(&self) -> Vec<f64> {
+        let (roll, pitch, yaw) = self.rotation.euler_angles();
+        vec![roll, pitch, yaw]
+    }
+    /// x, y, z
+    #[wasm_bindgen(js_name = scale)]
+    pub fn scale_array(&self) -> Vec<f64> {
+        self.scale.as_slice().to_vec()
+    }
+
+    /// Undoes this transform.
+    /// Returns nothing if any of the scales are zero.
+    /// A non-uniform scale followed by a rotation can't be undone exactly
+    /// without shear, so in that case this is only the nearest transform.
+    pub fn inverse(&self) -> Option<Transform> {
+        if self.scale.iter().any(|scale| scale.abs() < EPSILON) {
+            return None;
+        }
+        let inverse_scale = self.scale.map(|scale| 1.0 / scale);
+        if self.has_uniform_scale() || self.rotation.angle() < EPSILON {
+            let rotation = self.rotation.inverse();
+            Some(Self {
+                translation: -inverse_scale.component_mul(&(rotation * self.translation)),
+                rotation,
+                scale: inverse_scale,
+            })
+        } else {
+            self.to_homogeneous()
+                .try_inverse()
+                .and_then(|inverse| Self::decompose(&inverse).ok())
+        }
+    }
+
+    #[wasm_bindgen(js_name = transform_point)]
+    pub fn transform_point_js(&self, x: f64, y: f64, z: f64) -> Vec<f64> {
+        let point = self.transform_point(&Point3::new(x, y, z));
+        point.coords.as_slice().to_vec()
+    }
+    /// Like `transform_point`, but ignores the translation (for directions and offsets)
+    #[wasm_bindgen(js_name = transform_vector)]
+    pub fn transform_vector_js(&self, x: f64, y: f64, z: f64) -> Vec<f64> {
+        let vector = self.transform_vector(&Vector3::new(x, y, z));
+        vector.as_slice().to_vec()
+    }
+}
+
+impl Transform {
+    pub fn new(
+        translation: Vector3<f64>,
+        rotation: UnitQuaternion<f64>,
+        scale: Vector3<f64>,
+    ) -> Self {
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn look_at_points(eye: &Point3<f64>, target: &Point3<f64>, up: &Vector3<f64>) -> Self {
+        Self {
+            translation: eye.coords,
+            // face_towards points +z along the direction given, but a camera looks down -z
+            rotation: UnitQuaternion::face_towards(&(eye - target), up),
+            scale: Vector3::repeat(1.0),
+        }
+    }
+
+    pub fn decompose(matrix: &Matrix4<f64>) -> Result<Self, TransformError> {
+        if matrix.fixed_view::<1, 4>(3, 0) != Matrix4::identity().fixed_view::<1, 4>(3, 0) {
+            return Err(TransformError::NotAffine);
+        }
+        let linear: Matrix3<f64> = matrix.fixed_view::<3, 3>(0, 0).into();
+        let determinant = linear.determinant();
+        if determinant.abs() < EPSILON {
+            return Err(TransformError::Singular);
+        }
+        let mut scale = Vector3::from_iterator(linear.column_iter().map(|column| column.norm()));
+        if determinant < 0.0 {
+            scale.x = -scale.x;
+        }
+        let unscaled = Matrix3::from_columns(&[
+            linear.column(0) / scale.x,
+            linear.column(1) / scale.y,
+            linear.column(2) / scale.z,
+        ]);
+        Ok(Self {
+            translation: matrix.fixed_view::<3, 1>(0, 3).into(),
+            rotation: UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix(&unscaled)),
+            scale,
+        })
+    }
+
+    pub fn to_homogeneous(&self) -> Matrix4<f64> {
+        Translation3::from(self.translation).to_homogeneous()
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+
+    #[inline]
+    pub fn transform_point(&self, point: &Point3<f64>) -> Point3<f64> {
+        Point3::from(self.transform_vector(&point.coords) + self.translation)
+    }
+
+    #[inline]
+    pub fn transform_vector(&self, vector: &Vector3<f64>) -> Vector3<f64> {
+        self.rotation * self.scale.component_mul(vector)
+    }
+
+    /// Part way from this transform to another: 0 is this one and 1 is the other.
+    /// The translation and scale move in straight lines and the rotation turns at a steady rate.
+    pub fn interpolate(&self, other: &Transform, t: f64) -> Transform {
+        Self {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: self
+                .rotation
+                .try_slerp(&other.rotation, t, EPSILON)
+                .unwrap_or(other.rotation),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+
+    fn has_uniform_scale(&self) -> bool {
+        (self.scale.x - self.scale.y).abs() < EPSILON
+            && (self.scale.y - self.scale.z).abs() < EPSILON
+    }
+}
+
+impl From<Transform> for TransformMatrix {
+    #[inline]
+    fn from(transform: Transform) -> Self {
+        transform.to_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{point, vector};
+
+    fn assert_close(a: &Matrix4<f64>, b: &Matrix4<f64>) {
+        assert!((a - b).norm() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_matches_matrix_constructors() {
+        let transform = Transform::identity()
+            .with_translation(1.0, 2.0, 3.0)
+            .with_rotation_euler(0.1, 0.2, 0.3);
+        let matrix = TransformMatrix::translation(1.0, 2.0, 3.0)
+            .times(&TransformMatrix::rotation_euler(0.1, 0.2, 0.3));
+        assert_close(&transform.to_homogeneous(), matrix.matrix());
+
+        let transform = transform.with_scale(2.0, 3.0, -4.0);
+        let point = point![0.5, -1.0, 2.0];
+        assert!(
+            (transform.transform_point(&point)
+                - transform.to_homogeneous().transform_point(&point))
+            .norm()
+                < 1e-9
+        );
+        let vector = vector![0.5, -1.0, 2.0];
+        assert!(
+            (transform.transform_vector(&vector)
+                - transform.to_homogeneous().transform_vector(&vector))
+            .norm()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_decompose_round_trip() {
+        for scale in [
+            vector![1.0, 1.0, 1.0],
+            vector![2.0, 0.5, 3.0],
+            vector![-2.0, 0.5, 3.0],
+        ] {
+            let transform = Transform::new(
+                vector![-4.0, 5.0, 6.0],
+                UnitQuaternion::from_euler_angles(0.7, -0.4, 2.5),
+                scale,
+            );
+            let decomposed = Transform::decompose(&transform.to_homogeneous()).unwrap();
+            assert_close(&decomposed.to_homogeneous(), &transform.to_homogeneous());
+            assert!((decomposed.translation - transform.translation).norm() < 1e-9);
+            assert!((decomposed.scale - transform.scale).norm() < 1e-9);
+            assert!(decomposed.rotation.angle_to(&transform.rotation) < 1e-9);
+        }
+
+        // Mirroring in y comes back as mirroring in x, with a half turn to make up for it
+        let mirrored = Transform::identity().with_scale(1.0, -1.0, 1.0);
+        let decomposed = Transform::decompose(&mirrored.to_homogeneous()).unwrap();
+        assert_eq!(decomposed.scale, vector![-1.0, 1.0, 1.0]);
+        assert_close(&decomposed.to_homogeneous(), &mirrored.to_homogeneous());
+    }
+
+    #[test]
+    fn test_decompose_rejects() {
+        let perspective = Matrix4::new_perspective(1.0, 1.0, 0.1, 10.0);
+        assert_eq!(
+            Transform::decompose(&perspective),
+            Err(TransformError::NotAffine)
+        );
+        let flat = Transform::identity().with_scale(1.0, 0.0, 1.0);
+        assert_eq!(
+            Transform::decompose(&flat.to_homogeneous()),
+            Err(TransformError::Singular)
+        );
+        assert_eq!(flat.inverse(), None);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let transforms = [
+            Transform::identity()
+                .with_translation(1.0, -2.0, 3.0)
+                .with_rotation_euler(0.3, 0.2, -1.0)
+                .with_uniform_scale(2.5),
+            Transform::identity()
+                .with_translation(1.0, -2.0, 3.0)
+                .with_scale(2.0, 3.0, 4.0),
+        ];
+        for transform in transforms {
+            let inverse = transform.inverse().unwrap();
+            assert_close(
+                &(inverse.to_homogeneous() * transform.to_homogeneous()),
+                &Matrix4::identity(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let from = Transform::identity();
+        let to = Transform::identity()
+            .with_translation(2.0, 0.0, 0.0)
+            .with_rotation_euler(0.0, 0.0, 1.0)
+            .with_scale(3.0, 1.0, 1.0);
+        let halfway = from.interpolate(&to, 0.5);
+        assert_eq!(halfway.translation, vector![1.0, 0.0, 0.0]);
+        assert_eq!(halfway.scale, vector![2.0, 1.0, 1.0]);
+        assert!((halfway.rotation.angle() - 0.5).abs() < 1e-12);
+        let end = from.interpolate(&to, 1.0);
+        assert_close(&end.to_homogeneous(), &to.to_homogeneous());
+    }
+
+    #[test]
+    fn test_look_at() {
+        let eye = point![1.0, 2.0, 3.0];
+        let target = point![-4.0, 0.0, 1.0];
+        let up = vector![0.0, 1.0, 0.0];
+        let transform = Transform::look_at_points(&eye, &target, &up);
+        assert_close(
+            &transform.inverse().unwrap().to_homogeneous(),
+            &Matrix4::look_at_rh(&eye, &target, &up),
+        );
+        let forwards = transform.transform_vector(&vector![0.0, 0.0, -1.0]);
+        assert!((forwards - (target - eye).normalize()).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,45 @@
+use nalgebra::{Matrix4, Rotation3, Translation3};
+use wasm_bindgen::prelude::*;
+
+/// A little wrapper around the nalgebra matrix4 class, for JS use,
+/// so we only have to generate binding code for the methods we actually need.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct TransformMatrix(Matrix4<f64>);
+
+#[wasm_bindgen]
+impl TransformMatrix {
+    #[wasm_bindgen(constructor)]
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Self::translation(x, y, z)
+    }
+    pub fn translation(x: f64, y: f64, z: f64) -> Self {
+        Self(Translation3::new(x, y, z).to_homogeneous())
+    }
+    pub fn rotation_euler(roll: f64, pitch: f64, yaw: f64) -> Self {
+        Self(Rotation3::from_euler_angles(roll, pitch, yaw).to_homogeneous())
+    }
+    pub fn identity() -> Self {
+        Self(Matrix4::identity())
+    }
+    pub fn to_f64_array(&self) -> Vec<f64> {
+        self.0.as_slice().to_vec()
+    }
+    pub fn times(&self, other: &TransformMatrix) -> TransformMatrix {
+        Self(self.0 * other.0)
+    }
+}
+
+impl TransformMatrix {
+    #[inline]
+    pub fn matrix(&self) -> &Matrix4<f64> {
+        &self.0
+    }
+}
+
+impl From<Matrix4<f64>> for TransformMatrix {
+    #[inline]
+    fn from(matrix: Matrix4<f64>) -> Self {
+        Self(matrix)
+    }
+}
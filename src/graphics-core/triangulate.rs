@@ -0,0 +1,343 @@
+use nalgebra::Point2;
+use num_traits::Float;
+
+use crate::Number;
+
+/// Twice the signed area of a polygon (positive when the points wind counterclockwise)
+fn signed_area<T: Number>(points: &[Point2<T>]) -> T {
+    let mut area = T::zero();
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area
+}
+
+/// Z component of the cross product of (b - a) and (c - b):
+/// positive when a -> b -> c turns counterclockwise
+#[inline]
+fn turn<T: Number>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> T {
+    (b.x - a.x) * (c.y - b.y) - (b.y - a.y) * (c.x - b.x)
+}
+
+/// Whether p is inside (or on the edge of) the counterclockwise triangle a, b, c
+#[inline]
+fn in_triangle<T: Number>(p: &Point2<T>, a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> bool {
+    let zero = T::zero();
+    turn(a, b, p) >= zero && turn(b, c, p) >= zero && turn(c, a, p) >= zero
+}
+
+/// Whether every corner of the polygon turns the same direction
+/// (collinear corners are allowed), meaning a triangle fan is a valid triangulation
+pub fn is_convex<T: Number>(points: &[Point2<T>]) -> bool {
+    let zero = T::zero();
+    let mut has_left_turn = false;
+    let mut has_right_turn = false;
+    for i in 0..points.len() {
+        let t = turn(
+            &points[i],
+            &points[(i + 1) % points.len()],
+            &points[(i + 2) % points.len()],
+        );
+        has_left_turn |= t > zero;
+        has_right_turn |= t < zero;
+    }
+    !(has_left_turn && has_right_turn)
+}
+
+/// Triangle indices for a fan around the first point (only valid for convex polygons)
+pub fn fan<T: Number>(points: &[Point2<T>]) -> Vec<[usize; 3]> {
+    (1..points.len().saturating_sub(1))
+        .map(|i| [0, i, i + 1])
+        .collect()
+}
+
+/// Triangulates a simple polygon, which may be concave, by ear clipping.
+///
+/// `holes` are polygons strictly inside `outer`. The returned indices refer to
+/// `outer` followed by each hole's points, in order.
+/// Triangles are wound the same direction as `outer`.
+pub fn ear_clip<T: Number>(outer: &[Point2<T>], holes: &[Vec<Point2<T>>]) -> Vec<[usize; 3]> {
+    if outer.len() < 3 {
+        return vec![];
+    }
+    let all_points: Vec<Point2<T>> = outer
+        .iter()
+        .chain(holes.iter().flatten())
+        .copied()
+        .collect();
+
+    // Work on a counterclockwise outline, then flip the triangles at the end if needed
+    let is_clockwise = signed_area(outer) < T::zero();
+    let mut polygon: Vec<usize> = (0..outer.len()).collect();
+    if is_clockwise {
+        polygon.reverse();
+    }
+
+    // Holes must wind the opposite direction of the outline (clockwise)
+    let mut hole_start = outer.len();
+    let mut hole_polygons: Vec<Vec<usize>> = holes
+        .iter()
+        .filter(|hole| hole.len() >= 3)
+        .map(|hole| {
+            let mut ids: Vec<usize> = (hole_start..hole_start + hole.len()).collect();
+            hole_start += hole.len();
+            if signed_area(hole) > T::zero() {
+                ids.reverse();
+            }
+            ids
+        })
+        .collect();
+    // Bridging the right-most holes first guarantees that the bridge
+    // to each hole cannot cross a hole that has not been merged yet
+    let rightmost = |hole: &Vec<usize>| {
+        hole.iter()
+            .map(|&i| all_points[i].x)
+            .fold(T::neg_infinity(), T::max)
+    };
+    hole_polygons.sort_by(|a, b| rightmost(b).partial_cmp(&rightmost(a)).unwrap());
+    for hole in hole_polygons {
+        bridge_hole(&all_points, &mut polygon, &hole);
+    }
+
+    let mut triangles = clip_ears(&all_points, polygon);
+    if is_clockwise {
+        for triangle in &mut triangles {
+            triangle.swap(1, 2);
+        }
+    }
+    triangles
+}
+
+/// Splices a (clockwise) hole into the (counterclockwise) polygon,
+/// by connecting the hole's right-most point to a point on the polygon that it can "see"
+fn bridge_hole<T: Number>(points: &[Point2<T>], polygon: &mut Vec<usize>, hole: &[usize]) {
+    let (hole_index, &hole_point_id) = hole
+        .iter()
+        .enumerate()
+        .max_by(|(_, &a), (_, &b)| points[a].x.partial_cmp(&points[b].x).unwrap())
+        .unwrap();
+    let m = points[hole_point_id];
+
+    // Cast a ray in the +x direction from m, and find the closest polygon edge that it hits
+    let mut closest: Option<(T, usize)> = None;
+    for i in 0..polygon.len() {
+        let a = points[polygon[i]];
+        let b = points[polygon[(i + 1) % polygon.len()]];
+        if (a.y > m.y) == (b.y > m.y) {
+            continue;
+        }
+        let x = a.x + (m.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x < m.x {
+            continue;
+        }
+        let is_closer = match closest {
+            Some((closest_x, _)) => x < closest_x,
+            None => true,
+        };
+        if is_closer {
+            // Of the edge's two points, the one further right is a bridge candidate
+            let candidate = if a.x > b.x {
+                i
+            } else {
+                (i + 1) % polygon.len()
+            };
+            closest = Some((x, candidate));
+        }
+    }
+    let Some((hit_x, mut bridge)) = closest else {
+        // The hole is not inside the polygon, so there is nothing to cut out
+        return;
+    };
+
+    // The candidate might be hidden behind a reflex corner of the polygon.
+    // If any reflex points are inside the triangle (m, hit, candidate),
+    // the one with the smallest angle to the ray is visible instead
+    let hit = Point2::new(hit_x, m.y);
+    let candidate = points[polygon[bridge]];
+    let (tri_b, tri_c) = if candidate.y < m.y {
+        (candidate, hit)
+    } else {
+        (hit, candidate)
+    };
+    let mut best_slope = T::infinity();
+    for i in 0..polygon.len() {
+        let p = points[polygon[i]];
+        if i == bridge || p == m {
+            continue;
+        }
+        let prev = points[polygon[(i + polygon.len() - 1) % polygon.len()]];
+        let next = points[polygon[(i + 1) % polygon.len()]];
+        let is_reflex = turn(&prev, &p, &next) < T::zero();
+        if is_reflex && in_triangle(&p, &m, &tri_b, &tri_c) {
+            let slope = Float::abs((p.y - m.y) / (p.x - m.x));
+            if slope < best_slope {
+                best_slope = slope;
+                bridge = i;
+            }
+        }
+    }
+
+    // polygon[..=bridge], hole (starting and ending at m), then back to the bridge point
+    let mut spliced: Vec<usize> = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    spliced.extend_from_slice(&polygon[..=bridge]);
+    spliced.extend(
+        hole[hole_index..]
+            .iter()
+            .chain(&hole[..=hole_index])
+            .copied(),
+    );
+    spliced.extend_from_slice(&polygon[bridge..]);
+    *polygon = spliced;
+}
+
+/// Repeatedly removes "ears" (convex corners with no other points inside)
+/// from a counterclockwise polygon until only triangles remain
+fn clip_ears<T: Number>(points: &[Point2<T>], mut polygon: Vec<usize>) -> Vec<[usize; 3]> {
+    let zero = T::zero();
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    let mut i = 0;
+    // Number of corners visited in a row without finding an ear
+    let mut misses = 0;
+    while polygon.len() > 3 {
+        let len = polygon.len();
+        let prev = polygon[(i + len - 1) % len];
+        let curr = polygon[i % len];
+        let next = polygon[(i + 1) % len];
+        let (a, b, c) = (points[prev], points[curr], points[next]);
+        let t = turn(&a, &b, &c);
+
+        let is_ear = if t == zero {
+            // A collinear corner contributes no area, so it can be dropped
+            // (as long as it isn't a spike that doubles back on itself)
+            (b - a).dot(&(c - b)) >= zero
+        } else {
+            t > zero
+                && !polygon.iter().any(|&other| {
+                    let p = points[other];
+                    // Bridged holes repeat points, which must not block their own ears
+                    p != a && p != b && p != c && in_triangle(&p, &a, &b, &c)
+                })
+        };
+
+        // Give up on finding a proper ear once every corner has been tried,
+        // the input is self-intersecting or otherwise degenerate,
+        // so clipping anything is better than looping forever
+        if is_ear || misses > len {
+            if t != zero {
+                triangles.push([prev, curr, next]);
+            }
+            polygon.remove(i % len);
+            misses = 0;
+        } else {
+            i += 1;
+            misses += 1;
+        }
+        i %= polygon.len();
+    }
+    if polygon.len() == 3
+        && turn(
+            &points[polygon[0]],
+            &points[polygon[1]],
+            &points[polygon[2]],
+        ) != zero
+    {
+        triangles.push([polygon[0], polygon[1], polygon[2]]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area_of(points: &[Point2<f64>], triangles: &[[usize; 3]]) -> f64 {
+        triangles
+            .iter()
+            .map(|&[a, b, c]| signed_area(&[points[a], points[b], points[c]]) / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn test_convexity() {
+        let square = [
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ];
+        assert!(is_convex(&square));
+        let arrow = [
+            Point2::new(0.0, 0.0),
+            Point2::new(2.0, 1.0),
+            Point2::new(0.0, 2.0),
+            Point2::new(1.0, 1.0),
+        ];
+        assert!(!is_convex(&arrow));
+    }
+
+    #[test]
+    fn test_concave() {
+        // An "L" shape, wound counterclockwise, with area 3
+        let l_shape = [
+            Point2::new(0.0, 0.0),
+            Point2::new(2.0, 0.0),
+            Point2::new(2.0, 1.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(1.0, 2.0),
+            Point2::new(0.0, 2.0),
+        ];
+        let triangles = ear_clip(&l_shape, &[]);
+        assert_eq!(triangles.len(), 4);
+        assert!((area_of(&l_shape, &triangles) - 3.0).abs() < 1e-9);
+
+        // Clockwise input produces clockwise triangles
+        let reversed: Vec<_> = l_shape.iter().rev().copied().collect();
+        let triangles = ear_clip(&reversed, &[]);
+        assert_eq!(triangles.len(), 4);
+        assert!((area_of(&reversed, &triangles) + 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_collinear_points() {
+        // A square with an extra point in the middle of the bottom edge
+        let square = [
+            Point2::new(0.0, 0.0),
+            Point2::new(0.5, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ];
+        let triangles = ear_clip(&square, &[]);
+        assert!((area_of(&square, &triangles) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hole() {
+        // 4x4 square with a 2x2 hole in the middle, area 12
+        let outer = [
+            Point2::new(0.0, 0.0),
+            Point2::new(4.0, 0.0),
+            Point2::new(4.0, 4.0),
+            Point2::new(0.0, 4.0),
+        ];
+        let hole = vec![
+            Point2::new(1.0, 1.0),
+            Point2::new(3.0, 1.0),
+            Point2::new(3.0, 3.0),
+            Point2::new(1.0, 3.0),
+        ];
+        let all_points: Vec<_> = outer.iter().chain(&hole).copied().collect();
+        let triangles = ear_clip(&outer, &[hole]);
+        assert_eq!(triangles.len(), 8);
+        assert!((area_of(&all_points, &triangles) - 12.0).abs() < 1e-9);
+        // No triangle covers the middle of the hole
+        let center = Point2::new(2.0, 2.0);
+        assert!(!triangles.iter().any(|&[a, b, c]| in_triangle(
+            &center,
+            &all_points[a],
+            &all_points[b],
+            &all_points[c]
+        )));
+    }
+}
@@ -0,0 +1,142 @@
+//! Units of length, for models that were drawn in different units than the scene they're loaded into
+//! (Onshape exports OBJ files in metres, whatever the part was designed in)
+
+use std::{fmt, str::FromStr};
+
+use nalgebra::Matrix4;
+
+use crate::face::Face;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthUnit {
+    Millimetres,
+    Centimetres,
+    #[default]
+    Metres,
+    Inches,
+    Feet,
+}
+
+impl LengthUnit {
+    /// How many metres one of this unit is
+    pub const fn metres(self) -> f64 {
+        match self {
+            LengthUnit::Millimetres => 0.001,
+            LengthUnit::Centimetres => 0.01,
+            LengthUnit::Metres => 1.0,
+            LengthUnit::Inches => 0.0254,
+            LengthUnit::Feet => 0.3048,
+        }
+    }
+
+    /// What lengths in this unit have to be multiplied by to be in `target`
+    pub fn scale_to(self, target: LengthUnit) -> f64 {
+        if self == target {
+            1.0
+        } else {
+            self.metres() / target.metres()
+        }
+    }
+
+    /// The unit's abbreviation, as written in files
+    pub fn symbol(self) -> &'static str {
+        match self {
+            LengthUnit::Millimetres => "mm",
+            LengthUnit::Centimetres => "cm",
+            LengthUnit::Metres => "m",
+            LengthUnit::Inches => "in",
+            LengthUnit::Feet => "ft",
+        }
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// A unit that isn't one of `mm`, `cm`, `m`, `in` or `ft`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownUnit(pub String);
+
+impl fmt::Display for UnknownUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown unit {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownUnit {}
+
+impl FromStr for LengthUnit {
+    type Err = UnknownUnit;
+
+    fn from_str(symbol: &str) -> Result<Self, Self::Err> {
+        IntoIterator::into_iter([
+            LengthUnit::Millimetres,
+            LengthUnit::Centimetres,
+            LengthUnit::Metres,
+            LengthUnit::Inches,
+            LengthUnit::Feet,
+        ])
+        .find(|unit| unit.symbol() == symbol)
+        .ok_or_else(|| UnknownUnit(symbol.to_string()))
+    }
+}
+
+pub fn scale_faces(faces: &[Face<f64>], factor: f64) -> Vec<Face<f64>> {
+    faces.iter().map(|face| face.scaled(factor)).collect()
+}
+
+/// The same transform for a model that has been scaled by `factor`:
+/// the translation is scaled, and the rotation and scale are left alone
+pub fn scale_transform(transform: &Matrix4<f64>, factor: f64) -> Matrix4<f64> {
+    let mut scaled = *transform;
+    for row in 0..3 {
+        scaled[(row, 3)] *= factor;
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{point, Translation3, UnitQuaternion, Vector3};
+
+    use super::*;
+
+    #[test]
+    fn test_units() {
+        assert_eq!(LengthUnit::Inches.scale_to(LengthUnit::Metres), 0.0254);
+        assert!((LengthUnit::Feet.scale_to(LengthUnit::Inches) - 12.0).abs() < 1e-12);
+        assert_eq!(LengthUnit::Feet.scale_to(LengthUnit::Feet), 1.0);
+        assert_eq!("mm".parse(), Ok(LengthUnit::Millimetres));
+        assert_eq!(LengthUnit::Centimetres.to_string(), "cm");
+        assert_eq!(
+            "yd".parse::<LengthUnit>(),
+            Err(UnknownUnit("yd".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_scale_transform() {
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.5);
+        let transform =
+            Translation3::new(1.0, 2.0, 3.0).to_homogeneous() * rotation.to_homogeneous();
+        let face = Face::new(vec![
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![0.0, 1.0, 0.0],
+        ]);
+        let factor = LengthUnit::Inches.scale_to(LengthUnit::Millimetres);
+        let scaled_face = &scale_faces(std::slice::from_ref(&face), factor)[0];
+        let scaled_transform = scale_transform(&transform, factor);
+
+        // Scaling the model and its transform is the same as scaling where it ends up
+        for (point, scaled_point) in face.points().iter().zip(scaled_face.points()) {
+            let expected = transform.transform_point(point) * factor;
+            assert!((scaled_transform.transform_point(scaled_point) - expected).norm() < 1e-9);
+        }
+        assert_eq!(scaled_face.normal(), face.normal());
+        assert_eq!(scaled_face.bounding_box().max_pt, point![25.4, 25.4, 0.0]);
+    }
+}
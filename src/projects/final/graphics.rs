@@ -1,4 +1,5 @@
 extern crate console_error_panic_hook;
+extern crate graphics_core;
 pub(crate) extern crate nalgebra;
 pub(crate) extern crate num_traits;
 pub(crate) extern crate parry2d_f64 as parry2d;
@@ -6,84 +7,23 @@ pub(crate) extern crate parry3d_f64 as parry3d;
 pub(crate) extern crate rand;
 pub(crate) extern crate rand_chacha;
 pub(crate) extern crate wasm_bindgen;
-mod face;
 
-use face::Face;
-use nalgebra::{
-    point, vector, Matrix4, Point3, Rotation3, Scale3, Translation3, Unit, UnitQuaternion,
-    UnitVector3, Vector3,
+use graphics_core::{
+    camera::{Camera, CameraInput},
+    console_log,
+    face::Face,
+    fixed_timestep::FixedTimestep,
+    transform_matrix::TransformMatrix,
 };
+use nalgebra::{point, vector, Point3, Unit, UnitVector3, Vector3};
 use wasm_bindgen::prelude::*;
 
-pub(crate) trait Number:
-    'static
-    + std::fmt::Debug
-    + Copy
-    + nalgebra::ClosedAdd
-    + nalgebra::ClosedSub
-    + nalgebra::ClosedMul
-    + num_traits::identities::Zero
-    + nalgebra::SimdComplexField
-    + nalgebra::SimdRealField
-    + num_traits::Float
-    + std::convert::Into<f64>
-    + std::convert::From<f32>
-{
-}
-impl Number for f64 {}
-impl Number for f32 {}
-
 const NUM_CHANNELS: usize = 4; // R, G, B, A
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log(s: String);
-}
-
-#[macro_export]
-macro_rules! console_log {
-    ($($t:tt)*) => {
-        #[cfg(target_arch = "wasm32")] {
-            $crate::log(format!($($t)*));
-        }
-        #[cfg(not(target_arch = "wasm32"))] {
-            println!($($t)*);
-        }
-    };
-}
-
 #[wasm_bindgen]
 pub struct GameState {
-    camera_position: Point3<f64>,
-    camera_direction: UnitVector3<f64>,
-    camera_velocity: Vector3<f64>,
-    aspect_ratio: f64,
-}
-
-/// A little wrapper around the nalgebra matrix4 class, for JS use,
-/// so we only have to generate binding code for the methods we actually need.
-#[wasm_bindgen]
-#[derive(Clone, Copy)]
-pub struct TransformMatrix(Matrix4<f64>);
-
-#[wasm_bindgen]
-impl TransformMatrix {
-    pub fn translation(x: f64, y: f64, z: f64) -> Self {
-        Self(Translation3::new(x, y, z).to_homogeneous())
-    }
-    pub fn rotation_euler(roll: f64, pitch: f64, yaw: f64) -> Self {
-        Self(Rotation3::from_euler_angles(roll, pitch, yaw).to_homogeneous())
-    }
-    pub fn identity() -> Self {
-        Self(Matrix4::identity())
-    }
-    pub fn to_f64_array(&self) -> Vec<f64> {
-        self.0.as_slice().to_vec()
-    }
-    pub fn times(&self, other: &TransformMatrix) -> TransformMatrix {
-        Self(self.0 * other.0)
-    }
+    camera: Camera,
+    timestep: FixedTimestep,
 }
 
 #[wasm_bindgen]
@@ -93,10 +33,11 @@ impl GameState {
     pub fn new() -> Self {
         console_error_panic_hook::set_once();
         Self {
-            aspect_ratio: 1.0,
-            camera_position: point![0.0, -10.0, 0.0],
-            camera_direction: UnitVector3::new_normalize(vector![-1.0, 0.0, 0.0]),
-            camera_velocity: Vector3::zeros(),
+            camera: Camera::new(
+                point![0.0, -10.0, 0.0],
+                UnitVector3::new_normalize(vector![-1.0, 0.0, 0.0]),
+            ),
+            timestep: FixedTimestep::default(),
         }
     }
 
@@ -120,158 +61,59 @@ impl GameState {
     ) {
         if !is_active {
             // Reset the velocity so when the frame becomes active again it doesn't jump
-            self.camera_velocity = Vector3::zeros();
+            self.camera.velocity = Vector3::zeros();
             // Don't update the position if not active
             return;
         }
-        let up = self.up().into_inner();
-        // In seconds, time since last render
-        let dt = delta_time_ms as f64 / 1000.0;
-        let rotation_scale = 0.01;
-        let rotation_x = rotation_scale * cursor_movement_x;
-        let rotation_y = rotation_scale * cursor_movement_y;
-        let forwards = self.camera_direction.into_inner();
-        let right = forwards.cross(&up);
-        self.camera_direction = UnitVector3::new_normalize(
-            UnitQuaternion::new(up * rotation_x + right * rotation_y)
-                .transform_vector(&self.camera_direction),
-        );
-        self.camera_position += self.camera_velocity * dt;
-        let accel = 15.0;
-        let decel = 0.15;
-        let forwards = self.camera_direction.into_inner();
-        let right = forwards.cross(&up);
-        if input_w {
-            self.camera_velocity += accel * dt * forwards;
-        } else if input_s {
-            self.camera_velocity -= accel * dt * forwards;
-        } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&forwards)) * forwards;
-        }
-        if input_a {
-            self.camera_velocity += accel * dt * right;
-        } else if input_d {
-            self.camera_velocity -= accel * dt * right;
-        } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&right)) * right;
+        let input = CameraInput {
+            w: input_w,
+            a: input_a,
+            s: input_s,
+            d: input_d,
+            cursor_movement_x,
+            cursor_movement_y,
+        };
+        let up = self.up();
+        self.camera.rotate(&up, &input);
+        // Moves in fixed steps, so it moves the same at any frame rate
+        for _ in 0..self.timestep.advance(delta_time_ms as f64) {
+            self.camera.step(&up, &input, self.timestep.dt());
         }
-        // There was a bug where when turning the camera it could continue drifting
-        // Since the third component of the "camera directions" didn't have code to handle deceleration
-        // So I added this
-        let camera_up = forwards.cross(&right);
-        self.camera_velocity -= decel * (self.camera_velocity.dot(&camera_up)) * camera_up;
+    }
+
+    /// Where the camera is drawn, between its last two steps
+    fn rendered_camera(&self) -> Camera {
+        self.camera.interpolated(self.timestep.alpha())
     }
 
     pub fn camera_position(&self) -> Vec<f32> {
-        vec![
-            self.camera_position.x as _,
-            self.camera_position.y as _,
-            self.camera_position.z as _,
-        ]
+        let position = self.rendered_camera().position;
+        vec![position.x as _, position.y as _, position.z as _]
     }
 
     pub fn world_to_camera(&self) -> TransformMatrix {
-        self.world_to_camera_without_camera_translation()
-            .times(&TransformMatrix(Matrix4::new_translation(&Vector3::new(
-                -self.camera_position.x,
-                -self.camera_position.y,
-                -self.camera_position.z,
-            ))))
+        self.rendered_camera().world_to_camera(&self.up())
     }
 
     pub fn world_to_camera_without_camera_translation(&self) -> TransformMatrix {
-        TransformMatrix(
-            // Scale everything in the z direction down
-            // (does not affect the positions of any vertices)
-            // This just reduces the scope of z values to reduce clipping
-            Scale3::new(1.0, 1.0, 0.01).to_homogeneous()
-                * Matrix4::new_perspective(self.aspect_ratio, 30.0, 0.01, 100.0)
-                * Matrix4::look_at_rh(
-                    &Point3::origin(),
-                    &Point3::new(
-                        self.camera_direction.x,
-                        self.camera_direction.y,
-                        self.camera_direction.z,
-                    ),
-                    &self.up(),
-                ),
-        )
+        self.rendered_camera()
+            .world_to_camera_without_camera_translation(&self.up())
     }
 
     #[wasm_bindgen(getter)]
     pub fn aspect_ratio(&self) -> f64 {
-        self.aspect_ratio
+        self.camera.aspect_ratio
     }
 
     #[wasm_bindgen(setter)]
     pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
-        self.aspect_ratio = aspect_ratio;
+        self.camera.aspect_ratio = aspect_ratio;
     }
 }
 
 #[wasm_bindgen]
 pub fn generate_skybox_points() -> Vec<f32> {
-    let faces = {
-        let scale = 100.0;
-        let front_right_bottom = Point3::new(0.5, -0.5, 0.5) * scale;
-        let front_left_bottom = Point3::new(-0.5, -0.5, 0.5) * scale;
-        let front_right_top = Point3::new(0.5, 0.5, 0.5) * scale;
-        let front_left_top = Point3::new(-0.5, 0.5, 0.5) * scale;
-
-        let back_right_bottom = Point3::new(0.5, -0.5, -0.5) * scale;
-        let back_left_bottom = Point3::new(-0.5, -0.5, -0.5) * scale;
-        let back_right_top = Point3::new(0.5, 0.5, -0.5) * scale;
-        let back_left_top = Point3::new(-0.5, 0.5, -0.5) * scale;
-
-        vec![
-            Face::new(vec![
-                front_right_top,
-                front_right_bottom,
-                front_left_bottom,
-                front_left_top,
-            ]),
-            Face::new(vec![
-                front_right_top,
-                back_right_top,
-                back_right_bottom,
-                front_right_bottom,
-            ]),
-            Face::new(vec![
-                front_left_top,
-                front_left_bottom,
-                back_left_bottom,
-                back_left_top,
-            ]),
-            Face::new(vec![
-                front_right_top,
-                front_left_top,
-                back_left_top,
-                back_right_top,
-            ]),
-            Face::new(vec![
-                front_right_bottom,
-                back_right_bottom,
-                back_left_bottom,
-                front_left_bottom,
-            ]),
-            Face::new(vec![
-                back_right_top,
-                back_left_top,
-                back_left_bottom,
-                back_right_bottom,
-            ]),
-        ]
-    };
-
-    let points: Vec<_> = faces
-        .iter()
-        .flat_map(|face| face.break_into_triangles())
-        .collect();
-
-    points
-        .iter()
-        .flat_map(|point| [point.x as _, point.y as _, point.z as _, 1.0])
-        .collect()
+    graphics_core::skybox::generate_skybox_points()
 }
 
 #[wasm_bindgen]
@@ -511,3 +353,124 @@ pub fn layers_to_mesh(layers: &[u8], num_layers: usize) -> ColoredMesh {
 
     layers_to_mesh_from_bools(dimension, &pixel_layers)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_core::{
+        png::Image,
+        rasterizer::{assert_matches_golden, Attribute, Rasterizer},
+    };
+    use nalgebra::Vector4;
+
+    /// The same pixels layer-fragment-shader.glsl renders for each layer
+    /// (with the default zoom and center of view), as RGBA bytes.
+    /// Like in app.tsx, the imaginary part of the julia constant changes with each layer.
+    fn julia_layers(dimension: usize, num_layers: usize, min_c: f64, max_c: f64) -> Vec<u8> {
+        const MAX_N: usize = 100;
+        let step = (max_c - min_c) / num_layers as f64;
+        let mut pixels = vec![];
+        for layer in 0..num_layers {
+            let julia_c = vector![0.0, layer as f64 * step + min_c];
+            // readPixels starts at the bottom row
+            for row in 0..dimension {
+                for col in 0..dimension {
+                    let c = vector![
+                        (col as f64 + 0.5) / dimension as f64 * 2.0 - 1.0,
+                        (row as f64 + 0.5) / dimension as f64 * 2.0 - 1.0
+                    ];
+                    let mut z = c;
+                    let n = (0..MAX_N)
+                        .find(|_| {
+                            let (x2, y2) = (z.x * z.x, z.y * z.y);
+                            z = vector![x2 - y2, 2.0 * z.x * z.y] + julia_c;
+                            x2 + y2 > 4.0
+                        })
+                        .unwrap_or(MAX_N);
+                    if n >= MAX_N {
+                        pixels.extend([0, 0, 0, 255]);
+                    } else {
+                        let t = (10.0 * n as f64 / MAX_N as f64).clamp(0.0, 1.0);
+                        pixels.extend([255, (t * 255.0) as u8, (t * 255.0) as u8, 255]);
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Draws a mesh the way vertex-shader.glsl and fragment-shader.glsl do
+    fn render(
+        game_state: &mut GameState,
+        mesh: &ColoredMesh,
+        width: usize,
+        height: usize,
+    ) -> Image {
+        game_state.set_aspect_ratio(width as f64 / height as f64);
+        let camera_transform = *game_state.world_to_camera().matrix();
+        let mut rasterizer = Rasterizer::new(width, height, vector![0.0, 0.0, 0.0, 1.0]);
+        rasterizer.draw_triangles(
+            &[
+                Attribute::new(&mesh.points, 4),
+                Attribute::new(&mesh.colors, 4),
+            ],
+            |attributes| {
+                let vertex = Vector4::<f32>::from_column_slice(attributes[0]).cast::<f64>();
+                let color = attributes[1]
+                    .iter()
+                    .map(|&channel| channel as f64)
+                    .collect();
+                (camera_transform * vertex, color)
+            },
+            Vector4::from_column_slice,
+        );
+        rasterizer.into_image()
+    }
+
+    // Each face is 2 triangles, 3 points each, 4 floats per point
+    const FLOATS_PER_FACE: usize = 2 * 3 * 4;
+
+    #[test]
+    fn test_outside_counts_as_filled() {
+        // Faces on the edge of the volume are never visible, so they aren't emitted
+        let mesh = layers_to_mesh_from_bools(1, &[vec![(true, 0)]]);
+        assert!(mesh.points.is_empty());
+    }
+
+    #[test]
+    fn test_only_faces_next_to_empty_pixels() {
+        // The top row is filled, so each filled pixel only borders one empty pixel
+        let mesh =
+            layers_to_mesh_from_bools(2, &[vec![(true, 0), (true, 0), (false, 0), (false, 0)]]);
+        assert_eq!(mesh.points.len(), 2 * FLOATS_PER_FACE);
+        assert_eq!(mesh.colors.len(), mesh.points.len());
+    }
+
+    #[test]
+    fn test_layers_to_mesh() {
+        // Two 2x2 RGBA layers, with only the first pixel of the bottom layer filled.
+        // It borders two empty pixels in its layer and one in the layer above.
+        let mut layers = vec![0; 2 * 2 * 2 * NUM_CHANNELS];
+        layers[0] = 255;
+        let mesh = layers_to_mesh(&layers, 2);
+        assert_eq!(mesh.points.len(), 3 * FLOATS_PER_FACE);
+        assert_eq!(mesh.colors.len(), mesh.points.len());
+    }
+
+    #[test]
+    fn test_golden_fractal() {
+        let dimension = 32;
+        let num_layers = 16;
+        let mesh = layers_to_mesh(&julia_layers(dimension, num_layers, -0.1, 0.1), num_layers);
+        let mut game_state = GameState::new();
+        game_state.camera = Camera::new(
+            point![0.0, 60.0, 70.0],
+            UnitVector3::new_normalize(vector![0.0, -68.0, -70.0]),
+        );
+        let image = render(&mut game_state, &mesh, 128, 96);
+        assert_matches_golden(
+            &image,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/golden/fractal.png"),
+        );
+    }
+}
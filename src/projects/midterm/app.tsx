@@ -27,6 +27,7 @@ export interface GameState {
     input_a: boolean;
     input_s: boolean;
     input_d: boolean;
+    input_space: boolean;
   };
   skybox_vert_buffer?: WebGLBuffer | null;
 }
@@ -48,6 +49,7 @@ export const Midterm = ({}: Props) => {
   );
   const [rust_module, set_rust_module] = useState<rust.InitOutput | null>(null);
   const render_ref = useRef<() => void>();
+  const state_ref = useRef<GameState | null>(null);
 
   const capture_screenshot = () => {
     const link = document.createElement("a");
@@ -57,6 +59,18 @@ export const Midterm = ({}: Props) => {
     link.click();
   };
 
+  const export_glb = () => {
+    const state = state_ref.current;
+    if (!state) return;
+    const link = document.createElement("a");
+    link.download = `maze-${state.random_seed}.glb`;
+    link.href = URL.createObjectURL(
+      new Blob([state.rust_state.export_glb()], { type: "model/gltf-binary" }),
+    );
+    link.click();
+    URL.revokeObjectURL(link.href);
+  };
+
   useEffect(() => {
     const m_listener = (e: KeyboardEvent) => {
       if (e.key === "m") capture_screenshot();
@@ -100,12 +114,15 @@ export const Midterm = ({}: Props) => {
         input_a: false,
         input_s: false,
         input_d: false,
+        input_space: false,
       },
       random_seed,
     };
     const { cleanup, render } = init_canvas(canvas, game_state);
     render_ref.current = render;
+    state_ref.current = game_state;
     return () => {
+      state_ref.current = null;
       cleanup();
       game_state?.rust_state.free();
     };
@@ -145,6 +162,7 @@ export const Midterm = ({}: Props) => {
         Randomize Maze
       </button>
       <button onClick={capture_screenshot}>Download screenshot</button>
+      <button onClick={export_glb}>Download glTF</button>
     </div>
   );
 };
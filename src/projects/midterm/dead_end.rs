@@ -1,11 +1,9 @@
+use graphics_core::face::Face;
 use nalgebra::{Point3, Unit, UnitVector3};
 
-use crate::{
-    face::Face,
-    maze::{
-        make_door, Connector, ConnectorIdentifier, Coupler, Environment, EnvironmentIdentifier,
-        MazeSkeleton,
-    },
+use crate::maze::{
+    make_door, Connector, ConnectorIdentifier, Coupler, Environment, EnvironmentIdentifier,
+    ExitFace, MazeSkeleton,
 };
 
 #[derive(Clone)]
@@ -70,7 +68,10 @@ impl DeadEnd {
             } else {
                 vec![door.to_face()]
             },
-            exit_faces: vec![(EnvironmentIdentifier::Tunnel(tunnel_id), door.to_face())],
+            exit_faces: vec![ExitFace::new(
+                EnvironmentIdentifier::Tunnel(tunnel_id),
+                &door.to_face(),
+            )],
             dead_end: self.clone(),
         }
     }
@@ -78,7 +79,7 @@ impl DeadEnd {
 
 pub(crate) struct DeadEndEnvironment {
     faces: Vec<Face<f64>>,
-    exit_faces: Vec<(EnvironmentIdentifier, Face<f64>)>,
+    exit_faces: Vec<ExitFace>,
     dead_end: DeadEnd,
 }
 
@@ -91,7 +92,7 @@ impl Environment for DeadEndEnvironment {
     fn faces(&self) -> &[Face<f64>] {
         &self.faces
     }
-    fn exit_faces(&self) -> &[(EnvironmentIdentifier, Face<f64>)] {
+    fn exit_faces(&self) -> &[ExitFace] {
         &self.exit_faces
     }
     fn up(&self, _camera_position: Point3<f64>) -> UnitVector3<f64> {
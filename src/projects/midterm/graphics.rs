@@ -1,4 +1,5 @@
 extern crate console_error_panic_hook;
+extern crate graphics_core;
 pub(crate) extern crate nalgebra;
 pub(crate) extern crate num_traits;
 pub(crate) extern crate parry2d_f64 as parry2d;
@@ -6,87 +7,53 @@ pub(crate) extern crate parry3d_f64 as parry3d;
 pub(crate) extern crate rand;
 pub(crate) extern crate rand_chacha;
 pub(crate) extern crate wasm_bindgen;
-mod bounding_box;
 mod dead_end;
-mod face;
 mod landing;
-mod load_obj;
 mod maze;
-mod ray;
 mod tunnel;
 
-use face::Face;
-use maze::{Environment, EnvironmentIdentifier, Maze};
-use nalgebra::{
-    vector, Matrix4, Point3, Scale3, Translation3, UnitQuaternion, UnitVector3, Vector3,
+use graphics_core::{
+    camera::{Camera, CameraInput},
+    character_controller::CharacterController,
+    console_log,
+    fixed_timestep::FixedTimestep,
+    ray::Ray,
+    transform_matrix::TransformMatrix,
 };
+use maze::{Environment, EnvironmentIdentifier, Maze, LANDING_RADIUS};
+use nalgebra::{vector, UnitVector3, Vector3};
 use wasm_bindgen::prelude::*;
 
-use crate::ray::Ray;
-
-pub(crate) trait Number:
-    'static
-    + std::fmt::Debug
-    + Copy
-    + nalgebra::ClosedAdd
-    + nalgebra::ClosedSub
-    + nalgebra::ClosedMul
-    + num_traits::identities::Zero
-    + nalgebra::SimdComplexField
-    + nalgebra::SimdRealField
-    + num_traits::Float
-    + std::convert::Into<f64>
-    + std::convert::From<f32>
-{
-}
-impl Number for f64 {}
-impl Number for f32 {}
-
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log(s: String);
-}
-
-#[macro_export]
-macro_rules! console_log {
-    ($($t:tt)*) => {
-        #[cfg(target_arch = "wasm32")] {
-            $crate::log(format!($($t)*));
-        }
-        #[cfg(not(target_arch = "wasm32"))] {
-            println!($($t)*);
-        }
-    };
-}
+/// How close the camera can get to the walls
+const CAMERA_RADIUS: f64 = 0.1;
+/// The tallest ledge the camera climbs over instead of sliding along
+const STEP_HEIGHT: f64 = 0.2;
+/// When walking, the camera is at eye height above the ground,
+/// on top of a body that's a sphere this big
+const BODY_RADIUS: f64 = 0.4;
+const EYE_HEIGHT: f64 = 1.6;
+/// In units per second squared, along -up
+const GRAVITY: f64 = 9.8;
+/// How fast a jump leaves the ground, in units per second
+const JUMP_SPEED: f64 = 5.0;
+/// Steps are split up into moves no further than this, so a move can't skip past a whole landing
+const MAX_STEP_DISTANCE: f64 = LANDING_RADIUS;
 
 #[wasm_bindgen]
 pub struct GameState {
-    camera_position: Point3<f64>,
-    camera_direction: UnitVector3<f64>,
-    camera_velocity: Vector3<f64>,
-    aspect_ratio: f64,
+    camera: Camera,
+    /// Keeps the camera from going through the walls
+    character: CharacterController,
+    /// Like `character`, for the body under the camera when walking
+    body: CharacterController,
+    /// Whether the camera walks on the floor, pulled down by gravity,
+    /// instead of flying around
+    walking: bool,
+    /// Whether the body was standing on the floor after the last update, so it can jump
+    grounded: bool,
     maze: Maze,
     current_environment: EnvironmentIdentifier,
-}
-
-/// A little wrapper around the nalgebra matrix4 class, for JS use,
-/// so we only have to generate binding code for the methods we actually need.
-#[wasm_bindgen]
-pub struct TransformMatrix(Matrix4<f64>);
-
-#[wasm_bindgen]
-impl TransformMatrix {
-    #[wasm_bindgen(constructor)]
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
-        Self(Translation3::new(x, y, z).to_homogeneous())
-    }
-    pub fn to_f64_array(&self) -> Vec<f64> {
-        self.0.as_slice().to_vec()
-    }
-    pub fn times(&self, other: &TransformMatrix) -> TransformMatrix {
-        Self(self.0 * other.0)
-    }
+    timestep: FixedTimestep,
 }
 
 #[wasm_bindgen]
@@ -97,17 +64,22 @@ impl GameState {
         let maze = Maze::generate(rng_seed);
         let starting_landing = maze.landings()[0].landing();
         Self {
-            aspect_ratio: 1.0,
-            camera_position: starting_landing.point,
-            camera_direction: UnitVector3::new_normalize(vector![0.0, 0.0, -1.0]),
-            camera_velocity: Vector3::zeros(),
+            camera: Camera::new(
+                starting_landing.point,
+                UnitVector3::new_normalize(vector![0.0, 0.0, -1.0]),
+            ),
+            character: CharacterController::new(CAMERA_RADIUS).with_step_height(STEP_HEIGHT),
+            body: CharacterController::new(BODY_RADIUS).with_step_height(STEP_HEIGHT),
+            walking: false,
+            grounded: false,
             current_environment: EnvironmentIdentifier::Landing(starting_landing.id),
             maze,
+            timestep: FixedTimestep::default(),
         }
     }
 
     fn up(&self) -> UnitVector3<f64> {
-        self.environment().up(self.camera_position)
+        self.environment().up(self.camera.position)
     }
 
     // It would be a good idea to have this accept the arguments as a struct,
@@ -119,99 +91,118 @@ impl GameState {
         input_a: bool,
         input_s: bool,
         input_d: bool,
+        input_space: bool,
         cursor_movement_x: f64,
         cursor_movement_y: f64,
         delta_time_ms: usize,
     ) {
-        let up = self.up().into_inner();
-        // In seconds, time since last render
-        let dt = delta_time_ms as f64 / 1000.0;
-        let rotation_scale = 0.01;
-        let rotation_x = rotation_scale * cursor_movement_x;
-        let rotation_y = rotation_scale * cursor_movement_y;
-        let forwards = self.camera_direction.into_inner();
-        let right = forwards.cross(&up);
-        self.camera_direction = UnitVector3::new_normalize(
-            UnitQuaternion::new(up * rotation_x + right * rotation_y)
-                .transform_vector(&self.camera_direction),
+        let up = self.up();
+        let input = CameraInput {
+            w: input_w,
+            a: input_a,
+            s: input_s,
+            d: input_d,
+            cursor_movement_x,
+            cursor_movement_y,
+        };
+        self.camera.rotate(&up, &input);
+        if self.walking && self.grounded && input_space {
+            // Take off from the ground, whatever speed it was falling at
+            self.camera.velocity += up.into_inner() * (JUMP_SPEED - self.camera.velocity.dot(&up));
+            self.grounded = false;
+        }
+        // Moves in fixed steps, so the camera follows the twist of the tunnels,
+        // gravity curves its path instead of sending it in a straight line,
+        // and it moves and collides the same way at any frame rate
+        for _ in 0..self.timestep.advance(delta_time_ms as f64) {
+            self.step(&input, self.timestep.dt());
+        }
+    }
+
+    /// Moves and then accelerates the camera, `dt` seconds
+    fn step(&mut self, input: &CameraInput, dt: f64) {
+        self.camera.begin_step();
+        // Fast steps are split up further, so the camera crosses into every environment along the way
+        let moves = (self.camera.velocity.norm() * dt / MAX_STEP_DISTANCE)
+            .ceil()
+            .max(1.0);
+        for _ in 0..moves as usize {
+            self.move_camera(dt / moves);
+        }
+        let up = self.up();
+        if self.walking {
+            self.camera.accelerate_walking(&up, input, dt);
+        } else {
+            self.camera.accelerate(&up, input, dt);
+        }
+    }
+
+    /// Moves the camera along its velocity for `dt` seconds,
+    /// switching to whichever environment it crosses into
+    fn move_camera(&mut self, dt: f64) {
+        let up = self.up();
+        if self.walking {
+            self.camera.velocity -= up.into_inner() * GRAVITY * dt;
+        }
+        // Slide along whatever walls the camera runs into, instead of stopping dead.
+        // The whole path is swept, so it can't skip over a wall no matter how fast it goes.
+        let (character, eye_offset) = if self.walking {
+            (&self.body, up.into_inner() * (EYE_HEIGHT - BODY_RADIUS))
+        } else {
+            (&self.character, Vector3::zeros())
+        };
+        let movement = character.move_and_slide(
+            self.maze.bvh(),
+            self.camera.position - eye_offset,
+            self.camera.velocity,
+            dt,
+            &up,
         );
-        let new_camera_position = self.camera_position + self.camera_velocity * dt;
-        let camera_movement_ray = Ray::new(self.camera_position, new_camera_position);
+        let new_camera_position = movement.position + eye_offset;
+        let camera_movement_ray = Ray::new(self.camera.position, new_camera_position);
         let environment = self.environment();
         // If the camera passes through an "exit face",
         // the camera has switched to a new environment.
-        let exit_faces = environment.exit_faces();
-        let new_environment = exit_faces.iter().find_map(|(new_environment, exit_face)| {
-            let crosses = parry3d::query::intersection_test(
-                &nalgebra::Isometry::identity(),
-                &exit_face.to_convex_polyhedron(),
-                &nalgebra::Isometry::identity(),
-                &camera_movement_ray.to_segment(),
-            )
-            .unwrap();
-            if crosses {
-                Some(new_environment)
-            } else {
-                None
-            }
-        });
-        if let Some(&new_environment) = new_environment {
+        let new_environment = environment
+            .exit_faces()
+            .iter()
+            .find(|exit_face| exit_face.is_crossed_by(&camera_movement_ray))
+            .map(|exit_face| exit_face.environment);
+        if let Some(new_environment) = new_environment {
             if let EnvironmentIdentifier::DeadEnd(id) = new_environment {
                 // Since a dead end is not a space you can be "in", we are ignoring this
                 // _unless_ it is the maze exit
                 // in that case we allow you to pass into it
-                if self.maze.dead_ends()[id].dead_end().is_exit {
-                    console_log!("Crossed into exit! {:?}", new_environment);
-                    self.current_environment = new_environment;
-                    self.camera_position = new_camera_position;
+                if !self.maze.dead_ends()[id].dead_end().is_exit {
+                    return;
                 }
+                console_log!("Crossed into exit! {:?}", new_environment);
             } else {
                 console_log!("Crossed into {:?}", new_environment);
-                self.current_environment = new_environment;
-                self.camera_position = new_camera_position;
             }
-        } else {
-            let intersecting_face = self.environment().faces().iter().find(|face| {
-                let dist = parry3d::query::distance(
-                    &nalgebra::Isometry::identity(),
-                    &face.to_convex_polyhedron(),
-                    &nalgebra::Isometry::identity(),
-                    &camera_movement_ray.to_segment(),
-                )
-                .unwrap();
-                dist < 0.1
-            });
-            // Uncomment to disable collision detection
-            // let intersecting_face: Option<Face<f64>> = None;
-            if let Some(_intersecting_face) = intersecting_face {
-                self.camera_velocity = Vector3::zeros();
-            } else {
-                self.camera_position = new_camera_position;
-            }
-        }
-        let accel = 15.0;
-        let decel = 0.15;
-        let forwards = self.camera_direction.into_inner();
-        let right = forwards.cross(&up);
-        if input_w {
-            self.camera_velocity += accel * dt * forwards;
-        } else if input_s {
-            self.camera_velocity -= accel * dt * forwards;
-        } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&forwards)) * forwards;
+            self.current_environment = new_environment;
         }
-        if input_a {
-            self.camera_velocity += accel * dt * right;
-        } else if input_d {
-            self.camera_velocity -= accel * dt * right;
-        } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&right)) * right;
-        }
-        // There was a bug where when turning the camera it could continue drifting
-        // Since the third component of the "camera directions" didn't have code to handle deceleration
-        // So I added this
-        let camera_up = forwards.cross(&right);
-        self.camera_velocity -= decel * (self.camera_velocity.dot(&camera_up)) * camera_up;
+        self.camera.position = new_camera_position;
+        self.camera.velocity = movement.velocity;
+        self.grounded = movement
+            .contacts
+            .iter()
+            .any(|contact| contact.is_floor(&up))
+            || character
+                .ground(self.maze.bvh(), &movement.position, &up)
+                .is_some();
+    }
+
+    /// Whether the camera walks on the floor instead of flying around
+    #[wasm_bindgen(getter)]
+    pub fn walking(&self) -> bool {
+        self.walking
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_walking(&mut self, walking: bool) {
+        self.walking = walking;
+        self.grounded = false;
     }
 
     #[inline]
@@ -223,42 +214,28 @@ impl GameState {
         }
     }
 
+    /// Where the camera is drawn, between its last two steps
+    fn rendered_camera(&self) -> Camera {
+        self.camera.interpolated(self.timestep.alpha())
+    }
+
     pub fn world_to_camera(&self) -> TransformMatrix {
-        self.world_to_camera_without_camera_translation()
-            .times(&TransformMatrix(Matrix4::new_translation(&Vector3::new(
-                -self.camera_position.x,
-                -self.camera_position.y,
-                -self.camera_position.z,
-            ))))
+        self.rendered_camera().world_to_camera(&self.up())
     }
 
     pub fn world_to_camera_without_camera_translation(&self) -> TransformMatrix {
-        TransformMatrix(
-            // Scale everything in the z direction down
-            // (does not affect the positions of any vertices)
-            // This just reduces the scope of z values to reduce clipping
-            Scale3::new(1.0, 1.0, 0.01).to_homogeneous()
-                * Matrix4::new_perspective(self.aspect_ratio, 30.0, 0.01, 100.0)
-                * Matrix4::look_at_rh(
-                    &Point3::origin(),
-                    &Point3::new(
-                        self.camera_direction.x,
-                        self.camera_direction.y,
-                        self.camera_direction.z,
-                    ),
-                    &self.up(),
-                ),
-        )
+        self.rendered_camera()
+            .world_to_camera_without_camera_translation(&self.up())
     }
 
     #[wasm_bindgen(getter)]
     pub fn aspect_ratio(&self) -> f64 {
-        self.aspect_ratio
+        self.camera.aspect_ratio
     }
 
     #[wasm_bindgen(setter)]
     pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
-        self.aspect_ratio = aspect_ratio;
+        self.camera.aspect_ratio = aspect_ratio;
     }
 
     #[wasm_bindgen]
@@ -275,69 +252,309 @@ impl GameState {
     pub fn uvs_to_float32array(&self) -> Vec<f32> {
         self.maze.uvs_to_float32array()
     }
+
+    /// The maze as a GLB file, for opening in other tools
+    #[wasm_bindgen]
+    pub fn export_glb(&self) -> Vec<u8> {
+        self.maze.export_glb()
+    }
 }
 
 #[wasm_bindgen]
 pub fn generate_skybox_points() -> Vec<f32> {
-    let faces = {
-        let scale = 100.0;
-        let front_right_bottom = Point3::new(0.5, -0.5, 0.5) * scale;
-        let front_left_bottom = Point3::new(-0.5, -0.5, 0.5) * scale;
-        let front_right_top = Point3::new(0.5, 0.5, 0.5) * scale;
-        let front_left_top = Point3::new(-0.5, 0.5, 0.5) * scale;
-
-        let back_right_bottom = Point3::new(0.5, -0.5, -0.5) * scale;
-        let back_left_bottom = Point3::new(-0.5, -0.5, -0.5) * scale;
-        let back_right_top = Point3::new(0.5, 0.5, -0.5) * scale;
-        let back_left_top = Point3::new(-0.5, 0.5, -0.5) * scale;
-
-        vec![
-            Face::new(vec![
-                front_right_top,
-                front_right_bottom,
-                front_left_bottom,
-                front_left_top,
-            ]),
-            Face::new(vec![
-                front_right_top,
-                back_right_top,
-                back_right_bottom,
-                front_right_bottom,
-            ]),
-            Face::new(vec![
-                front_left_top,
-                front_left_bottom,
-                back_left_bottom,
-                back_left_top,
-            ]),
-            Face::new(vec![
-                front_right_top,
-                front_left_top,
-                back_left_top,
-                back_right_top,
-            ]),
-            Face::new(vec![
-                front_right_bottom,
-                back_right_bottom,
-                back_left_bottom,
-                front_left_bottom,
-            ]),
-            Face::new(vec![
-                back_right_top,
-                back_left_top,
-                back_left_bottom,
-                back_right_bottom,
-            ]),
-        ]
+    graphics_core::skybox::generate_skybox_points()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_core::{
+        bounding_box::BoundingBox,
+        png::Image,
+        rasterizer::{assert_matches_golden, Attribute, Rasterizer},
     };
+    use maze::TUNNEL_HEIGHT;
+    use nalgebra::Vector4;
 
-    let points: Vec<_> = faces
-        .iter()
-        .flat_map(|face| face.break_into_triangles())
-        .collect();
+    /// Draws the maze the way vertex-shader.glsl and fragment-shader.glsl do,
+    /// with a checkerboard standing in for the texture
+    fn render(game_state: &mut GameState, width: usize, height: usize) -> Image {
+        game_state.set_aspect_ratio(width as f64 / height as f64);
+        let transform = *game_state.world_to_camera().matrix();
+        let points = game_state.points_to_float32array();
+        let normals = game_state.normals_to_float32array();
+        let uvs = game_state.uvs_to_float32array();
 
-    points
-        .iter()
-        .flat_map(|point| [point.x as _, point.y as _, point.z as _, 1.0])
-        .collect()
+        let mut rasterizer = Rasterizer::new(width, height, vector![0.0, 0.0, 0.0, 1.0]);
+        rasterizer.draw_triangles(
+            &[
+                Attribute::new(&points, 4),
+                Attribute::new(&normals, 4),
+                Attribute::new(&uvs, 2),
+            ],
+            |attributes| {
+                let vertex = Vector4::<f32>::from_column_slice(attributes[0]).cast::<f64>();
+                let normal = Vector4::<f32>::from_column_slice(attributes[1])
+                    .cast::<f64>()
+                    .normalize();
+                let shade_1 = normal
+                    .dot(&vector![-0.5, -0.5, -0.5, 0.0].normalize())
+                    .clamp(0.0, 1.0);
+                let shade_2 = normal.dot(&vector![0.0, -1.0, 0.0, 0.0]).clamp(0.0, 1.0);
+                let shade_3 = normal.dot(&vector![0.0, 1.0, 0.0, 0.0]).clamp(0.0, 1.0);
+                let color = vector![0.7, 0.6, 0.5, 1.0] * shade_1
+                    + vector![0.4, 0.4, 0.4, 1.0] * shade_2
+                    + vector![0.1, 0.1, 0.2, 1.0] * shade_3
+                    + vector![0.0, 0.0, 0.0, 1.0];
+                let uv = attributes[2];
+                (
+                    transform * vertex,
+                    vec![color.x, color.y, color.z, uv[0] as f64, uv[1] as f64],
+                )
+            },
+            |varyings| {
+                let checker = ((varyings[3] * 4.0).floor() + (varyings[4] * 4.0).floor()) as i64;
+                let tex = if checker.rem_euclid(2) == 0 { 0.3 } else { 0.5 };
+                vector![
+                    tex + varyings[0] * 0.15,
+                    tex + varyings[1] * 0.15,
+                    tex + varyings[2] * 0.15,
+                    1.0
+                ]
+            },
+        );
+        rasterizer.into_image()
+    }
+
+    #[test]
+    fn test_golden_maze() {
+        let mut game_state = GameState::new(0);
+        let image = render(&mut game_state, 128, 96);
+        assert_matches_golden(
+            &image,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/golden/maze.png"),
+        );
+    }
+
+    #[test]
+    fn test_camera_stays_in_maze() {
+        for seed in 0..3 {
+            let mut game_state = GameState::new(seed);
+            let maze_points: Vec<_> = game_state
+                .maze
+                .faces()
+                .iter()
+                .flat_map(|face| face.points().to_vec())
+                .collect();
+            let maze_bounds = BoundingBox::from_points(&maze_points);
+            for frame in 0..300 {
+                // Fly forwards, turning every so often
+                let cursor_movement_x = if frame % 100 == 0 { 50.0 } else { 0.0 };
+                game_state.update(true, false, false, false, false, cursor_movement_x, 0.0, 16);
+                assert!(
+                    maze_bounds.includes_point(&game_state.camera.position),
+                    "seed {}: camera escaped to {}",
+                    seed,
+                    game_state.camera.position
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_camera_slides_along_walls() {
+        for seed in 0..3 {
+            let mut game_state = GameState::new(seed);
+            // Aim off to the side, so the camera runs into the walls at an angle
+            game_state.update(true, false, false, false, false, 30.0, 0.0, 16);
+            for frame in 0..300 {
+                let position = game_state.camera.position;
+                game_state.update(true, false, false, false, false, 0.0, 0.0, 16);
+                // Running into a wall at an angle, even the dead end at the end of the tunnel,
+                // slows the camera down but never stops it dead
+                assert!(
+                    (game_state.camera.position - position).norm() > 1e-6,
+                    "seed {}: camera stopped at {} on frame {}",
+                    seed,
+                    game_state.camera.position,
+                    frame
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_camera_never_passes_through_walls() {
+        for seed in 0..3 {
+            for direction in 0..8 {
+                for speed in [1000.0, 100000.0] {
+                    let mut game_state = GameState::new(seed);
+                    let angle = direction as f64 * std::f64::consts::TAU / 8.0;
+                    // Fired sideways and a little bit up or down, at the walls of the landing
+                    // or down a tunnel into the next landing's walls
+                    let velocity =
+                        vector![angle.cos(), (direction % 3) as f64 - 1.0, angle.sin()] * speed;
+                    for delta_time_ms in [16, 16, 16, 1000] {
+                        // Keep it going full speed, to try to push it through
+                        game_state.camera.velocity = velocity;
+                        game_state.update(
+                            false,
+                            false,
+                            false,
+                            false,
+                            false,
+                            0.0,
+                            0.0,
+                            delta_time_ms,
+                        );
+                        if let EnvironmentIdentifier::DeadEnd(_) = game_state.current_environment {
+                            // Made it out of the maze the right way
+                            break;
+                        }
+                        // Anywhere in the maze, there's a floor right below and a ceiling right above
+                        // (or a wall, in the corners of the tunnels where they twist).
+                        // Outside of it, at least one of them is missing.
+                        let floor_to_ceiling = height_above_floor(&game_state)
+                            .zip(height_below_ceiling(&game_state))
+                            .map(|(floor, ceiling)| floor + ceiling);
+                        assert!(
+                            floor_to_ceiling.is_some_and(|height| height < TUNNEL_HEIGHT + 0.5),
+                            "seed {}: fired at {:?}, went through a wall to {}",
+                            seed,
+                            velocity.as_slice(),
+                            game_state.camera.position
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_frames_are_split_up() {
+        let mut game_state = GameState::new(0);
+        game_state.set_walking(true);
+        for _ in 0..60 {
+            game_state.update(false, false, false, false, false, 0.0, 0.0, 16);
+        }
+        game_state.update(false, false, false, false, true, 0.0, 0.0, 16);
+        // Halfway through the jump in one go, it's still at the top of the curve
+        // instead of flying off in a straight line
+        let top_of_jump = JUMP_SPEED * JUMP_SPEED / (2.0 * GRAVITY);
+        let time_to_top = (JUMP_SPEED / GRAVITY * 1000.0) as usize;
+        game_state.update(false, false, false, false, false, 0.0, 0.0, time_to_top);
+        let height = height_above_floor(&game_state).unwrap();
+        assert!(
+            (height - (EYE_HEIGHT + top_of_jump)).abs() < 0.1,
+            "{} instead of {}",
+            height,
+            EYE_HEIGHT + top_of_jump
+        );
+    }
+
+    #[test]
+    fn test_same_at_any_frame_rate() {
+        let walk = |frames: &[usize]| {
+            let mut game_state = GameState::new(0);
+            game_state.set_walking(true);
+            for _ in 0..60 {
+                game_state.update(false, false, false, false, false, 0.0, 0.0, 16);
+            }
+            // Jumps, and walks forwards into the walls for a second
+            for (i, delta_time_ms) in frames.iter().enumerate() {
+                let jump = i == 0;
+                game_state.update(true, false, false, false, jump, 0.0, 0.0, *delta_time_ms);
+            }
+            game_state.camera.position
+        };
+        let at_60_fps = walk(&[16; 60]);
+        assert_eq!(at_60_fps, walk(&[8; 120]));
+        assert_eq!(at_60_fps, walk(&[32; 30]));
+        assert_eq!(at_60_fps, walk(&[40, 8, 24, 24].repeat(10)));
+    }
+
+    /// How far the camera is from the first face in the `direction`, if there's any within 10 units
+    fn distance_to_face(game_state: &GameState, direction: Vector3<f64>) -> Option<f64> {
+        let eye = game_state.camera.position;
+        game_state
+            .maze
+            .bvh()
+            .cast_ray(&Ray::new(eye, eye + direction * 10.0))
+            .map(|hit| hit.fraction * 10.0)
+    }
+
+    fn height_above_floor(game_state: &GameState) -> Option<f64> {
+        distance_to_face(game_state, -game_state.up().into_inner())
+    }
+
+    fn height_below_ceiling(game_state: &GameState) -> Option<f64> {
+        distance_to_face(game_state, game_state.up().into_inner())
+    }
+
+    #[test]
+    fn test_walks_on_floor() {
+        for seed in 0..3 {
+            let mut game_state = GameState::new(seed);
+            game_state.set_walking(true);
+            // Falls from the middle of the landing down onto its floor
+            for _ in 0..60 {
+                game_state.update(false, false, false, false, false, 0.0, 0.0, 16);
+            }
+            assert!(game_state.grounded, "seed {}: never landed", seed);
+            let standing_height = height_above_floor(&game_state).unwrap();
+            assert!(
+                (standing_height - EYE_HEIGHT).abs() < 0.05,
+                "{}",
+                standing_height
+            );
+
+            game_state.update(false, false, false, false, true, 0.0, 0.0, 16);
+            assert!(!game_state.grounded);
+            let mut highest: f64 = 0.0;
+            for _ in 0..120 {
+                game_state.update(false, false, false, false, false, 0.0, 0.0, 16);
+                highest = highest.max(height_above_floor(&game_state).unwrap());
+            }
+            assert!(
+                highest > EYE_HEIGHT + 1.0,
+                "seed {}: jumped to {}",
+                seed,
+                highest
+            );
+            assert!(game_state.grounded, "seed {}: never came back down", seed);
+
+            // Walks down the tunnel, staying on its floor as it twists
+            for frame in 0..300 {
+                game_state.update(true, false, false, false, false, 0.0, 0.0, 16);
+                if let EnvironmentIdentifier::Tunnel(_) = game_state.current_environment {
+                    let height = height_above_floor(&game_state).unwrap();
+                    assert!(
+                        (height - EYE_HEIGHT).abs() < 0.1,
+                        "seed {}: {} above the floor on frame {}",
+                        seed,
+                        height,
+                        frame
+                    );
+                }
+            }
+            assert!(
+                matches!(
+                    game_state.current_environment,
+                    EnvironmentIdentifier::Tunnel(_)
+                ),
+                "seed {}: didn't make it to a tunnel",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_mesh_buffers_line_up() {
+        let game_state = GameState::new(0);
+        let num_points = game_state.points_to_float32array().len() / 4;
+        assert!(num_points > 0);
+        assert_eq!(num_points % 3, 0);
+        assert_eq!(game_state.normals_to_float32array().len(), num_points * 4);
+        assert_eq!(game_state.uvs_to_float32array().len(), num_points * 2);
+    }
 }
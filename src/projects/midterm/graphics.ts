@@ -142,7 +142,14 @@ export const init_canvas = (
       game_state.input_state.input_s = is_down;
     } else if (event.key === "d") {
       game_state.input_state.input_d = is_down;
+    } else if (event.key === " ") {
+      game_state.input_state.input_space = is_down;
+    } else {
+      return;
     }
+    // Keeps space from scrolling the page while jumping,
+    // but leaves the keys alone for the seed input and buttons otherwise
+    if (game_state.is_active) event.preventDefault();
   };
   const mousemove_listener = (event: MouseEvent) => {
     game_state.input_state.cursor_movement_x = event.movementX;
@@ -159,6 +166,8 @@ export const init_canvas = (
   const keypress_listener = (event: KeyboardEvent) => {
     if (event.key === "f") {
       fullscreen();
+    } else if (event.key === "g") {
+      game_state.rust_state.walking = !game_state.rust_state.walking;
     }
   };
   const click_listener = () => {
@@ -196,6 +205,7 @@ export const init_canvas = (
       game_state.input_state.input_a,
       game_state.input_state.input_s,
       game_state.input_state.input_d,
+      game_state.input_state.input_space,
       game_state.input_state.cursor_movement_x,
       game_state.input_state.cursor_movement_y,
       now - last_render_time,
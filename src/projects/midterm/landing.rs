@@ -1,16 +1,14 @@
+use graphics_core::face::Face;
 use maze::ConnectorIdentifier;
 use nalgebra::{Point3, Unit, UnitVector3};
 
-use crate::{
-    face::Face,
-    maze::{
-        make_door, min_angle_between_tunnels, Connector, Coupler, Environment,
-        EnvironmentIdentifier, MazeSkeleton, LANDING_RADIUS, TUNNEL_HEIGHT,
-    },
+use crate::maze::{
+    make_door, min_angle_between_tunnels, Connector, Coupler, Environment, EnvironmentIdentifier,
+    ExitFace, MazeSkeleton, LANDING_RADIUS, TUNNEL_HEIGHT,
 };
 pub(crate) struct LandingEnvironment {
     faces: Vec<Face<f64>>,
-    exit_faces: Vec<(EnvironmentIdentifier, Face<f64>)>,
+    exit_faces: Vec<ExitFace>,
     landing: Landing,
 }
 impl LandingEnvironment {
@@ -22,7 +20,7 @@ impl Environment for LandingEnvironment {
     fn faces(&self) -> &[Face<f64>] {
         &self.faces
     }
-    fn exit_faces(&self) -> &[(EnvironmentIdentifier, Face<f64>)] {
+    fn exit_faces(&self) -> &[ExitFace] {
         &self.exit_faces
     }
     fn up(&self, _camera_position: Point3<f64>) -> UnitVector3<f64> {
@@ -49,7 +47,7 @@ impl Landing {
     }
     pub(crate) fn to_environment(&self, maze: &MazeSkeleton) -> LandingEnvironment {
         assert!(self.tunnel_ids.len() >= 2);
-        let mut exit_faces: Vec<(EnvironmentIdentifier, Face<f64>)> = vec![];
+        let mut exit_faces: Vec<ExitFace> = vec![];
 
         let floor_to_ceiling = self.up.into_inner() * TUNNEL_HEIGHT;
 
@@ -126,7 +124,10 @@ impl Landing {
                     &self.up,
                     &towards_tunnel,
                 );
-                exit_faces.push((EnvironmentIdentifier::Tunnel(tunnel_id), door.to_face()));
+                exit_faces.push(ExitFace::new(
+                    EnvironmentIdentifier::Tunnel(tunnel_id),
+                    &door.to_face(),
+                ));
                 [door.bottom_left, door.bottom_right]
             })
             .collect();
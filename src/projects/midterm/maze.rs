@@ -1,13 +1,20 @@
-use nalgebra::{point, vector, Matrix4, Point3, Unit, UnitVector3, Vector3, Vector4};
+use nalgebra::{point, vector, Isometry, Matrix4, Point3, Unit, UnitVector3, Vector3, Vector4};
 use rand::seq::SliceRandom;
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use rand::{distributions::Uniform, SeedableRng};
 use rand::{Rng, RngCore};
 
-use crate::console_log;
+use graphics_core::bvh::Bvh;
+use graphics_core::console_log;
+use graphics_core::face::Face;
+use graphics_core::gltf::{Gltf, Mesh, Node, Primitive};
+use graphics_core::material::Material;
+use graphics_core::points_to_float32array;
+use graphics_core::ray::Ray;
+use parry3d::shape::SharedShape;
+
 use crate::dead_end::{DeadEnd, DeadEndEnvironment};
-use crate::face::Face;
 use crate::landing::{Landing, LandingEnvironment};
 use crate::tunnel::{Tunnel, TunnelEnvironment};
 
@@ -69,6 +76,36 @@ pub(crate) enum EnvironmentIdentifier {
     DeadEnd(usize),
 }
 
+/// A face (not displayed) that when passed through,
+/// triggers a "handoff" of player control into the next environment
+pub(crate) struct ExitFace {
+    pub(crate) environment: EnvironmentIdentifier,
+    /// Built once, since it's checked against the camera's movement every frame.
+    /// Nothing if the face is too thin to cross.
+    shape: Option<SharedShape>,
+}
+
+impl ExitFace {
+    pub(crate) fn new(environment: EnvironmentIdentifier, face: &Face<f64>) -> Self {
+        Self {
+            environment,
+            shape: face.to_collision_shape(),
+        }
+    }
+
+    pub(crate) fn is_crossed_by(&self, ray: &Ray<f64, 3>) -> bool {
+        self.shape.as_ref().is_some_and(|shape| {
+            parry3d::query::intersection_test(
+                &Isometry::identity(),
+                &**shape,
+                &Isometry::identity(),
+                &ray.to_segment(),
+            )
+            .unwrap()
+        })
+    }
+}
+
 /// The usize represents the corresponding id.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub(crate) enum ConnectorIdentifier {
@@ -107,9 +144,7 @@ pub(crate) trait Connector {
 
 pub(crate) trait Environment {
     fn faces(&self) -> &[Face<f64>];
-    /// Faces (not displayed) that when passed through,
-    /// trigger a "handoff" of player control into the next environment
-    fn exit_faces(&self) -> &[(EnvironmentIdentifier, Face<f64>)];
+    fn exit_faces(&self) -> &[ExitFace];
     fn up(&self, camera_position: Point3<f64>) -> UnitVector3<f64>;
 }
 
@@ -176,13 +211,7 @@ pub struct Maze {
     landings: Vec<LandingEnvironment>,
     tunnels: Vec<TunnelEnvironment>,
     dead_ends: Vec<DeadEndEnvironment>,
-}
-
-fn points_to_float32array(points: &[Vector3<f64>]) -> Vec<f32> {
-    points
-        .iter()
-        .flat_map(|point| [point.x as _, point.y as _, point.z as _, 1.0])
-        .collect()
+    bvh: Bvh,
 }
 
 #[wasm_bindgen]
@@ -282,24 +311,44 @@ impl Maze {
         console_log!("landings: {}", maze.landings.len());
         console_log!("dead ends: {}", maze.dead_ends.len());
 
-        Self {
-            landings: maze
-                .landings
-                .iter()
-                .map(|landing| landing.to_environment(&maze))
-                .collect(),
-            tunnels: maze
-                .tunnels
-                .iter()
-                .map(|tunnel| tunnel.to_environment(&maze))
-                .collect(),
-            dead_ends: maze
-                .dead_ends
+        let landings: Vec<_> = maze
+            .landings
+            .iter()
+            .map(|landing| landing.to_environment(&maze))
+            .collect();
+        let tunnels: Vec<_> = maze
+            .tunnels
+            .iter()
+            .map(|tunnel| tunnel.to_environment(&maze))
+            .collect();
+        let dead_ends: Vec<_> = maze
+            .dead_ends
+            .iter()
+            .map(|dead_end| dead_end.to_environment(&maze))
+            .collect();
+        let bvh = Bvh::new(
+            tunnels
                 .iter()
-                .map(|dead_end| dead_end.to_environment(&maze))
+                .flat_map(|tunnel| tunnel.faces())
+                .chain(landings.iter().flat_map(|landing| landing.faces()))
+                .chain(dead_ends.iter().flat_map(|dead_end| dead_end.faces()))
+                .cloned()
                 .collect(),
+        );
+        Self {
+            landings,
+            tunnels,
+            dead_ends,
+            bvh,
         }
     }
+    /// Every face in the maze, for collision checks.
+    /// Around a doorway, the walls on both sides of it can be in the way,
+    /// so this doesn't go by environment.
+    #[inline]
+    pub(crate) fn bvh(&self) -> &Bvh {
+        &self.bvh
+    }
     #[inline]
     pub(crate) fn tunnels(&self) -> &[TunnelEnvironment] {
         &self.tunnels
@@ -356,6 +405,77 @@ impl Maze {
             .collect()
     }
 
+    /// The maze's faces as a GLB file, with a node for each tunnel, landing and dead end
+    #[wasm_bindgen]
+    pub fn export_glb(&self) -> Vec<u8> {
+        self.to_gltf().to_glb()
+    }
+
+    /// For debugging: the furthest any face's points stray from that face's plane
+    #[wasm_bindgen]
+    pub fn max_planarity_error(&self) -> f64 {
+        self.faces()
+            .iter()
+            .map(|face| face.planarity_error())
+            .fold(0.0, f64::max)
+    }
+
+    /// For debugging: how many faces have a point further than `tolerance` from the face's plane
+    #[wasm_bindgen]
+    pub fn count_non_planar_faces(&self, tolerance: f64) -> usize {
+        self.faces()
+            .iter()
+            .filter(|face| !face.is_planar(tolerance))
+            .count()
+    }
+
+    /// Every environment as a top-level node with its own mesh, all sharing one plain material
+    /// (the texture isn't exported)
+    pub(crate) fn to_gltf(&self) -> Gltf {
+        let environments = self
+            .tunnels
+            .iter()
+            .enumerate()
+            .map(|(i, tunnel)| (format!("tunnel {}", i), tunnel.faces()))
+            .chain(
+                self.landings
+                    .iter()
+                    .enumerate()
+                    .map(|(i, landing)| (format!("landing {}", i), landing.faces())),
+            )
+            .chain(
+                self.dead_ends
+                    .iter()
+                    .enumerate()
+                    .map(|(i, dead_end)| (format!("dead end {}", i), dead_end.faces())),
+            );
+        let mut nodes = vec![];
+        let mut meshes = vec![];
+        for (name, faces) in environments {
+            nodes.push(Node {
+                name: Some(name.clone()),
+                children: vec![],
+                transform: Matrix4::identity(),
+                mesh: Some(meshes.len()),
+            });
+            meshes.push(Mesh {
+                name: Some(name),
+                primitives: vec![Primitive::from_faces(faces, Some(0))],
+            });
+        }
+        Gltf {
+            roots: (0..nodes.len()).collect(),
+            nodes,
+            meshes,
+            materials: vec![Material {
+                name: Some("maze".to_string()),
+                metallic: 0.0,
+                ..Material::default()
+            }],
+            images: vec![],
+        }
+    }
+
     #[inline]
     pub(crate) fn faces(&self) -> Vec<Face<f64>> {
         self.tunnels
@@ -379,4 +499,92 @@ mod tests {
             Maze::generate(rng.next_u32());
         }
     }
+
+    #[test]
+    fn test_exit_faces() {
+        let maze = Maze::generate(7);
+        let environments = maze
+            .tunnels()
+            .iter()
+            .map(|tunnel| tunnel as &dyn Environment)
+            .chain(
+                maze.landings()
+                    .iter()
+                    .map(|landing| landing as &dyn Environment),
+            )
+            .chain(
+                maze.dead_ends()
+                    .iter()
+                    .map(|dead_end| dead_end as &dyn Environment),
+            );
+        for environment in environments {
+            // Doors are flat, which is fine for a triangle mesh
+            assert!(environment
+                .exit_faces()
+                .iter()
+                .all(|exit_face| exit_face.shape.is_some()));
+        }
+
+        let door = make_door(
+            &point![0.0, 0.0, 0.0],
+            &Vector3::y_axis(),
+            &Vector3::x_axis(),
+        );
+        let exit_face = ExitFace::new(EnvironmentIdentifier::Landing(0), &door.to_face());
+        let through = |x: f64| Ray::new(point![-x, 1.0, 0.0], point![x, 1.0, 0.0]);
+        assert!(exit_face.is_crossed_by(&through(0.5)));
+        assert!(exit_face.is_crossed_by(&through(-0.5)));
+        assert!(!exit_face.is_crossed_by(&Ray::new(point![0.1, 1.0, 0.0], point![0.5, 1.0, 0.0])));
+    }
+
+    #[test]
+    fn test_export_glb() {
+        let maze = Maze::generate(7);
+        let gltf = Gltf::from_bytes(&maze.export_glb()).unwrap();
+        assert_eq!(
+            gltf.nodes.len(),
+            maze.tunnels().len() + maze.landings().len() + maze.dead_ends().len()
+        );
+        assert_eq!(gltf.nodes[0].name.as_deref(), Some("tunnel 0"));
+
+        // The meshes, one after another, make up the same buffers the maze is drawn with
+        let faces: Vec<Face<f64>> = gltf
+            .meshes
+            .iter()
+            .flat_map(|mesh| mesh.primitives[0].faces())
+            .collect();
+        let points: Vec<Vector3<f64>> = faces
+            .iter()
+            .flat_map(|face| face.break_into_triangles())
+            .map(|point| point.coords)
+            .collect();
+        assert_eq!(
+            points_to_float32array(&points),
+            maze.points_to_float32array()
+        );
+
+        // Flipping the UVs' v and renormalizing the normals can round them slightly
+        let close = |buffer: Vec<f32>, original: Vec<f32>| {
+            buffer.len() == original.len()
+                && buffer
+                    .iter()
+                    .zip(&original)
+                    .all(|(value, original)| (value - original).abs() < 1e-6)
+        };
+        let normals: Vec<Vector3<f64>> = faces
+            .iter()
+            .flat_map(|face| face.break_into_normal_triangles())
+            .map(|normal| normal.into_inner())
+            .collect();
+        assert!(close(
+            points_to_float32array(&normals),
+            maze.normals_to_float32array()
+        ));
+        let uvs = faces
+            .iter()
+            .flat_map(|face| face.break_into_uv_triangles())
+            .flat_map(|uv| [uv.x as f32, uv.y as f32])
+            .collect();
+        assert!(close(uvs, maze.uvs_to_float32array()));
+    }
 }
@@ -1,17 +1,15 @@
+use graphics_core::face::{Face, UVPair};
 use nalgebra::{Point3, UnitVector3, Vector2};
 
-use crate::{
-    face::{Face, UVPair},
-    maze::{
-        ConnectorIdentifier, Coupler, Environment, EnvironmentIdentifier, MazeSkeleton,
-        TUNNEL_HEIGHT, TUNNEL_SUBDIVISIONS, TUNNEL_WIDTH,
-    },
+use crate::maze::{
+    ConnectorIdentifier, Coupler, Environment, ExitFace, MazeSkeleton, TUNNEL_HEIGHT,
+    TUNNEL_SUBDIVISIONS, TUNNEL_WIDTH,
 };
 
 pub(crate) struct TunnelEnvironment {
     faces: Vec<Face<f64>>,
     tunnel: Tunnel,
-    exit_faces: Vec<(EnvironmentIdentifier, Face<f64>)>,
+    exit_faces: Vec<ExitFace>,
     start_coupler: Coupler,
     end_coupler: Coupler,
 }
@@ -25,7 +23,7 @@ impl Environment for TunnelEnvironment {
     fn faces(&self) -> &[Face<f64>] {
         &self.faces
     }
-    fn exit_faces(&self) -> &[(EnvironmentIdentifier, Face<f64>)] {
+    fn exit_faces(&self) -> &[ExitFace] {
         &self.exit_faces
     }
     fn up(&self, camera_position: Point3<f64>) -> UnitVector3<f64> {
@@ -184,8 +182,8 @@ impl Tunnel {
         ]);
 
         let exit_faces = vec![
-            (self.start_connector.environment_identifier(), start_face),
-            (self.end_connector.environment_identifier(), end_face),
+            ExitFace::new(self.start_connector.environment_identifier(), &start_face),
+            ExitFace::new(self.end_connector.environment_identifier(), &end_face),
         ];
 
         TunnelEnvironment {
@@ -1,57 +1,28 @@
+extern crate graphics_core;
 extern crate nalgebra as na;
 extern crate wasm_bindgen;
 
 use std::f64::consts::PI;
 
-use na::{point, vector, Matrix4, Point3, Scale3, Translation3, UnitVector3, Vector3};
+use graphics_core::{
+    camera::decel, fixed_timestep::FixedTimestep, transform_matrix::TransformMatrix,
+};
+use na::{point, vector, Matrix4, Point3, Scale3, UnitVector3, Vector3};
 use wasm_bindgen::prelude::*;
 
 static UP: Vector3<f64> = vector![0.0, -1.0, 0.0];
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log(s: String);
-}
-
-macro_rules! console_log {
-    ($($t:tt)*) => {
-        #[cfg(target_arch = "wasm32")] {
-            log(format!($($t)*));
-        }
-        #[cfg(not(target_arch = "wasm32"))] {
-            println!($($t)*);
-        }
-    };
-}
-
 #[wasm_bindgen]
 pub struct GameState {
     camera_position: Point3<f64>,
+    /// Where the camera was before its last step, so it can be drawn partway between the two
+    previous_camera_position: Point3<f64>,
+    timestep: FixedTimestep,
     camera_direction: UnitVector3<f64>,
     camera_velocity: Vector3<f64>,
     aspect_ratio: f64,
 }
 
-/// A little wrapper around the nalgebra matrix4 class, for JS use,
-/// so we only have to generate binding code for the methods we actually need.
-#[wasm_bindgen]
-pub struct TransformMatrix(Matrix4<f64>);
-
-#[wasm_bindgen]
-impl TransformMatrix {
-    #[wasm_bindgen(constructor)]
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
-        Self(Translation3::new(x, y, z).to_homogeneous())
-    }
-    pub fn to_f64_array(&self) -> Vec<f64> {
-        self.0.as_slice().to_vec()
-    }
-    pub fn times(&self, other: &TransformMatrix) -> TransformMatrix {
-        Self(self.0 * other.0)
-    }
-}
-
 #[wasm_bindgen]
 impl GameState {
     #[wasm_bindgen(constructor)]
@@ -72,8 +43,6 @@ impl GameState {
         cursor_y: f64,
         delta_time_ms: usize,
     ) {
-        // In seconds, time since last render
-        let dt = delta_time_ms as f64 / 1000.0;
         // Ranges from -pi to +pi
         let angle_x = cursor_x * PI;
         let angle_y = cursor_y * PI;
@@ -81,30 +50,10 @@ impl GameState {
             vector![angle_x.sin(), 0.0, -angle_x.cos()]
                 + vector![0.0, -angle_y.sin(), -angle_y.cos()],
         );
-        self.camera_position += self.camera_velocity * dt;
-        let accel = 15.0;
-        let decel = 0.15;
-        let forwards = self.camera_direction.into_inner();
-        if input_w {
-            self.camera_velocity -= accel * dt * forwards;
-        } else if input_s {
-            self.camera_velocity += accel * dt * forwards;
-        } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&forwards)) * forwards;
+        // Moves in fixed steps, so it moves the same at any frame rate
+        for _ in 0..self.timestep.advance(delta_time_ms as f64) {
+            self.step(input_w, input_a, input_s, input_d, self.timestep.dt());
         }
-        let right = forwards.cross(&UP);
-        if input_a {
-            self.camera_velocity -= accel * dt * right;
-        } else if input_d {
-            self.camera_velocity += accel * dt * right;
-        } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&right)) * right;
-        }
-        // There was a bug where when turning the camera it could continue drifting
-        // Since the third component of the "camera directions" didn't have code to handle deceleration
-        // So I added this
-        let camera_up = forwards.cross(&right);
-        self.camera_velocity -= decel * (self.camera_velocity.dot(&camera_up)) * camera_up;
     }
 
     pub fn world_to_camera(&self) -> TransformMatrix {
@@ -122,12 +71,12 @@ impl GameState {
                 ),
                 &UP,
             )
-            * Matrix4::new_translation(&Vector3::new(
-                self.camera_position.x,
-                self.camera_position.y,
-                self.camera_position.z,
-            ));
-        TransformMatrix(transforms)
+            * Matrix4::new_translation(
+                &(self.previous_camera_position.coords
+                    + (self.camera_position - self.previous_camera_position)
+                        * self.timestep.alpha()),
+            );
+        TransformMatrix::from(transforms)
     }
 
     #[wasm_bindgen(getter)]
@@ -141,14 +90,72 @@ impl GameState {
     }
 }
 
+impl GameState {
+    /// Moves and then accelerates the camera, `dt` seconds
+    fn step(&mut self, input_w: bool, input_a: bool, input_s: bool, input_d: bool, dt: f64) {
+        self.previous_camera_position = self.camera_position;
+        self.camera_position += self.camera_velocity * dt;
+        let accel = 15.0;
+        let decel = decel(dt);
+        let forwards = self.camera_direction.into_inner();
+        if input_w {
+            self.camera_velocity -= accel * dt * forwards;
+        } else if input_s {
+            self.camera_velocity += accel * dt * forwards;
+        } else {
+            self.camera_velocity -= decel * (self.camera_velocity.dot(&forwards)) * forwards;
+        }
+        let right = forwards.cross(&UP);
+        if input_a {
+            self.camera_velocity -= accel * dt * right;
+        } else if input_d {
+            self.camera_velocity += accel * dt * right;
+        } else {
+            self.camera_velocity -= decel * (self.camera_velocity.dot(&right)) * right;
+        }
+        // There was a bug where when turning the camera it could continue drifting
+        // Since the third component of the "camera directions" didn't have code to handle deceleration
+        // So I added this
+        let camera_up = forwards.cross(&right);
+        self.camera_velocity -= decel * (self.camera_velocity.dot(&camera_up)) * camera_up;
+    }
+}
+
 impl Default for GameState {
     #[inline]
     fn default() -> Self {
         Self {
             aspect_ratio: 1.0,
             camera_position: point![0.0, 0.0, -2.0],
+            previous_camera_position: point![0.0, 0.0, -2.0],
+            timestep: FixedTimestep::default(),
             camera_direction: UnitVector3::new_normalize(vector![0.0, 0.0, -1.0]),
             camera_velocity: Vector3::zeros(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_at_any_frame_rate() {
+        let fly = |frames: &[usize]| {
+            let mut game_state = GameState::new();
+            let mut time_ms = 0;
+            for delta_time_ms in frames {
+                // Speeds up for a while, then coasts
+                let input_w = time_ms < 480;
+                time_ms += delta_time_ms;
+                game_state.update(input_w, false, false, false, 0.25, 0.0, *delta_time_ms);
+            }
+            game_state.camera_position
+        };
+        let at_60_fps = fly(&[16; 60]);
+        assert!((at_60_fps - point![0.0, 0.0, -2.0]).norm() > 1.0);
+        assert_eq!(at_60_fps, fly(&[8; 120]));
+        assert_eq!(at_60_fps, fly(&[32; 30]));
+        assert_eq!(at_60_fps, fly(&[40, 8, 24, 24].repeat(10)));
+    }
+}
@@ -32,6 +32,28 @@ export interface GameState {
 
 interface Props {}
 
+const describe_ik_result = (result: rust.IkResult) => {
+  // The scene is in meters. Once the tip is at the target
+  // (to a tenth of a millimeter), it's the way it faces that's off
+  const degrees = (result.orientation_error * 180) / Math.PI;
+  const miss =
+    result.position_error < 0.0001
+      ? `turned ${degrees.toFixed(1)}° away from the target`
+      : `${(result.position_error * 100).toFixed(1)} cm from the target`;
+  switch (result.status) {
+    case rust.IkStatus.Reached:
+      return "Target reached";
+    case rust.IkStatus.Clamped:
+      return `The joint limits keep the arm ${miss}`;
+    case rust.IkStatus.Unsolved:
+      return `No way to get there, the arm stops ${miss}`;
+    case rust.IkStatus.Unreachable:
+      return `Out of reach, the arm stops ${miss}`;
+    case rust.IkStatus.Collides:
+      return "The arm would run into itself to get there";
+  }
+};
+
 export const Proj4 = ({}: Props) => {
   // eslint-disable-next-line @typescript-eslint/naming-convention
   const [error, _reset_error] = useErrorBoundary();
@@ -39,6 +61,8 @@ export const Proj4 = ({}: Props) => {
   const [rust_module, set_rust_module] = useState<rust.InitOutput | null>(null);
   const state_ref = useRef<GameState | null>(null);
   const render_ref = useRef<() => void>();
+  const load_scene_ref = useRef<(scene: rust.SceneDescription) => void>();
+  const [ik_status, set_ik_status] = useState("");
 
   const capture_screenshot = () => {
     const link = document.createElement("a");
@@ -48,6 +72,37 @@ export const Proj4 = ({}: Props) => {
     link.click();
   };
 
+  const export_scene = () => {
+    const state = state_ref.current;
+    if (!state) return;
+    const link = document.createElement("a");
+    link.download = "robot-arm.json";
+    link.href = URL.createObjectURL(
+      new Blob([state.rust_state.export_scene()], { type: "application/json" }),
+    );
+    link.click();
+    URL.revokeObjectURL(link.href);
+  };
+
+  const export_glb = () => {
+    const state = state_ref.current;
+    if (!state) return;
+    const link = document.createElement("a");
+    link.download = "robot-arm.glb";
+    link.href = URL.createObjectURL(
+      new Blob([state.rust_state.export_glb()], { type: "model/gltf-binary" }),
+    );
+    link.click();
+    URL.revokeObjectURL(link.href);
+  };
+
+  // Scene files name OBJ meshes that aren't bundled with them, so only glTF files can be opened
+  const open_gltf = async (file: File) => {
+    const bytes = new Uint8Array(await file.arrayBuffer());
+    load_scene_ref.current?.(rust.SceneDescription.from_gltf(bytes));
+    render_ref.current?.();
+  };
+
   useEffect(() => {
     const m_listener = (e: KeyboardEvent) => {
       if (e.key === "m") capture_screenshot();
@@ -79,9 +134,10 @@ export const Proj4 = ({}: Props) => {
     };
     state_ref.current = game_state;
     let canvas_cleanup = () => {};
-    init_canvas(canvas, game_state).then(({ cleanup, render }) => {
+    init_canvas(canvas, game_state).then(({ cleanup, render, load_scene }) => {
       canvas_cleanup = cleanup;
       render_ref.current = render;
+      load_scene_ref.current = load_scene;
     });
     return () => {
       canvas_cleanup();
@@ -103,9 +159,13 @@ export const Proj4 = ({}: Props) => {
           const state = state_ref.current;
           if (!state) return;
           state.rust_state.update_target(x, y, z);
+          const result = state.rust_state.ik_result();
+          set_ik_status(result ? describe_ik_result(result) : "");
+          result?.free();
           render_ref.current?.();
         }}
       />
+      <p>{ik_status}</p>
       <CoordinateInput
         name="Light Source Position"
         min={-20}
@@ -119,6 +179,19 @@ export const Proj4 = ({}: Props) => {
         }}
       />
       <button onClick={capture_screenshot}>Download screenshot</button>
+      <button onClick={export_scene}>Download scene</button>
+      <button onClick={export_glb}>Download glTF</button>
+      <label>
+        Open glTF{" "}
+        <input
+          type="file"
+          accept=".glb,.gltf"
+          onChange={(event) => {
+            const file = event.currentTarget.files?.[0];
+            if (file) open_gltf(file);
+          }}
+        />
+      </label>
     </div>
   );
 };
@@ -0,0 +1,258 @@
+//! Collision checks for the arm: each game object is wrapped in the convex hull of its faces,
+//! which is tested against the other objects and against the floor.
+
+use std::borrow::Cow;
+
+use graphics_core::{face::Face, transform::Transform};
+use nalgebra::{Isometry3, Matrix4, Point3, Translation3, UnitVector3, Vector3};
+use parry3d::{
+    query,
+    shape::{ConvexPolyhedron, HalfSpace, Shape},
+};
+use wasm_bindgen::prelude::*;
+
+/// How far two shapes have to overlap to count as colliding,
+/// so parts that are only resting against each other don't
+const CONTACT_TOLERANCE: f64 = 1e-6;
+
+/// The convex hull around a game object, in the object's own coordinates
+#[derive(Debug, Clone)]
+pub struct CollisionShape {
+    hull: ConvexPolyhedron,
+}
+
+impl CollisionShape {
+    /// Nothing if the faces don't enclose any volume (e.g. there are none, or they're all flat)
+    pub fn from_faces(faces: &[Face<f64>]) -> Option<Self> {
+        let points: Vec<Point3<f64>> = faces
+            .iter()
+            .flat_map(|face| face.points())
+            .copied()
+            .collect();
+        if points.len() < 4 {
+            return None;
+        }
+        let hull = ConvexPolyhedron::from_convex_hull(&points)?;
+        // Flat faces give a hull with no volume, which parry can't find contacts with
+        if hull.compute_local_aabb().extents().min() <= 0.0 {
+            return None;
+        }
+        Some(Self { hull })
+    }
+
+    #[inline]
+    pub fn hull(&self) -> &ConvexPolyhedron {
+        &self.hull
+    }
+
+    /// The shape moved by a world transform.
+    /// Nothing if the transform can't be split into a translation, rotation and scale.
+    pub fn placed(&self, world_transform: &Matrix4<f64>) -> Option<PlacedShape<'_>> {
+        let transform = Transform::decompose(world_transform).ok()?;
+        let hull = if (transform.scale - Vector3::repeat(1.0)).norm() < 1e-12 {
+            Cow::Borrowed(&self.hull)
+        } else {
+            Cow::Owned(self.hull.clone().scaled(&transform.scale)?)
+        };
+        Some(PlacedShape {
+            position: Isometry3::from_parts(
+                Translation3::from(transform.translation),
+                transform.rotation,
+            ),
+            hull,
+        })
+    }
+}
+
+/// A collision shape somewhere in the world
+#[derive(Debug, Clone)]
+pub struct PlacedShape<'a> {
+    pub position: Isometry3<f64>,
+    pub hull: Cow<'a, ConvexPolyhedron>,
+}
+
+impl<'a> PlacedShape<'a> {
+    /// How far the shapes overlap, if they do
+    pub fn penetration(&self, other: &PlacedShape) -> Option<f64> {
+        penetration(&self.position, &*self.hull, &other.position, &*other.hull)
+    }
+
+    /// How far the shape sinks into the floor, if it does
+    pub fn floor_penetration(&self, floor: &Floor) -> Option<f64> {
+        let half_space = HalfSpace::new(floor.normal);
+        let floor_position = Isometry3::from(Translation3::from(floor.point));
+        // parry only finds contacts with the half space first
+        penetration(&floor_position, &half_space, &self.position, &*self.hull)
+    }
+}
+
+fn penetration(
+    position_1: &Isometry3<f64>,
+    shape_1: &dyn Shape,
+    position_2: &Isometry3<f64>,
+    shape_2: &dyn Shape,
+) -> Option<f64> {
+    let contact = query::contact(position_1, shape_1, position_2, shape_2, 0.0).ok()??;
+    if contact.dist < -CONTACT_TOLERANCE {
+        Some(-contact.dist)
+    } else {
+        None
+    }
+}
+
+/// Everything on the other side of a plane from `normal` is solid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Floor {
+    pub point: Point3<f64>,
+    pub normal: UnitVector3<f64>,
+}
+
+/// Two game objects (or one and the floor) that overlap
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArmContact {
+    first: String,
+    second: Option<String>,
+    /// How far the two overlap
+    pub depth: f64,
+}
+
+#[wasm_bindgen]
+impl ArmContact {
+    #[wasm_bindgen(getter)]
+    pub fn first(&self) -> String {
+        self.first.clone()
+    }
+
+    /// The other game object, or nothing for the floor
+    #[wasm_bindgen(getter)]
+    pub fn second(&self) -> Option<String> {
+        self.second.clone()
+    }
+}
+
+impl ArmContact {
+    pub fn new(first: &str, second: Option<&str>, depth: f64) -> Self {
+        Self {
+            first: first.to_string(),
+            second: second.map(String::from),
+            depth,
+        }
+    }
+
+    #[inline]
+    pub fn is_floor(&self) -> bool {
+        self.second.is_none()
+    }
+
+    /// Whether this is a contact between these two (in either order), or with the floor for `None`
+    pub fn is_between(&self, a: &str, b: Option<&str>) -> bool {
+        match b {
+            Some(b) => {
+                (self.first == a && self.second.as_deref() == Some(b))
+                    || (self.first == b && self.second.as_deref() == Some(a))
+            }
+            None => self.first == a && self.second.is_none(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct CollisionReport {
+    contacts: Vec<ArmContact>,
+}
+
+#[wasm_bindgen]
+impl CollisionReport {
+    pub fn len(&self) -> usize {
+        self.contacts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+    pub fn get_contact(&self, index: usize) -> ArmContact {
+        self.contacts[index].clone()
+    }
+}
+
+impl CollisionReport {
+    pub fn new(contacts: Vec<ArmContact>) -> Self {
+        Self { contacts }
+    }
+
+    pub fn contacts(&self) -> &[ArmContact] {
+        &self.contacts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_core::load_obj::load_obj;
+    use nalgebra::{vector, Rotation3};
+    use std::f64::consts::PI;
+
+    /// A cube from (0, 0, 0) to (1, 1, 1)
+    fn cube() -> CollisionShape {
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                   vt 0 0\n\
+                   f 1/1 4/1 3/1 2/1\nf 5/1 6/1 7/1 8/1\nf 1/1 2/1 6/1 5/1\n\
+                   f 2/1 3/1 7/1 6/1\nf 3/1 4/1 8/1 7/1\nf 4/1 1/1 5/1 8/1\n";
+        CollisionShape::from_faces(&load_obj(obj)).unwrap()
+    }
+
+    fn translation(x: f64, y: f64, z: f64) -> Matrix4<f64> {
+        Translation3::new(x, y, z).to_homogeneous()
+    }
+
+    #[test]
+    fn test_from_faces() {
+        assert_eq!(cube().hull().points().len(), 8);
+        assert!(CollisionShape::from_faces(&[]).is_none());
+        let square = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nf 1/1 2/1 3/1 4/1\n";
+        assert!(CollisionShape::from_faces(&load_obj(square)).is_none());
+    }
+
+    #[test]
+    fn test_penetration() {
+        let cube = cube();
+        let at_origin = cube.placed(&Matrix4::identity()).unwrap();
+        let overlapping = cube.placed(&translation(0.75, 0.0, 0.0)).unwrap();
+        let touching = cube.placed(&translation(1.0, 0.0, 0.0)).unwrap();
+        let apart = cube.placed(&translation(0.0, 2.0, 0.0)).unwrap();
+        assert!((at_origin.penetration(&overlapping).unwrap() - 0.25).abs() < 1e-9);
+        assert_eq!(at_origin.penetration(&touching), None);
+        assert_eq!(at_origin.penetration(&apart), None);
+
+        // Doubled in size, reaching from x = -1.5 to 0.5
+        let scaled = cube
+            .placed(&(translation(-1.5, 0.0, 0.0) * Matrix4::new_scaling(2.0)))
+            .unwrap();
+        assert!((at_origin.penetration(&scaled).unwrap() - 0.5).abs() < 1e-9);
+        // Turned a quarter turn about z, reaching from x = 0.5 to 1.5
+        let rotation = Rotation3::from_axis_angle(&Vector3::z_axis(), PI / 2.0).to_homogeneous();
+        let turned = cube
+            .placed(&(translation(1.5, 0.0, 0.0) * rotation))
+            .unwrap();
+        assert!((at_origin.penetration(&turned).unwrap() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_floor_penetration() {
+        let floor = Floor {
+            point: Point3::origin(),
+            normal: Vector3::y_axis(),
+        };
+        let cube = cube();
+        let sunk = cube.placed(&translation(0.0, -0.5, 0.0)).unwrap();
+        assert!((sunk.floor_penetration(&floor).unwrap() - 0.5).abs() < 1e-9);
+        let resting = cube.placed(&Matrix4::identity()).unwrap();
+        assert_eq!(resting.floor_penetration(&floor), None);
+
+        let ceiling = Floor {
+            point: Point3::from(vector![0.0, 0.5, 0.0]),
+            normal: -Vector3::y_axis(),
+        };
+        assert!((resting.floor_penetration(&ceiling).unwrap() - 0.5).abs() < 1e-9);
+    }
+}
@@ -18,10 +18,11 @@ uniform mat4 camera_transform;
 uniform mat4 transform;
 uniform vec3 light_position;
 uniform vec3 camera_position;
+// From the object's material
+uniform vec3 base_color;
 
 void main(void) {
 
-  vec3 base_color = vec3(0.3, 0.3, 0.3);
   vec3 L_ambient = base_color; // around the scene light color
   vec3 L_diffuse = base_color + vec3(0.3, 0.3, 0.3); // Scattered light color
   vec3 L_specular = vec3(0.1, 0.1, 0.1); // Color of shininess of object
@@ -1,4 +1,5 @@
 extern crate console_error_panic_hook;
+extern crate graphics_core;
 pub(crate) extern crate nalgebra;
 pub(crate) extern crate num_traits;
 pub(crate) extern crate parry2d_f64 as parry2d;
@@ -6,129 +7,62 @@ pub(crate) extern crate parry3d_f64 as parry3d;
 pub(crate) extern crate rand;
 pub(crate) extern crate rand_chacha;
 pub(crate) extern crate wasm_bindgen;
-mod bounding_box;
-mod face;
-mod load_obj;
-mod ray;
+pub mod collision;
+pub mod ik;
+pub mod scene;
+pub mod scene_graph;
+pub mod trajectory;
 
-use std::f64::consts::PI;
-
-use face::Face;
+use collision::{ArmContact, CollisionReport, CollisionShape, Floor};
+use graphics_core::{
+    animation::{AnimationClip, AnimationPlayer, Interpolation, PlaybackMode},
+    bounding_box::BoundingBox,
+    camera::{Camera, CameraInput},
+    face::Face,
+    fixed_timestep::FixedTimestep,
+    gltf::{self, Gltf, Primitive},
+    load_obj::load_obj,
+    material::Material,
+    points_to_float32array,
+    transform::Transform,
+    transform_matrix::TransformMatrix,
+    units::LengthUnit,
+};
+use ik::{Chain, IkResult, IkStatus, Joint, Link, Orientation, Solver, SolverSettings, Target};
 use nalgebra::{
-    point, vector, Matrix4, Point3, Rotation3, Scale3, Translation3, Unit, UnitQuaternion,
-    UnitVector3, Vector3,
+    point, vector, Matrix4, Point3, Quaternion, Translation3, Unit, UnitQuaternion, UnitVector3,
+    Vector3,
 };
+use rand::{Rng, SeedableRng};
+use scene::{ArmDescription, JointDescription, ObjectDescription, SceneDescription, SceneError};
+use scene_graph::{NodeId, SceneGraph, SceneGraphError};
+use std::{collections::HashMap, f64::consts::PI};
+use trajectory::{MotionLimits, MotionPlanning, Trajectory};
 use wasm_bindgen::prelude::*;
 
-pub(crate) trait Number:
-    'static
-    + std::fmt::Debug
-    + Copy
-    + nalgebra::ClosedAdd
-    + nalgebra::ClosedSub
-    + nalgebra::ClosedMul
-    + num_traits::identities::Zero
-    + nalgebra::SimdComplexField
-    + nalgebra::SimdRealField
-    + num_traits::Float
-    + std::convert::Into<f64>
-    + std::convert::From<f32>
-{
-}
-impl Number for f64 {}
-impl Number for f32 {}
-
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console, js_name = log)]
-    fn log(s: String);
-}
-
-#[macro_export]
-macro_rules! console_log {
-    ($($t:tt)*) => {
-        #[cfg(target_arch = "wasm32")] {
-            $crate::log(format!($($t)*));
-        }
-        #[cfg(not(target_arch = "wasm32"))] {
-            println!($($t)*);
-        }
-    };
-}
-
-fn points_to_float32array(points: &[Vector3<f64>]) -> Vec<f32> {
-    points
-        .iter()
-        .flat_map(|point| [point.x as _, point.y as _, point.z as _, 1.0])
-        .collect()
-}
-
 #[derive(Clone)]
 #[wasm_bindgen]
 pub struct GameObject {
-    parent_index: usize,
     faces: Vec<Face<f64>>,
-    obj_vert_buffer: Option<JsValue>,
-    obj_normals_buffer: Option<JsValue>,
-    obj_uvs_buffer: Option<JsValue>,
     num_points: usize,
     initial_transform: Matrix4<f64>,
     dynamic_transform: Matrix4<f64>,
+    /// Cached from the parent's world transform and this object's transforms,
+    /// only up to date after `GameState::update_world_transforms`
+    world_transform: Matrix4<f64>,
+    /// The convex hull of the faces, if they have any volume
+    collision_shape: Option<CollisionShape>,
+    /// The name of the mesh the faces were loaded from, kept for exporting the scene
+    mesh: Option<String>,
+    material: Option<Material>,
 }
 
 #[wasm_bindgen]
 impl GameObject {
     #[wasm_bindgen(constructor)]
     #[allow(clippy::new_without_default)]
-    pub fn new(obj_text: String, parent_index: usize, initial_transform: TransformMatrix) -> Self {
-        let faces = load_obj::load_obj(&obj_text);
-
-        let num_points = faces
-            .iter()
-            .fold(0, |count, face| count + (face.break_into_triangles().len()));
-
-        Self {
-            parent_index,
-            faces,
-            obj_vert_buffer: None,
-            obj_normals_buffer: None,
-            obj_uvs_buffer: None,
-            num_points,
-            initial_transform: initial_transform.0,
-            dynamic_transform: Matrix4::identity(),
-        }
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn obj_vert_buffer(&self) -> JsValue {
-        self.obj_vert_buffer.clone().unwrap_or(JsValue::UNDEFINED)
-    }
-
-    #[wasm_bindgen(setter)]
-    pub fn set_obj_vert_buffer(&mut self, obj_vert_buffer: JsValue) {
-        self.obj_vert_buffer = Some(obj_vert_buffer);
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn obj_normals_buffer(&self) -> JsValue {
-        self.obj_normals_buffer
-            .clone()
-            .unwrap_or(JsValue::UNDEFINED)
-    }
-
-    #[wasm_bindgen(setter)]
-    pub fn set_obj_normals_buffer(&mut self, obj_normals_buffer: JsValue) {
-        self.obj_normals_buffer = Some(obj_normals_buffer);
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn obj_uvs_buffer(&self) -> JsValue {
-        self.obj_uvs_buffer.clone().unwrap_or(JsValue::UNDEFINED)
-    }
-
-    #[wasm_bindgen(setter)]
-    pub fn set_obj_uvs_buffer(&mut self, obj_uvs_buffer: JsValue) {
-        self.obj_uvs_buffer = Some(obj_uvs_buffer);
+    pub fn new(obj_text: String, initial_transform: TransformMatrix) -> Self {
+        Self::from_faces(load_obj(&obj_text), *initial_transform.matrix())
     }
 
     pub fn points_to_float32array(&self) -> Vec<f32> {
@@ -152,9 +86,9 @@ impl GameObject {
                 .faces
                 .iter()
                 .flat_map(|face| {
-                    face.break_into_triangles()
+                    face.break_into_normal_triangles()
                         .into_iter()
-                        .map(move |_triangle| face.normal().into_inner())
+                        .map(|normal| normal.into_inner())
                 })
                 .collect::<Vec<_>>(),
         )
@@ -172,67 +106,163 @@ impl GameObject {
             .collect()
     }
 
+    /// For debugging: the furthest any face's points stray from that face's plane
+    pub fn max_planarity_error(&self) -> f64 {
+        self.faces
+            .iter()
+            .map(|face| face.planarity_error())
+            .fold(0.0, f64::max)
+    }
+
+    /// For debugging: how many faces have a point further than `tolerance` from the face's plane
+    pub fn count_non_planar_faces(&self, tolerance: f64) -> usize {
+        self.faces
+            .iter()
+            .filter(|face| !face.is_planar(tolerance))
+            .count()
+    }
+}
+
+impl GameObject {
+    /// Builds a game object from faces that are already loaded
+    pub fn from_faces(faces: Vec<Face<f64>>, initial_transform: Matrix4<f64>) -> Self {
+        let num_points = faces
+            .iter()
+            .fold(0, |count, face| count + (face.break_into_triangles().len()));
+
+        Self {
+            collision_shape: CollisionShape::from_faces(&faces),
+            faces,
+            num_points,
+            initial_transform,
+            dynamic_transform: Matrix4::identity(),
+            world_transform: Matrix4::identity(),
+            mesh: None,
+            material: None,
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Remembers which mesh the faces were loaded from
+    pub fn with_mesh(mut self, mesh: &str) -> Self {
+        self.mesh = Some(mesh.to_string());
+        self
+    }
+
+    /// The axis-aligned box around the object's points once they have been transformed,
+    /// or nothing if the object has no points
+    fn bounding_box(&self, transform: &Matrix4<f64>) -> Option<BoundingBox<f64, 3>> {
+        let points: Vec<Point3<f64>> = self
+            .faces
+            .iter()
+            .flat_map(|face| face.points())
+            .map(|point| transform.transform_point(point))
+            .collect();
+        if points.is_empty() {
+            None
+        } else {
+            Some(BoundingBox::from_points(&points))
+        }
+    }
+
     fn get_transform_matrix(&self, parent_transform: &Matrix4<f64>) -> Matrix4<f64> {
         parent_transform * self.initial_transform * self.dynamic_transform
     }
 }
 
+/// How many points the IK is solved at along a straight line move
+const CARTESIAN_SEGMENTS: usize = 32;
+/// How many other starting poses the IK tries when its first answer collides
+const COLLISION_RESTARTS: usize = 16;
+/// How many poses along a planned move are checked for collisions
+const COLLISION_PATH_SAMPLES: usize = 16;
+/// The grey objects without a material are lit as
+const DEFAULT_BASE_COLOR: f32 = 0.3;
+
 #[wasm_bindgen]
 pub struct GameState {
-    camera_position: Point3<f64>,
-    camera_direction: UnitVector3<f64>,
-    camera_velocity: Vector3<f64>,
-    aspect_ratio: f64,
-    game_objects: Vec<GameObject>,
+    camera: Camera,
+    timestep: FixedTimestep,
+    game_objects: SceneGraph<GameObject>,
     target: Point3<f64>,
     light_position: Point3<f64>,
+    /// The joints the IK turns, from the base of the arm out
+    arm_joints: Vec<ArmJoint>,
+    /// The point moved to the target, in the last arm joint's coordinates
+    end_effector_tip: Point3<f64>,
+    /// The way the end effector points, in the last arm joint's coordinates
+    end_effector_forward: UnitVector3<f64>,
+    /// The way up for the end effector when it isn't rolled, in the last arm joint's coordinates
+    end_effector_up: UnitVector3<f64>,
+    target_orientation: TargetOrientation,
+    ik_settings: SolverSettings,
+    /// How close the last IK update got to the target
+    ik_result: Option<IkResult>,
+    /// The clips moving game objects, moved on by `advance`
+    animations: AnimationPlayer,
+    /// How the arm gets to a new target
+    motion_planning: MotionPlanning,
+    /// The arm's move to the latest target, if it's still on its way
+    trajectory: Option<Trajectory>,
+    floor: Option<Floor>,
+    /// Game objects that are meant to touch (`None` for the floor),
+    /// on top of every object touching its parent
+    allowed_contacts: Vec<(String, Option<String>)>,
+    /// Whether the IK turns down poses where the arm hits itself or the floor
+    avoid_collisions: bool,
 }
 
-/// A little wrapper around the nalgebra matrix4 class, for JS use,
-/// so we only have to generate binding code for the methods we actually need.
-#[wasm_bindgen]
-#[derive(Clone, Copy)]
-pub struct TransformMatrix(Matrix4<f64>);
+/// Which way the end effector should face at the target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetOrientation {
+    Free,
+    /// The end effector points this way, rolled any amount
+    Direction(UnitVector3<f64>),
+    /// The end effector points this way, rolled this far from upright
+    DirectionRoll(UnitVector3<f64>, f64),
+    /// The last arm joint's rotation, in world coordinates
+    Rotation(UnitQuaternion<f64>),
+}
 
-#[wasm_bindgen]
-impl TransformMatrix {
-    pub fn translation(x: f64, y: f64, z: f64) -> Self {
-        Self(Translation3::new(x, y, z).to_homogeneous())
-    }
-    pub fn rotation_euler(roll: f64, pitch: f64, yaw: f64) -> Self {
-        Self(Rotation3::from_euler_angles(roll, pitch, yaw).to_homogeneous())
-    }
-    pub fn identity() -> Self {
-        Self(Matrix4::identity())
-    }
-    pub fn to_f64_array(&self) -> Vec<f64> {
-        self.0.as_slice().to_vec()
-    }
-    pub fn times(&self, other: &TransformMatrix) -> TransformMatrix {
-        Self(self.0 * other.0)
-    }
+/// A game object the IK can turn around one axis
+#[derive(Debug, Clone)]
+struct ArmJoint {
+    name: String,
+    joint: Joint,
+    angle: f64,
+    /// How fast planned motions can turn the joint
+    limits: MotionLimits,
 }
 
+/// What JS needs to draw one game object.
+/// The GPU buffers are kept on the JS side, keyed by the object's name.
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct ObjectRenderSnapshot {
-    obj_vert_buffer: JsValue,
-    obj_normals_buffer: JsValue,
-    obj_uvs_buffer: JsValue,
+    name: String,
+    base_color: [f32; 3],
     pub num_points: usize,
     pub transform: TransformMatrix,
+    /// Whether the world transform changed since the previous snapshot
+    pub changed: bool,
 }
 
 #[wasm_bindgen]
 impl ObjectRenderSnapshot {
-    pub fn get_obj_vert_buffer(&self) -> JsValue {
-        self.obj_vert_buffer.clone()
-    }
-    pub fn get_obj_normals_buffer(&self) -> JsValue {
-        self.obj_normals_buffer.clone()
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
     }
-    pub fn get_obj_uvs_buffer(&self) -> JsValue {
-        self.obj_uvs_buffer.clone()
+
+    /// The red, green and blue the object is lit as,
+    /// from its material or the same grey for everything without one
+    #[wasm_bindgen(getter)]
+    pub fn base_color(&self) -> Vec<f32> {
+        self.base_color.to_vec()
     }
 }
 
@@ -246,6 +276,14 @@ impl RenderSnapshot {
     pub fn object_ids(&self) -> Vec<usize> {
         self.objects.iter().enumerate().map(|(i, _)| i).collect()
     }
+    pub fn changed_object_ids(&self) -> Vec<usize> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| object.changed)
+            .map(|(i, _)| i)
+            .collect()
+    }
     pub fn get_object(&self, object_index: usize) -> ObjectRenderSnapshot {
         self.objects[object_index].clone()
     }
@@ -258,13 +296,27 @@ impl GameState {
     pub fn new() -> Self {
         console_error_panic_hook::set_once();
         Self {
-            aspect_ratio: 1.0,
-            camera_position: point![3.0, 0.0, 0.0],
-            camera_direction: UnitVector3::new_normalize(vector![-1.0, 0.0, 0.0]),
-            camera_velocity: Vector3::zeros(),
-            game_objects: vec![],
+            camera: Camera::new(
+                point![3.0, 0.0, 0.0],
+                UnitVector3::new_normalize(vector![-1.0, 0.0, 0.0]),
+            ),
+            timestep: FixedTimestep::default(),
+            game_objects: SceneGraph::new(),
             target: Point3::origin(),
             light_position: Point3::origin(),
+            arm_joints: vec![],
+            end_effector_tip: Point3::origin(),
+            end_effector_forward: Vector3::x_axis(),
+            end_effector_up: Vector3::y_axis(),
+            target_orientation: TargetOrientation::Free,
+            ik_settings: SolverSettings::default(),
+            ik_result: None,
+            animations: AnimationPlayer::new(),
+            motion_planning: MotionPlanning::Snap,
+            trajectory: None,
+            floor: None,
+            allowed_contacts: vec![],
+            avoid_collisions: false,
         }
     }
 
@@ -273,46 +325,125 @@ impl GameState {
     }
 
     #[wasm_bindgen]
-    pub fn get_render_snapshot(&self) -> RenderSnapshot {
-        let mut snapshot_objects: Vec<ObjectRenderSnapshot> = self
-            .game_objects
-            .iter()
-            .map(|game_object| ObjectRenderSnapshot {
-                obj_vert_buffer: game_object.obj_vert_buffer(),
-                obj_normals_buffer: game_object.obj_normals_buffer(),
-                obj_uvs_buffer: game_object.obj_uvs_buffer(),
-                num_points: game_object.num_points,
-                // Just a placeholder until we go back and accumulate the parent transforms below
-                transform: TransformMatrix(game_object.initial_transform),
+    pub fn get_render_snapshot(&mut self) -> RenderSnapshot {
+        let changed = self.update_world_transforms();
+        RenderSnapshot {
+            objects: self
+                .game_objects
+                .depth_first()
+                .into_iter()
+                .map(|id| {
+                    let game_object = self.game_objects.get(id).unwrap();
+                    ObjectRenderSnapshot {
+                        name: self.game_objects.name(id).unwrap().to_string(),
+                        base_color: game_object.material.as_ref().map_or(
+                            [DEFAULT_BASE_COLOR; 3],
+                            |material| {
+                                let [r, g, b, _] = material.base_color;
+                                [r as f32, g as f32, b as f32]
+                            },
+                        ),
+                        num_points: game_object.num_points,
+                        transform: TransformMatrix::from(game_object.world_transform),
+                        changed: changed.contains(&id),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds a game object under the object named `parent`,
+    /// or at the top level if there is no parent.
+    /// Fails if the parent doesn't exist or the name is already taken.
+    #[wasm_bindgen]
+    pub fn add_game_object(
+        &mut self,
+        name: &str,
+        parent: Option<String>,
+        game_object: GameObject,
+    ) -> Result<NodeId, JsError> {
+        let parent = parent
+            .map(|parent| self.find_existing(&parent))
+            .transpose()
+            .and_then(|parent| self.game_objects.add(name, parent, game_object));
+        parent.map_err(|err| JsError::new(&format!("Could not add {}: {}", name, err)))
+    }
+
+    /// Removes a game object and all of the objects attached to it
+    #[wasm_bindgen]
+    pub fn remove_game_object(&mut self, name: &str) -> Result<(), JsError> {
+        let id = self.find_existing(name)?;
+        self.game_objects.remove(id)?;
+        Ok(())
+    }
+
+    /// Attaches a game object (and the objects attached to it) to a different parent,
+    /// or to the top level if there is no parent.
+    /// Fails if either doesn't exist, or the new parent is attached to the game object.
+    #[wasm_bindgen]
+    pub fn reparent_game_object(
+        &mut self,
+        name: &str,
+        new_parent: Option<String>,
+    ) -> Result<(), JsError> {
+        self.find_existing(name)
+            .and_then(|id| {
+                let new_parent = new_parent
+                    .map(|parent| self.find_existing(&parent))
+                    .transpose()?;
+                self.game_objects.reparent(id, new_parent)
             })
-            .collect();
+            .map_err(|err| JsError::new(&format!("Could not reparent {}: {}", name, err)))
+    }
 
-        for (i, game_object) in self.game_objects.iter().enumerate() {
-            let parent_index = game_object.parent_index;
-            assert!(parent_index <= i);
-            // Since we are iterating through in order,
-            // we will have already calculated the parent's transform,
-            // and can pass it into get_transform_matrix
+    #[wasm_bindgen]
+    pub fn find_game_object(&self, name: &str) -> Option<NodeId> {
+        self.game_objects.find(name)
+    }
 
-            // If parent index is itself, that means it has no parent,
-            // so we'll pass the identity matrix as the parent.
-            let identity = Matrix4::identity();
-            snapshot_objects[i].transform =
-                TransformMatrix(game_object.get_transform_matrix(if parent_index == i {
-                    &identity
-                } else {
-                    &snapshot_objects[game_object.parent_index].transform.0
-                }))
-        }
+    /// Replaces the transform a game object has relative to its parent
+    /// before any joint movement is applied
+    #[wasm_bindgen]
+    pub fn set_initial_transform(
+        &mut self,
+        name: &str,
+        initial_transform: TransformMatrix,
+    ) -> Result<(), JsError> {
+        let id = self.find_existing(name)?;
+        self.game_objects.get_mut(id).unwrap().initial_transform = *initial_transform.matrix();
+        self.game_objects.mark_dirty(id)?;
+        Ok(())
+    }
 
-        RenderSnapshot {
-            objects: snapshot_objects,
-        }
+    /// Replaces the game objects, the arm and the light with a scene's, keeping the camera.
+    /// Every mesh the scene uses has to have been added to it.
+    #[wasm_bindgen]
+    pub fn load_scene(&mut self, scene: &SceneDescription) -> Result<(), JsError> {
+        let mut loaded =
+            GameState::from_scene(scene).map_err(|err| JsError::new(&err.to_string()))?;
+        std::mem::swap(&mut loaded.camera, &mut self.camera);
+        std::mem::swap(&mut loaded.timestep, &mut self.timestep);
+        *self = loaded;
+        Ok(())
     }
 
+    /// The scene as it is now, with the arm in its current pose, as JSON
     #[wasm_bindgen]
-    pub fn add_game_object(&mut self, game_object: GameObject) {
-        self.game_objects.push(game_object);
+    pub fn export_scene(&self) -> String {
+        self.scene_description().to_json().to_string_pretty()
+    }
+
+    /// The game objects as they're drawn now, as a GLB file
+    #[wasm_bindgen]
+    pub fn export_glb(&self) -> Vec<u8> {
+        self.to_gltf().to_glb()
+    }
+
+    /// A copy of a game object, e.g. to fill the GPU buffers for a scene that has been loaded
+    #[wasm_bindgen]
+    pub fn get_game_object(&self, name: &str) -> Option<GameObject> {
+        let id = self.game_objects.find(name)?;
+        self.game_objects.get(id).cloned()
     }
 
     #[wasm_bindgen]
@@ -328,94 +459,444 @@ impl GameState {
         self.light_position.x = x;
         self.light_position.y = y;
         self.light_position.z = z;
+        // The light doesn't make any difference to the arm, only its marker moves
+        self.place_markers();
+    }
+
+    /// Lets the IK turn a game object around `axis` (in the object's own coordinates),
+    /// as the next joint out along the arm.
+    /// Each joint has to be attached, directly or not, to the joint before it.
+    #[wasm_bindgen]
+    pub fn add_arm_joint(
+        &mut self,
+        name: &str,
+        axis_x: f64,
+        axis_y: f64,
+        axis_z: f64,
+        min_angle: f64,
+        max_angle: f64,
+    ) -> Result<(), JsError> {
+        Unit::try_new(vector![axis_x, axis_y, axis_z], 1e-12)
+            .ok_or_else(|| "its axis is zero".to_string())
+            .and_then(|axis| {
+                self.try_add_arm_joint(name, Joint::new(axis).with_limits(min_angle, max_angle))
+            })
+            .map_err(|err| JsError::new(&format!("Could not add arm joint {}: {}", name, err)))
+    }
+
+    /// Limits how fast an arm joint turns when the arm is moving to a new target,
+    /// in radians per second and radians per second squared
+    #[wasm_bindgen]
+    pub fn set_arm_joint_motion_limits(
+        &mut self,
+        name: &str,
+        max_velocity: f64,
+        max_acceleration: f64,
+    ) -> Result<(), JsError> {
+        let arm_joint = self.arm_joint_mut(name).map_err(|err| JsError::new(&err))?;
+        arm_joint.limits = MotionLimits {
+            max_velocity,
+            max_acceleration,
+        };
+        Ok(())
+    }
+
+    /// Chooses how the arm gets to new targets.
+    /// Anything other than snapping moves the arm a bit more on each `update`.
+    #[wasm_bindgen]
+    pub fn set_motion_planning(&mut self, motion_planning: MotionPlanning) {
+        self.motion_planning = motion_planning;
+    }
+
+    /// Whether the arm is still on its way to the latest target
+    #[wasm_bindgen]
+    pub fn is_arm_moving(&self) -> bool {
+        self.trajectory.is_some()
+    }
+
+    /// Makes everything on the other side of a plane from the normal solid for the arm
+    #[wasm_bindgen]
+    pub fn set_floor(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: f64,
+        normal_x: f64,
+        normal_y: f64,
+        normal_z: f64,
+    ) {
+        let normal = Unit::try_new(vector![normal_x, normal_y, normal_z], 1e-12)
+            .unwrap_or_else(|| panic!("Could not set the floor: its normal is zero"));
+        self.floor = Some(Floor {
+            point: point![x, y, z],
+            normal,
+        });
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_floor(&mut self) {
+        self.floor = None;
+    }
+
+    /// Stops reporting contacts between two game objects (or one and the floor, for no `second`),
+    /// e.g. for parts that are bolted together.
+    /// Objects can always touch their parents.
+    #[wasm_bindgen]
+    pub fn allow_contact(&mut self, first: &str, second: Option<String>) {
+        self.allowed_contacts.push((first.to_string(), second));
+    }
+
+    /// Whether the IK should turn down poses where the arm hits itself or the floor,
+    /// and look for others instead
+    #[wasm_bindgen]
+    pub fn set_collision_avoidance(&mut self, avoid_collisions: bool) {
+        self.avoid_collisions = avoid_collisions;
+    }
+
+    /// Where the arm overlaps itself or the floor, as it is now
+    #[wasm_bindgen]
+    pub fn arm_collisions(&self) -> CollisionReport {
+        CollisionReport::new(self.arm_contacts_at(&self.arm_angles()))
+    }
+
+    /// Sets the point the IK moves to the target, in the last arm joint's coordinates
+    #[wasm_bindgen]
+    pub fn set_end_effector_tip(&mut self, x: f64, y: f64, z: f64) {
+        self.end_effector_tip = point![x, y, z];
+    }
+
+    /// Sets the way the end effector points, and the way that is up for it when it isn't rolled,
+    /// both in the last arm joint's coordinates
+    #[wasm_bindgen]
+    pub fn set_end_effector_axes(
+        &mut self,
+        forward_x: f64,
+        forward_y: f64,
+        forward_z: f64,
+        up_x: f64,
+        up_y: f64,
+        up_z: f64,
+    ) {
+        self.end_effector_forward = Unit::new_normalize(vector![forward_x, forward_y, forward_z]);
+        self.end_effector_up = Unit::new_normalize(vector![up_x, up_y, up_z]);
         self.update_inverse_kinematics();
     }
 
-    #[inline]
-    fn update_inverse_kinematics(&mut self) {
-        // Light ball
-        self.game_objects[0].dynamic_transform =
-            Translation3::from(self.light_position).to_homogeneous();
-
-        // Target icosahedron
-        self.game_objects[1].dynamic_transform = Translation3::from(self.target).to_homogeneous();
-
-        // Onshape exports the OBJ in meters,
-        // this scales to inches to match how my model is defined in onshape
-        const INCHES: f64 = 0.0254;
-
-        const ARM_1_LENGTH: f64 = 72.0 * INCHES;
-        const ARM_2_TO_WRIST_END: f64 = (48.0 + 30.0) * INCHES;
-        const ARM_1_START_FROM_GROUND: f64 = 10.0 * INCHES;
-        const END_LENGTH: f64 = 33.0 * INCHES;
-
-        let end_direction = vector![0.0, 1.0, 0.0].normalize();
-        let end_vector = end_direction * END_LENGTH;
-
-        let wrist_end_position = self.target - end_vector;
-
-        let shoulder_angle = f64::atan2(wrist_end_position.z, wrist_end_position.x);
-        // Turn the shoulder joint to face the target position
-        self.game_objects[3].dynamic_transform =
-            Rotation3::from_euler_angles(0.0, 0.0, PI / 2.0 + shoulder_angle).to_homogeneous();
-
-        let shoulder_rotation_absolute = Rotation3::from_euler_angles(0.0, shoulder_angle, 0.0);
-        let shoulder_direction =
-            shoulder_rotation_absolute.transform_vector(&vector![1.0, 0.0, 0.0]);
-        let perpendicular_to_shoulder_direction =
-            Rotation3::from_euler_angles(0.0, shoulder_angle + PI / 2.0, 0.0)
-                .transform_vector(&vector![1.0, 0.0, 0.0]);
-
-        let delta_y = wrist_end_position.y + ARM_1_START_FROM_GROUND;
-        let dist_to_target =
-            (wrist_end_position.x.powi(2) + delta_y.powi(2) + wrist_end_position.z.powi(2)).sqrt();
-        let dist_in_xz_plane = (wrist_end_position.x.powi(2) + wrist_end_position.z.powi(2)).sqrt();
-
-        let n = (-ARM_1_LENGTH.powi(2) + ARM_2_TO_WRIST_END.powi(2) + dist_to_target.powi(2))
-            / (2.0 * dist_to_target);
-        let m = dist_to_target - n;
-        let h = (ARM_2_TO_WRIST_END.powi(2) - n.powi(2)).sqrt();
-        let c = (n / h).atan();
-        let d = (m / h).atan();
-        let arm_2_angle = c + d;
-        let arm_1_angle = (h / m).atan() - (delta_y / dist_in_xz_plane).atan();
-
-        let end_projected_in_shoulder_direction = -end_direction.dot(&shoulder_direction);
-        let end_projected_in_up = -end_direction.y;
-        let c = f64::atan(end_projected_in_shoulder_direction / end_projected_in_up);
-        let end_angle = arm_1_angle + arm_2_angle - PI / 2.0 - c;
-
-        // TODO: which euler angle?
-        let arm_2_up = shoulder_rotation_absolute.transform_vector(
-            &Rotation3::from_euler_angles(arm_1_angle + arm_2_angle, 0.0, 0.0)
-                .transform_vector(&vector![0.0, 1.0, 0.0]),
+    /// Points the end effector along a direction (in world coordinates), rolled any amount
+    #[wasm_bindgen]
+    pub fn set_target_direction(&mut self, x: f64, y: f64, z: f64) {
+        self.target_orientation =
+            TargetOrientation::Direction(Unit::new_normalize(vector![x, y, z]));
+        self.update_inverse_kinematics();
+    }
+
+    /// Points the end effector along a direction (in world coordinates),
+    /// rolled `roll` radians around it from upright
+    #[wasm_bindgen]
+    pub fn set_target_direction_roll(&mut self, x: f64, y: f64, z: f64, roll: f64) {
+        self.target_orientation =
+            TargetOrientation::DirectionRoll(Unit::new_normalize(vector![x, y, z]), roll);
+        self.update_inverse_kinematics();
+    }
+
+    /// Turns the last arm joint to a rotation (in world coordinates), given as a quaternion
+    #[wasm_bindgen]
+    pub fn set_target_rotation(&mut self, x: f64, y: f64, z: f64, w: f64) {
+        self.target_orientation = TargetOrientation::Rotation(UnitQuaternion::from_quaternion(
+            Quaternion::new(w, x, y, z),
+        ));
+        self.update_inverse_kinematics();
+    }
+
+    /// Moves the end effector's tip to the pose's translation,
+    /// and turns the last arm joint to the pose's rotation (the scale is ignored)
+    #[wasm_bindgen]
+    pub fn set_target_pose(&mut self, pose: &Transform) {
+        self.target = Point3::from(pose.translation);
+        self.target_orientation = TargetOrientation::Rotation(pose.rotation);
+        self.update_inverse_kinematics();
+    }
+
+    /// Lets the end effector face any way, so only its position is solved for
+    #[wasm_bindgen]
+    pub fn free_target_orientation(&mut self) {
+        self.target_orientation = TargetOrientation::Free;
+        self.update_inverse_kinematics();
+    }
+
+    /// Changes how far an arm joint can turn, and moves the arm to respect the new limits
+    #[wasm_bindgen]
+    pub fn set_arm_joint_limits(
+        &mut self,
+        name: &str,
+        min_angle: f64,
+        max_angle: f64,
+    ) -> Result<(), JsError> {
+        let arm_joint = self.arm_joint_mut(name).map_err(|err| JsError::new(&err))?;
+        arm_joint.joint = arm_joint.joint.clone().with_limits(min_angle, max_angle);
+        arm_joint.angle = arm_joint.joint.clamp(arm_joint.angle);
+        self.update_inverse_kinematics();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_ik_solver(&mut self, solver: Solver) {
+        self.ik_settings.solver = solver;
+    }
+
+    /// How close the arm got to the target the last time it moved,
+    /// or nothing if the arm hasn't been set up
+    #[wasm_bindgen]
+    pub fn ik_result(&self) -> Option<IkResult> {
+        self.ik_result
+    }
+
+    /// Where a game object's origin is, in world coordinates (x, y, z)
+    #[wasm_bindgen]
+    pub fn world_position(&self, name: &str) -> Option<Vec<f64>> {
+        let position = self
+            .world_transform(name)?
+            .transform_point(&Point3::origin());
+        Some(position.coords.as_slice().to_vec())
+    }
+
+    /// Which way a game object is turned, in world coordinates,
+    /// as a quaternion (x, y, z, w)
+    #[wasm_bindgen]
+    pub fn world_orientation(&self, name: &str) -> Option<Vec<f64>> {
+        Some(self.world_pose(name)?.rotation_array())
+    }
+
+    /// A game object's world transform, split into translation, rotation and scale.
+    /// Nothing if the object (or one of its parents) has been scaled flat.
+    #[wasm_bindgen]
+    pub fn world_pose(&self, name: &str) -> Option<Transform> {
+        Transform::decompose(&self.world_transform(name)?).ok()
+    }
+
+    /// The axis-aligned box around a game object in world coordinates,
+    /// as its lowest corner followed by its highest corner (x, y, z, x, y, z).
+    /// Nothing if the object has no geometry.
+    #[wasm_bindgen(js_name = world_bounding_box)]
+    pub fn world_bounding_box_array(&self, name: &str) -> Option<Vec<f64>> {
+        let bounding_box = self.world_bounding_box(name)?;
+        Some(
+            bounding_box
+                .min_pt
+                .iter()
+                .chain(bounding_box.max_pt.iter())
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Where the tip of the end effector is, in world coordinates (x, y, z)
+    #[wasm_bindgen(js_name = end_effector_tip)]
+    pub fn end_effector_tip_array(&self) -> Option<Vec<f64>> {
+        Some(self.end_effector_tip()?.coords.as_slice().to_vec())
+    }
+
+    /// Moves the light and target markers to the light and the target.
+    /// Only the ones that actually moved are reported as changed.
+    fn place_markers(&mut self) {
+        self.set_dynamic_transform(
+            "light_ball",
+            Translation3::from(self.light_position).to_homogeneous(),
         );
+        self.set_dynamic_transform("target", Translation3::from(self.target).to_homogeneous());
+    }
 
-        let wrist_angle = PI / 2.0
-            + f64::atan2(
-                end_direction.dot(&arm_2_up),
-                end_direction.dot(&perpendicular_to_shoulder_direction),
-            );
+    fn update_inverse_kinematics(&mut self) {
+        self.place_markers();
 
-        if !arm_1_angle.is_nan() {
-            // Arm 1 joint
-            self.game_objects[4].dynamic_transform =
-                Rotation3::from_euler_angles(0.0, 0.0, -arm_1_angle).to_homogeneous();
+        let chain = match self.arm_chain() {
+            Some(chain) => chain,
+            // The arm hasn't been set up (yet)
+            None => return,
+        };
+        let start = self.arm_angles();
+        let target = Target {
+            position: self.target,
+            orientation: self.end_effector_orientation(),
+        };
+        let limits: Vec<MotionLimits> = self.arm_joints.iter().map(|joint| joint.limits).collect();
+        let (trajectory, result) = match self.motion_planning {
+            MotionPlanning::Snap | MotionPlanning::Joint => {
+                let (end, result) = self.solve_avoiding_collisions(&chain, &start, &target);
+                self.ik_result = Some(result);
+                let end = match end {
+                    Some(end) => end,
+                    None => return,
+                };
+                if self.motion_planning == MotionPlanning::Snap {
+                    self.trajectory = None;
+                    self.set_arm_angles(&end);
+                    return;
+                }
+                (
+                    Trajectory::joint_space(&chain, &start, &end, &limits),
+                    result,
+                )
+            }
+            MotionPlanning::Cartesian => Trajectory::cartesian(
+                &chain,
+                &start,
+                &target,
+                &self.ik_settings,
+                &limits,
+                CARTESIAN_SEGMENTS,
+            ),
+        };
+        if self.avoid_collisions && self.path_collides(&trajectory) {
+            self.ik_result = Some(IkResult {
+                status: IkStatus::Collides,
+                ..result
+            });
+            return;
         }
-        if !arm_2_angle.is_nan() {
-            // Arm 2 joint
-            self.game_objects[5].dynamic_transform =
-                Rotation3::from_euler_angles(0.0, 0.0, arm_2_angle).to_homogeneous();
+        self.ik_result = Some(result);
+        // The new move starts from wherever the arm has got to, at rest
+        if trajectory.is_finished() {
+            self.trajectory = None;
+            self.set_arm_angles(trajectory.end());
+        } else {
+            self.trajectory = Some(trajectory);
         }
-        if !wrist_angle.is_nan() {
-            // Wrist joint
-            self.game_objects[6].dynamic_transform =
-                Rotation3::from_euler_angles(0.0, 0.0, wrist_angle).to_homogeneous();
+    }
+
+    /// Solves the IK from the arm's current pose, and if collisions are being avoided
+    /// and that pose collides, from other poses until one doesn't.
+    /// Nothing if every pose collides.
+    fn solve_avoiding_collisions(
+        &self,
+        chain: &Chain,
+        start: &[f64],
+        target: &Target,
+    ) -> (Option<Vec<f64>>, IkResult) {
+        let mut angles = start.to_vec();
+        let result = chain.solve(&mut angles, target, &self.ik_settings);
+        if !self.avoid_collisions || self.arm_contacts_at(&angles).is_empty() {
+            return (Some(angles), result);
+        }
+        // The same starting poses every time, so the arm doesn't jitter between answers
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let mut best: Option<(Vec<f64>, IkResult)> = None;
+        for _ in 0..COLLISION_RESTARTS {
+            let mut angles: Vec<f64> = chain
+                .links
+                .iter()
+                .map(|link| {
+                    let min_angle = link.joint.min_angle.max(-PI);
+                    let max_angle = link.joint.max_angle.min(PI);
+                    rng.gen_range(min_angle..=max_angle)
+                })
+                .collect();
+            let result = chain.solve(&mut angles, target, &self.ik_settings);
+            if !self.arm_contacts_at(&angles).is_empty() {
+                continue;
+            }
+            if result.status == IkStatus::Reached {
+                return (Some(angles), result);
+            }
+            if best
+                .as_ref()
+                .is_none_or(|(_, best)| result.position_error < best.position_error)
+            {
+                best = Some((angles, result));
+            }
         }
-        if !end_angle.is_nan() {
-            // End joint
-            self.game_objects[7].dynamic_transform =
-                Rotation3::from_euler_angles(0.0, 0.0, end_angle).to_homogeneous();
+        match best {
+            Some((angles, result)) => (Some(angles), result),
+            None => (
+                None,
+                IkResult {
+                    status: IkStatus::Collides,
+                    ..result
+                },
+            ),
+        }
+    }
+
+    /// Whether the arm would hit anything along a planned move
+    fn path_collides(&self, trajectory: &Trajectory) -> bool {
+        (1..=COLLISION_PATH_SAMPLES).any(|sample| {
+            let time = trajectory.duration() * sample as f64 / COLLISION_PATH_SAMPLES as f64;
+            !self.arm_contacts_at(&trajectory.angles_at(time)).is_empty()
+        })
+    }
+
+    /// Moves the arm `dt` seconds further along its way to the target
+    fn advance_trajectory(&mut self, dt: f64) {
+        if let Some(trajectory) = &mut self.trajectory {
+            let angles = trajectory.advance(dt);
+            if trajectory.is_finished() {
+                self.trajectory = None;
+            }
+            self.set_arm_angles(&angles);
+        }
+    }
+
+    /// Starts playing a clip, replacing any clip already playing under the same name.
+    /// Game objects moved by more than one clip get a blend of them, by weight.
+    pub fn play_animation(
+        &mut self,
+        name: &str,
+        clip: &AnimationClip,
+        mode: PlaybackMode,
+        weight: f64,
+    ) {
+        self.animations.play(name, clip.clone(), mode, weight);
+    }
+
+    /// Returns whether there was a clip playing under that name.
+    /// The game objects it moved stay where they are.
+    pub fn stop_animation(&mut self, name: &str) -> bool {
+        self.animations.stop(name)
+    }
+
+    /// Returns whether there is a clip playing under that name
+    pub fn set_animation_weight(&mut self, name: &str, weight: f64) -> bool {
+        self.animations.set_weight(name, weight)
+    }
+
+    /// Returns whether there is a clip playing under that name.
+    /// Negative speeds play backwards.
+    pub fn set_animation_speed(&mut self, name: &str, speed: f64) -> bool {
+        self.animations.set_speed(name, speed)
+    }
+
+    /// Moves the playing clips on by `dt` seconds and poses the game objects they animate.
+    /// Arm joints only take the part of the rotation about their axis, within their limits.
+    fn advance_animations(&mut self, dt: f64) {
+        self.animations.advance(dt);
+        for (name, transform) in self.animations.sample() {
+            match self.arm_joints.iter_mut().find(|joint| joint.name == name) {
+                Some(arm_joint) => {
+                    let axis = arm_joint.joint.axis.into_inner();
+                    let rotation = transform.rotation;
+                    let twist = 2.0 * rotation.imag().dot(&axis).atan2(rotation.w);
+                    arm_joint.angle = arm_joint.joint.clamp(twist);
+                    let transform = arm_joint.joint.rotation(arm_joint.angle);
+                    self.set_dynamic_transform(&name, transform);
+                }
+                None => self.set_dynamic_transform(&name, transform.to_homogeneous()),
+            }
+        }
+    }
+
+    /// Adds a keyframe to a clip with each arm joint's current rotation,
+    /// so the arm can play back a motion posed (or solved) one step at a time
+    pub fn record_arm_keyframe(
+        &self,
+        clip: &mut AnimationClip,
+        time: f64,
+        interpolation: Interpolation,
+    ) {
+        for arm_joint in &self.arm_joints {
+            let rotation = UnitQuaternion::from_axis_angle(&arm_joint.joint.axis, arm_joint.angle);
+            clip.node_mut(&arm_joint.name)
+                .rotation
+                .add(time, rotation, interpolation);
         }
     }
 
@@ -433,158 +914,1349 @@ impl GameState {
         cursor_movement_y: f64,
         delta_time_ms: usize,
     ) {
-        if !is_active {
-            // Reset the velocity so when the frame becomes active again it doesn't jump
-            self.camera_velocity = Vector3::zeros();
-            // Don't update the position if not active
-            return;
-        }
-        let up = self.up().into_inner();
-        // In seconds, time since last render
-        let dt = delta_time_ms as f64 / 1000.0;
-        let rotation_scale = 0.01;
-        let rotation_x = rotation_scale * cursor_movement_x;
-        let rotation_y = rotation_scale * cursor_movement_y;
-        let forwards = self.camera_direction.into_inner();
-        let right = forwards.cross(&up);
-        self.camera_direction = UnitVector3::new_normalize(
-            UnitQuaternion::new(up * rotation_x + right * rotation_y)
-                .transform_vector(&self.camera_direction),
-        );
-        self.camera_position += self.camera_velocity * dt;
-        let accel = 15.0;
-        let decel = 0.15;
-        let forwards = self.camera_direction.into_inner();
-        let right = forwards.cross(&up);
-        if input_w {
-            self.camera_velocity += accel * dt * forwards;
-        } else if input_s {
-            self.camera_velocity -= accel * dt * forwards;
+        let input = CameraInput {
+            w: input_w,
+            a: input_a,
+            s: input_s,
+            d: input_d,
+            cursor_movement_x,
+            cursor_movement_y,
+        };
+        let up = self.up();
+        if is_active {
+            self.camera.rotate(&up, &input);
         } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&forwards)) * forwards;
+            // Reset the velocity so when the frame becomes active again it doesn't jump
+            self.camera.velocity = Vector3::zeros();
+            self.camera.begin_step();
         }
-        if input_a {
-            self.camera_velocity += accel * dt * right;
-        } else if input_d {
-            self.camera_velocity -= accel * dt * right;
-        } else {
-            self.camera_velocity -= decel * (self.camera_velocity.dot(&right)) * right;
+        // Everything moves in fixed steps, so the arm, its collision checks, the animations
+        // and the camera all move the same at any frame rate
+        for _ in 0..self.timestep.advance(delta_time_ms as f64) {
+            let dt = self.timestep.dt();
+            // The arm and the animations keep moving while the canvas isn't focused
+            self.advance_trajectory(dt);
+            self.advance_animations(dt);
+            // Don't update the position if not active
+            if is_active {
+                self.camera.step(&up, &input, dt);
+            }
         }
-        // There was a bug where when turning the camera it could continue drifting
-        // Since the third component of the "camera directions" didn't have code to handle deceleration
-        // So I added this
-        let camera_up = forwards.cross(&right);
-        self.camera_velocity -= decel * (self.camera_velocity.dot(&camera_up)) * camera_up;
+    }
+
+    /// Where the camera is drawn, between its last two steps
+    fn rendered_camera(&self) -> Camera {
+        self.camera.interpolated(self.timestep.alpha())
     }
 
     pub fn camera_position(&self) -> Vec<f32> {
-        vec![
-            self.camera_position.x as _,
-            self.camera_position.y as _,
-            self.camera_position.z as _,
-        ]
+        let position = self.rendered_camera().position;
+        vec![position.x as _, position.y as _, position.z as _]
     }
 
     pub fn world_to_camera(&self) -> TransformMatrix {
-        self.world_to_camera_without_camera_translation()
-            .times(&TransformMatrix(Matrix4::new_translation(&Vector3::new(
-                -self.camera_position.x,
-                -self.camera_position.y,
-                -self.camera_position.z,
-            ))))
+        self.rendered_camera().world_to_camera(&self.up())
     }
 
     pub fn world_to_camera_without_camera_translation(&self) -> TransformMatrix {
-        TransformMatrix(
-            // Scale everything in the z direction down
-            // (does not affect the positions of any vertices)
-            // This just reduces the scope of z values to reduce clipping
-            Scale3::new(1.0, 1.0, 0.01).to_homogeneous()
-                * Matrix4::new_perspective(self.aspect_ratio, 30.0, 0.01, 100.0)
-                * Matrix4::look_at_rh(
-                    &Point3::origin(),
-                    &Point3::new(
-                        self.camera_direction.x,
-                        self.camera_direction.y,
-                        self.camera_direction.z,
-                    ),
-                    &self.up(),
-                ),
-        )
+        self.rendered_camera()
+            .world_to_camera_without_camera_translation(&self.up())
     }
 
     #[wasm_bindgen(getter)]
     pub fn aspect_ratio(&self) -> f64 {
-        self.aspect_ratio
+        self.camera.aspect_ratio
     }
 
     #[wasm_bindgen(setter)]
     pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
-        self.aspect_ratio = aspect_ratio;
+        self.camera.aspect_ratio = aspect_ratio;
+    }
+}
+
+impl GameState {
+    /// Recomputes the cached world transforms of the game objects that moved,
+    /// along with everything attached to them.
+    /// Returns the game objects that were recomputed.
+    pub fn update_world_transforms(&mut self) -> Vec<NodeId> {
+        let dirty = self.game_objects.take_dirty();
+        for &id in &dirty {
+            // Parents come before their children,
+            // so the parent's cached transform is already up to date
+            let parent_transform = self
+                .game_objects
+                .parent(id)
+                .map(|parent| self.game_objects.get(parent).unwrap().world_transform)
+                .unwrap_or_else(Matrix4::identity);
+            let game_object = self.game_objects.get_mut(id).unwrap();
+            game_object.world_transform = game_object.get_transform_matrix(&parent_transform);
+        }
+        dirty
+    }
+
+    /// The object-to-world transform of the game object with the given name,
+    /// computed from its ancestors rather than the cache
+    pub fn world_transform(&self, name: &str) -> Option<Matrix4<f64>> {
+        self.game_objects
+            .find(name)
+            .map(|id| self.world_transform_of(id))
+    }
+
+    pub fn world_bounding_box(&self, name: &str) -> Option<BoundingBox<f64, 3>> {
+        let id = self.game_objects.find(name)?;
+        self.game_objects
+            .get(id)
+            .unwrap()
+            .bounding_box(&self.world_transform_of(id))
+    }
+
+    /// Where the tip of the end effector is, in world coordinates.
+    /// Nothing if the arm hasn't been set up.
+    pub fn end_effector_tip(&self) -> Option<Point3<f64>> {
+        let last_joint = self.arm_joints.last()?;
+        Some(
+            self.world_transform(&last_joint.name)?
+                .transform_point(&self.end_effector_tip),
+        )
+    }
+
+    fn world_transform_of(&self, id: NodeId) -> Matrix4<f64> {
+        let chain = std::iter::once(id).chain(self.game_objects.ancestors(id));
+        chain.fold(Matrix4::identity(), |transform, id| {
+            self.local_transform(id) * transform
+        })
+    }
+
+    /// The transform from a game object to its parent
+    fn local_transform(&self, id: NodeId) -> Matrix4<f64> {
+        self.game_objects
+            .get(id)
+            .unwrap()
+            .get_transform_matrix(&Matrix4::identity())
+    }
+
+    /// Builds a game state from a scene, once all of the meshes it uses have been added.
+    /// Objects are added in the order they're listed, so parents have to come before their children,
+    /// and the arm's joints from the base out.
+    /// The scene is scaled into metres, so the arm's links are as long as its pivots are apart.
+    pub fn from_scene(scene: &SceneDescription) -> Result<Self, SceneError> {
+        let scene = &scene.in_units(LengthUnit::Metres);
+        let mut game_state = Self::new();
+        for object in &scene.objects {
+            let error = |message: String| SceneError::Object {
+                name: object.name.clone(),
+                message,
+            };
+            let parent = match &object.parent {
+                Some(parent) => Some(
+                    game_state
+                        .game_objects
+                        .find_existing(parent)
+                        .map_err(|err| error(err.to_string()))?,
+                ),
+                None => None,
+            };
+            let mut initial_transform = object.initial_transform;
+            if let Some(pivot) = &object.pivot {
+                let parent_faces = parent
+                    .and_then(|parent| game_state.game_objects.get(parent))
+                    .map_or(&[][..], |parent| &parent.faces);
+                let offset = pivot.offset(parent_faces).ok_or_else(|| {
+                    error("its parent has no mesh to measure its pivot from".to_string())
+                })?;
+                initial_transform = Translation3::from(offset).to_homogeneous() * initial_transform;
+            }
+            let mut game_object = match &object.mesh {
+                Some(mesh) => GameObject::from_faces(scene.mesh(mesh)?.to_vec(), initial_transform)
+                    .with_mesh(mesh),
+                None => GameObject::from_faces(vec![], initial_transform),
+            };
+            game_object.material = object.material.clone();
+            game_state
+                .game_objects
+                .add(&object.name, parent, game_object)
+                .map_err(|err| error(err.to_string()))?;
+            if let Some(joint) = &object.joint {
+                game_state
+                    .try_add_arm_joint(&object.name, joint.joint.clone())
+                    .map_err(error)?;
+                game_state.arm_joints.last_mut().unwrap().limits = joint.limits;
+            }
+        }
+        let angles: Vec<f64> = scene
+            .objects
+            .iter()
+            .filter_map(|object| object.joint.as_ref())
+            .map(|joint| joint.angle)
+            .collect();
+        game_state.set_arm_angles(&angles);
+
+        let arm = &scene.arm;
+        game_state.end_effector_tip = arm.end_effector_tip;
+        game_state.end_effector_forward = arm.end_effector_forward;
+        game_state.end_effector_up = arm.end_effector_up;
+        game_state.target_orientation = arm.target_orientation;
+        game_state.motion_planning = arm.motion_planning;
+        game_state.avoid_collisions = arm.avoid_collisions;
+        game_state.floor = arm.floor;
+        game_state.allowed_contacts = arm.allowed_contacts.clone();
+        game_state.light_position = scene.light_position;
+        game_state.target = scene.target;
+        game_state.place_markers();
+        Ok(game_state)
+    }
+
+    /// The scene as it is now, with the arm joints at their current angles, in metres.
+    /// Meshes are only named, not included, and pivots are written out as the transforms they measured.
+    pub fn scene_description(&self) -> SceneDescription {
+        let objects = self
+            .game_objects
+            .depth_first()
+            .into_iter()
+            .map(|id| {
+                let name = self.game_objects.name(id).unwrap();
+                let game_object = self.game_objects.get(id).unwrap();
+                ObjectDescription {
+                    name: name.to_string(),
+                    parent: self
+                        .game_objects
+                        .parent(id)
+                        .map(|parent| self.game_objects.name(parent).unwrap().to_string()),
+                    mesh: game_object.mesh.clone(),
+                    initial_transform: game_object.initial_transform,
+                    joint: self
+                        .arm_joints
+                        .iter()
+                        .find(|arm_joint| arm_joint.name == name)
+                        .map(|arm_joint| JointDescription {
+                            joint: arm_joint.joint.clone(),
+                            angle: arm_joint.angle,
+                            limits: arm_joint.limits,
+                        }),
+                    material: game_object.material.clone(),
+                    pivot: None,
+                }
+            })
+            .collect();
+        SceneDescription::new(
+            objects,
+            self.light_position,
+            self.target,
+            ArmDescription {
+                end_effector_tip: self.end_effector_tip,
+                end_effector_forward: self.end_effector_forward,
+                end_effector_up: self.end_effector_up,
+                target_orientation: self.target_orientation,
+                motion_planning: self.motion_planning,
+                avoid_collisions: self.avoid_collisions,
+                floor: self.floor,
+                allowed_contacts: self.allowed_contacts.clone(),
+            },
+        )
+    }
+
+    /// The game objects as glTF nodes, parents before their children,
+    /// each with its own mesh if it has any faces.
+    /// The nodes' transforms multiply out to the ones in `get_render_snapshot`,
+    /// and objects without a material get the grey they're drawn in.
+    pub fn to_gltf(&self) -> Gltf {
+        let ids = self.game_objects.depth_first();
+        let index_of: HashMap<NodeId, usize> =
+            ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        let mut meshes = vec![];
+        let mut materials: Vec<Material> = vec![];
+        let nodes = ids
+            .iter()
+            .map(|&id| {
+                let name = self.game_objects.name(id).unwrap();
+                let game_object = self.game_objects.get(id).unwrap();
+                let mesh = (!game_object.faces.is_empty()).then(|| {
+                    let material = game_object.material.clone().unwrap_or(Material {
+                        name: Some("default".to_string()),
+                        base_color: [
+                            DEFAULT_BASE_COLOR.into(),
+                            DEFAULT_BASE_COLOR.into(),
+                            DEFAULT_BASE_COLOR.into(),
+                            1.0,
+                        ],
+                        metallic: 0.0,
+                        ..Material::default()
+                    });
+                    let material = materials
+                        .iter()
+                        .position(|existing| *existing == material)
+                        .unwrap_or_else(|| {
+                            materials.push(material);
+                            materials.len() - 1
+                        });
+                    meshes.push(gltf::Mesh {
+                        name: Some(game_object.mesh.clone().unwrap_or_else(|| name.to_string())),
+                        primitives: vec![Primitive::from_faces(&game_object.faces, Some(material))],
+                    });
+                    meshes.len() - 1
+                });
+                gltf::Node {
+                    name: Some(name.to_string()),
+                    children: self
+                        .game_objects
+                        .children(id)
+                        .iter()
+                        .map(|child| index_of[child])
+                        .collect(),
+                    transform: self.local_transform(id),
+                    mesh,
+                }
+            })
+            .collect();
+        Gltf {
+            nodes,
+            roots: self
+                .game_objects
+                .roots()
+                .iter()
+                .map(|root| index_of[root])
+                .collect(),
+            meshes,
+            materials,
+            images: vec![],
+        }
+    }
+
+    /// The angle of each arm joint, from the base of the arm out
+    pub fn arm_angles(&self) -> Vec<f64> {
+        self.arm_joints.iter().map(|joint| joint.angle).collect()
+    }
+
+    /// Turns each arm joint (from the base of the arm out) to an angle, within its limits
+    pub fn set_arm_angles(&mut self, angles: &[f64]) {
+        assert_eq!(angles.len(), self.arm_joints.len());
+        let mut moved = Vec::with_capacity(angles.len());
+        for (arm_joint, &angle) in self.arm_joints.iter_mut().zip(angles) {
+            arm_joint.angle = arm_joint.joint.clamp(angle);
+            moved.push((
+                arm_joint.name.clone(),
+                arm_joint.joint.rotation(arm_joint.angle),
+            ));
+        }
+        for (name, transform) in moved {
+            self.set_dynamic_transform(&name, transform);
+        }
+    }
+
+    fn end_effector_orientation(&self) -> Orientation {
+        match self.target_orientation {
+            TargetOrientation::Free => Orientation::Free,
+            TargetOrientation::Direction(direction) => Orientation::Direction {
+                axis: self.end_effector_forward,
+                direction,
+            },
+            TargetOrientation::DirectionRoll(direction, roll) => Orientation::from_direction_roll(
+                &self.end_effector_forward,
+                &self.end_effector_up.into_inner(),
+                &direction,
+                roll,
+                &self.up().into_inner(),
+            ),
+            TargetOrientation::Rotation(rotation) => Orientation::Rotation(rotation),
+        }
+    }
+
+    /// The arm joints and the game objects between them, as the IK sees them.
+    /// Nothing if there are no joints, or they have been removed or moved out of order.
+    fn arm_chain(&self) -> Option<Chain> {
+        let ids = self
+            .arm_joints
+            .iter()
+            .map(|joint| self.game_objects.find(&joint.name))
+            .collect::<Option<Vec<_>>>()?;
+        let base = self.game_objects.parent(*ids.first()?);
+        let mut links = Vec::with_capacity(ids.len());
+        for (i, (&id, arm_joint)) in ids.iter().zip(&self.arm_joints).enumerate() {
+            let previous = if i == 0 { base } else { Some(ids[i - 1]) };
+            let mut offset = self.game_objects.get(id).unwrap().initial_transform;
+            // Everything between this joint and the one before it moves along with this one
+            let mut ancestors = self.game_objects.ancestors(id);
+            loop {
+                match ancestors.next() {
+                    ancestor if ancestor == previous => break,
+                    Some(ancestor) => offset = self.local_transform(ancestor) * offset,
+                    None => return None,
+                }
+            }
+            links.push(Link {
+                offset,
+                joint: arm_joint.joint.clone(),
+            });
+        }
+        Some(Chain {
+            base: base.map_or_else(Matrix4::identity, |base| self.world_transform_of(base)),
+            links,
+            tip: self.end_effector_tip,
+        })
+    }
+
+    /// Where the arm would overlap itself or the floor with its joints at these angles.
+    /// The arm is everything attached (directly or not) to the same root as the first joint.
+    pub fn arm_contacts_at(&self, angles: &[f64]) -> Vec<ArmContact> {
+        let bodies: Vec<_> = self
+            .arm_world_transforms(angles)
+            .into_iter()
+            .filter_map(|(id, world_transform)| {
+                let shape = self.game_objects.get(id)?.collision_shape.as_ref()?;
+                Some((
+                    id,
+                    self.game_objects.name(id)?,
+                    shape.placed(&world_transform)?,
+                ))
+            })
+            .collect();
+        let mut contacts = vec![];
+        for (i, (id, name, shape)) in bodies.iter().enumerate() {
+            if let Some(floor) = &self.floor {
+                if !self.is_contact_allowed(name, None) {
+                    if let Some(depth) = shape.floor_penetration(floor) {
+                        contacts.push(ArmContact::new(name, None, depth));
+                    }
+                }
+            }
+            for (other_id, other_name, other_shape) in &bodies[i + 1..] {
+                let attached = self.game_objects.parent(*id) == Some(*other_id)
+                    || self.game_objects.parent(*other_id) == Some(*id);
+                if attached || self.is_contact_allowed(name, Some(other_name)) {
+                    continue;
+                }
+                if let Some(depth) = shape.penetration(other_shape) {
+                    contacts.push(ArmContact::new(name, Some(other_name), depth));
+                }
+            }
+        }
+        contacts
+    }
+
+    fn is_contact_allowed(&self, first: &str, second: Option<&str>) -> bool {
+        self.allowed_contacts
+            .iter()
+            .any(|(a, b)| match (b, second) {
+                (Some(b), Some(second)) => {
+                    (a == first && b == second) || (a == second && b == first)
+                }
+                (None, None) => a == first,
+                _ => false,
+            })
+    }
+
+    /// The world transform of every game object in the arm, with its joints at these angles
+    fn arm_world_transforms(&self, angles: &[f64]) -> Vec<(NodeId, Matrix4<f64>)> {
+        assert_eq!(angles.len(), self.arm_joints.len());
+        let joints: Vec<(NodeId, Matrix4<f64>)> = self
+            .arm_joints
+            .iter()
+            .zip(angles)
+            .filter_map(|(arm_joint, &angle)| {
+                let id = self.game_objects.find(&arm_joint.name)?;
+                Some((id, arm_joint.joint.rotation(arm_joint.joint.clamp(angle))))
+            })
+            .collect();
+        let root = match joints.first() {
+            Some(&(first, _)) => self.game_objects.ancestors(first).last().unwrap_or(first),
+            None => return vec![],
+        };
+        let mut transforms = vec![];
+        let mut stack = vec![(root, Matrix4::identity())];
+        while let Some((id, parent_transform)) = stack.pop() {
+            let game_object = self.game_objects.get(id).unwrap();
+            let dynamic_transform = joints
+                .iter()
+                .find(|(joint, _)| *joint == id)
+                .map_or(game_object.dynamic_transform, |(_, rotation)| *rotation);
+            let transform = parent_transform * game_object.initial_transform * dynamic_transform;
+            transforms.push((id, transform));
+            stack.extend(
+                self.game_objects
+                    .children(id)
+                    .iter()
+                    .map(|&child| (child, transform)),
+            );
+        }
+        transforms
+    }
+
+    /// Adds the next joint out along the arm, unless it isn't attached to the joint before it
+    fn try_add_arm_joint(&mut self, name: &str, joint: Joint) -> Result<(), String> {
+        let id = self
+            .game_objects
+            .find_existing(name)
+            .map_err(|err| err.to_string())?;
+        if let Some(previous) = self.arm_joints.last() {
+            let attached = match self.game_objects.find(&previous.name) {
+                Some(previous_id) => self.game_objects.ancestors(id).any(|id| id == previous_id),
+                None => false,
+            };
+            if !attached {
+                return Err(format!("it is not attached to {}", previous.name));
+            }
+        }
+        self.arm_joints.push(ArmJoint {
+            name: name.to_string(),
+            joint,
+            angle: 0.0,
+            limits: MotionLimits::default(),
+        });
+        Ok(())
+    }
+
+    fn find_existing(&self, name: &str) -> Result<NodeId, SceneGraphError> {
+        self.game_objects.find_existing(name)
+    }
+
+    fn arm_joint_mut(&mut self, name: &str) -> Result<&mut ArmJoint, String> {
+        self.arm_joints
+            .iter_mut()
+            .find(|arm_joint| arm_joint.name == name)
+            .ok_or_else(|| format!("{} is not an arm joint", name))
+    }
+
+    /// Sets the transform of a game object on top of its initial transform.
+    /// Game objects that haven't been added (yet) are skipped,
+    /// and ones whose transform is the same as before aren't reported as changed.
+    fn set_dynamic_transform(&mut self, name: &str, transform: Matrix4<f64>) {
+        if let Some(id) = self.game_objects.find(name) {
+            let game_object = self.game_objects.get_mut(id).unwrap();
+            if game_object.dynamic_transform == transform {
+                return;
+            }
+            game_object.dynamic_transform = transform;
+            self.game_objects.mark_dirty(id).unwrap();
+        }
     }
 }
 
 #[wasm_bindgen]
 pub fn generate_skybox_points() -> Vec<f32> {
-    let faces = {
-        let scale = 100.0;
-        let front_right_bottom = Point3::new(0.5, -0.5, 0.5) * scale;
-        let front_left_bottom = Point3::new(-0.5, -0.5, 0.5) * scale;
-        let front_right_top = Point3::new(0.5, 0.5, 0.5) * scale;
-        let front_left_top = Point3::new(-0.5, 0.5, 0.5) * scale;
-
-        let back_right_bottom = Point3::new(0.5, -0.5, -0.5) * scale;
-        let back_left_bottom = Point3::new(-0.5, -0.5, -0.5) * scale;
-        let back_right_top = Point3::new(0.5, 0.5, -0.5) * scale;
-        let back_left_top = Point3::new(-0.5, 0.5, -0.5) * scale;
-
-        vec![
-            Face::new(vec![
-                front_right_top,
-                front_right_bottom,
-                front_left_bottom,
-                front_left_top,
-            ]),
-            Face::new(vec![
-                front_right_top,
-                back_right_top,
-                back_right_bottom,
-                front_right_bottom,
-            ]),
-            Face::new(vec![
-                front_left_top,
-                front_left_bottom,
-                back_left_bottom,
-                back_left_top,
-            ]),
-            Face::new(vec![
-                front_right_top,
-                front_left_top,
-                back_left_top,
-                back_right_top,
-            ]),
-            Face::new(vec![
-                front_right_bottom,
-                back_right_bottom,
-                back_left_bottom,
-                front_left_bottom,
-            ]),
-            Face::new(vec![
-                back_right_top,
-                back_left_top,
-                back_left_bottom,
-                back_right_bottom,
-            ]),
-        ]
+    graphics_core::skybox::generate_skybox_points()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics_core::{
+        png::Image,
+        rasterizer::{assert_matches_golden, Attribute, Rasterizer},
     };
+    use ik::{IkStatus, Orientation};
+    use nalgebra::{Rotation3, Vector3, Vector4};
+    use std::f64::consts::PI;
+
+    const INCHES: f64 = LengthUnit::Inches.metres();
+
+    fn rotation(roll: f64, pitch: f64, yaw: f64) -> Matrix4<f64> {
+        Rotation3::from_euler_angles(roll, pitch, yaw).to_homogeneous()
+    }
+
+    fn translation(x: f64, y: f64, z: f64) -> Matrix4<f64> {
+        Translation3::new(x, y, z).to_homogeneous()
+    }
+
+    /// The same hierarchy that graphics.ts sets up, without any geometry
+    fn arm_game_state() -> GameState {
+        arm_game_state_with_faces(|_| vec![])
+    }
+
+    /// The same hierarchy that graphics.ts sets up,
+    /// with `load_faces` called with each object's OBJ file name
+    fn arm_game_state_with_faces(load_faces: impl Fn(&str) -> Vec<Face<f64>>) -> GameState {
+        let mut game_state = GameState::new();
+        let objects = [
+            ("light_ball", None, "Target Ico", Matrix4::identity()),
+            ("target", None, "Target Ico", Matrix4::identity()),
+            ("base", None, "Base", rotation(PI / 2.0, 0.0, 0.0)),
+            ("shoulder", Some("base"), "Shoulder 1", Matrix4::identity()),
+            (
+                "arm_1",
+                Some("shoulder"),
+                "Arm 1",
+                translation(8.0 * INCHES, 0.0, 10.0 * INCHES) * rotation(0.0, PI / 2.0, 0.0),
+            ),
+            (
+                "arm_2",
+                Some("arm_1"),
+                "Arm 2",
+                translation(0.0, -72.0 * INCHES, 0.0) * rotation(PI, 0.0, 0.0),
+            ),
+            (
+                "wrist",
+                Some("arm_2"),
+                "Wrist",
+                translation(0.0, -48.0 * INCHES, 8.0 * INCHES) * rotation(PI / 2.0, PI / 2.0, 0.0),
+            ),
+            (
+                "end",
+                Some("wrist"),
+                "End",
+                translation(0.0, 0.0, 30.0 * INCHES) * rotation(PI / 2.0, PI / 2.0, PI / 2.0),
+            ),
+        ];
+        for (name, parent, obj_name, initial_transform) in objects {
+            game_state
+                .add_game_object(
+                    name,
+                    parent.map(String::from),
+                    GameObject::from_faces(load_faces(obj_name), initial_transform)
+                        .with_mesh(&format!("{}.obj", obj_name)),
+                )
+                .unwrap();
+        }
+        let unlimited = (f64::NEG_INFINITY, f64::INFINITY);
+        for (name, (min_angle, max_angle)) in [
+            ("shoulder", unlimited),
+            // Only able to lean forwards, and only able to bend one way at the elbow
+            ("arm_1", (-PI, 0.0)),
+            ("arm_2", (0.0, PI)),
+            ("wrist", unlimited),
+            ("end", unlimited),
+        ] {
+            game_state
+                .add_arm_joint(name, 0.0, 0.0, 1.0, min_angle, max_angle)
+                .unwrap();
+        }
+        game_state.set_end_effector_tip(-33.0 * INCHES, 0.0, 0.0);
+        game_state.set_end_effector_axes(-1.0, 0.0, 0.0, 0.0, 1.0, 0.0);
+        game_state.set_target_direction(0.0, 1.0, 0.0);
+        game_state
+    }
+
+    /// The same scene that graphics.ts sets up, with the arm's real meshes
+    fn arm_game_state_with_meshes() -> GameState {
+        arm_game_state_with_faces(|obj_name| load_mesh(&format!("{}.obj", obj_name)))
+    }
+
+    fn read_mesh(file_name: &str) -> String {
+        let path = format!("{}/assets/objs/{}", env!("CARGO_MANIFEST_DIR"), file_name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn load_mesh(file_name: &str) -> Vec<Face<f64>> {
+        load_obj(&read_mesh(file_name))
+    }
+
+    /// Parses a scene and adds the meshes it uses from the assets, in the scene's units
+    fn scene_with_meshes(text: &str) -> SceneDescription {
+        let mut scene = SceneDescription::from_json(text).unwrap();
+        for mesh in scene.mesh_names() {
+            scene.add_mesh(&mesh, &read_mesh(&mesh));
+        }
+        scene
+    }
+
+    #[test]
+    fn test_transform_accumulation() {
+        let mut game_state = GameState::new();
+        // Added before its parent, and attached afterwards
+        game_state
+            .add_game_object(
+                "child",
+                None,
+                GameObject::from_faces(vec![], translation(2.0, 0.0, 0.0)),
+            )
+            .unwrap();
+        game_state
+            .add_game_object(
+                "root",
+                None,
+                GameObject::from_faces(vec![], translation(1.0, 0.0, 0.0)),
+            )
+            .unwrap();
+        game_state
+            .add_game_object(
+                "rotated",
+                Some("root".to_string()),
+                GameObject::from_faces(vec![], rotation(0.0, 0.0, PI / 2.0)),
+            )
+            .unwrap();
+        game_state
+            .reparent_game_object("child", Some("rotated".to_string()))
+            .unwrap();
+        game_state.set_dynamic_transform("child", translation(0.0, 0.0, 3.0));
+
+        let origin = |name: &str| {
+            game_state
+                .world_transform(name)
+                .unwrap()
+                .transform_point(&Point3::origin())
+        };
+        assert!((origin("root") - point![1.0, 0.0, 0.0]).norm() < 1e-10);
+        assert!((origin("rotated") - point![1.0, 0.0, 0.0]).norm() < 1e-10);
+        // Moved 2 along the parent's x axis, which the parent rotated onto y
+        assert!((origin("child") - point![1.0, 2.0, 3.0]).norm() < 1e-10);
+
+        game_state.remove_game_object("rotated").unwrap();
+        assert_eq!(game_state.find_game_object("child"), None);
+        assert_eq!(game_state.get_render_snapshot().object_ids().len(), 1);
+    }
+
+    /// The errors that the exported methods hand to JS instead of panicking
+    #[test]
+    fn test_game_object_errors() {
+        let mut game_state = arm_game_state();
+        assert_eq!(
+            game_state.find_existing("nope"),
+            Err(SceneGraphError::UnknownName("nope".to_string()))
+        );
+        assert_eq!(
+            game_state.arm_joint_mut("base").unwrap_err(),
+            "base is not an arm joint"
+        );
+        // Not attached to the last joint of the arm
+        assert!(game_state
+            .try_add_arm_joint("light_ball", Joint::new(Vector3::z_axis()))
+            .is_err());
+        assert_eq!(game_state.arm_joints.len(), 5);
+    }
+
+    #[test]
+    fn test_inverse_kinematics_reaches_target() {
+        let mut game_state = arm_game_state();
+        for target in [
+            point![1.0, 0.5, 1.0],
+            point![-1.5, 1.0, 0.3],
+            point![0.5, -0.2, -1.5],
+        ] {
+            game_state.update_target(target.x, target.y, target.z);
+            let tip = game_state.end_effector_tip().unwrap();
+            assert!((tip - target).norm() < 1e-6, "{} != {}", tip, target);
+            let end_transform = game_state.world_transform("end").unwrap();
+            // Pointing straight up at the target
+            let direction = end_transform.transform_vector(&vector![-1.0, 0.0, 0.0]);
+            assert!((direction - vector![0.0, 1.0, 0.0]).norm() < 1e-6);
+            assert_eq!(game_state.ik_result().unwrap().status, IkStatus::Reached);
+        }
+    }
+
+    /// The pose of the end effector's tip, and the rotation of the end joint
+    fn end_effector_pose(game_state: &GameState) -> Transform {
+        let mut pose = game_state.world_pose("end").unwrap();
+        pose.translation = game_state.end_effector_tip().unwrap().coords;
+        pose
+    }
+
+    #[test]
+    fn test_reaches_grid_of_poses() {
+        let mut game_state = arm_game_state();
+        let start = [0.0, -0.8, 0.8, 0.0, 0.0];
+        let mut count = 0;
+        for shoulder in [-2.0, 0.5, 2.5] {
+            for arm_1 in [-1.3, -0.6] {
+                for arm_2 in [0.5, 1.2] {
+                    for wrist in [-0.8, 0.7] {
+                        for end in [-1.0, 0.9] {
+                            // Posed by running the joints forwards, so it must be reachable
+                            game_state.set_arm_angles(&[shoulder, arm_1, arm_2, wrist, end]);
+                            let pose = end_effector_pose(&game_state);
+
+                            game_state.set_arm_angles(&start);
+                            game_state.set_target_pose(&pose);
+                            let result = game_state.ik_result().unwrap();
+                            assert_eq!(result.status, IkStatus::Reached, "{:?}", result);
+                            let reached = end_effector_pose(&game_state);
+                            assert!((reached.translation - pose.translation).norm() < 1e-6);
+                            assert!(reached.rotation.angle_to(&pose.rotation) < 1e-6);
+                            count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(count, 48);
+    }
+
+    #[test]
+    fn test_target_direction_roll() {
+        let mut game_state = arm_game_state();
+        game_state.set_arm_angles(&[1.0, -1.0, 1.0, 0.5, 0.3]);
+        let pose = end_effector_pose(&game_state);
+        let end_transform = game_state.world_transform("end").unwrap();
+        let direction = Unit::new_normalize(end_transform.transform_vector(&-Vector3::x()));
+
+        // How far that pose is rolled from upright
+        let upright = match Orientation::from_direction_roll(
+            &-Vector3::x_axis(),
+            &Vector3::y(),
+            &direction,
+            0.0,
+            &Vector3::y(),
+        ) {
+            Orientation::Rotation(rotation) => rotation,
+            orientation => panic!("{:?}", orientation),
+        };
+        let roll = (pose.rotation * upright.inverse())
+            .scaled_axis()
+            .dot(&direction);
+
+        game_state.set_arm_angles(&[0.0, -0.8, 0.8, 0.0, 0.0]);
+        game_state.update_target(pose.translation.x, pose.translation.y, pose.translation.z);
+        game_state.set_target_direction_roll(direction.x, direction.y, direction.z, roll);
+        assert_eq!(game_state.ik_result().unwrap().status, IkStatus::Reached);
+        let reached = end_effector_pose(&game_state);
+        assert!((reached.translation - pose.translation).norm() < 1e-6);
+        assert!(reached.rotation.angle_to(&pose.rotation) < 1e-6);
+    }
+
+    #[test]
+    fn test_unreachable_target() {
+        let mut game_state = arm_game_state();
+        game_state.update_target(1.0, 0.5, 1.0);
+        let reached_angles: Vec<f64> = game_state.arm_angles();
+
+        game_state.update_target(10.0, 0.0, 0.0);
+        let result = game_state.ik_result().unwrap();
+        assert_eq!(result.status, IkStatus::Unreachable);
+        let tip = game_state.end_effector_tip().unwrap();
+        assert!((result.position_error - (tip - point![10.0, 0.0, 0.0]).norm()).abs() < 1e-9);
+        // Reaching towards the target rather than staying where it was
+        assert!(tip.x > 2.0, "{}", tip);
+        for arm_joint in &game_state.arm_joints {
+            assert!(arm_joint.angle.is_finite());
+            assert!(arm_joint.angle >= arm_joint.joint.min_angle);
+            assert!(arm_joint.angle <= arm_joint.joint.max_angle);
+        }
+
+        // Within reach, but only if the elbow could bend backwards
+        game_state.set_arm_joint_limits("arm_2", 0.0, 0.1).unwrap();
+        game_state.update_target(1.0, 0.5, 1.0);
+        let result = game_state.ik_result().unwrap();
+        assert_eq!(result.status, IkStatus::Clamped);
+        assert!(game_state.arm_joints[2].angle <= 0.1);
+
+        game_state.set_arm_joint_limits("arm_2", 0.0, PI).unwrap();
+        assert_eq!(game_state.ik_result().unwrap().status, IkStatus::Reached);
+        let angles: Vec<f64> = game_state.arm_angles();
+        for (angle, reached_angle) in angles.iter().zip(reached_angles) {
+            assert!((angle - reached_angle).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_forward_kinematics_queries() {
+        let mut game_state = arm_game_state();
+        assert_eq!(game_state.world_position("missing"), None);
+        // Nothing has any geometry
+        assert!(game_state.world_bounding_box("arm_2").is_none());
+
+        game_state.update_target(1.0, 0.5, 1.0);
+        let arm_2 = game_state.world_transform("arm_2").unwrap();
+        let position = game_state.world_position("arm_2").unwrap();
+        assert!((Vector3::from_vec(position) - arm_2.column(3).xyz()).norm() < 1e-12);
+        let orientation = game_state.world_orientation("arm_2").unwrap();
+        let rotation = UnitQuaternion::from_quaternion(Quaternion::new(
+            orientation[3],
+            orientation[0],
+            orientation[1],
+            orientation[2],
+        ));
+        let x = arm_2.transform_vector(&Vector3::x());
+        assert!((rotation * Vector3::x() - x).norm() < 1e-9);
+
+        let tip = game_state.end_effector_tip_array().unwrap();
+        assert!((Vector3::from_vec(tip) - vector![1.0, 0.5, 1.0]).norm() < 1e-6);
+
+        // A unit square, moved along with the arm
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nf 1/1 2/2 3/3 4/4\n";
+        game_state
+            .add_game_object(
+                "square",
+                Some("wrist".to_string()),
+                GameObject::new(obj.to_string(), TransformMatrix::translation(0.0, 0.0, 2.0)),
+            )
+            .unwrap();
+        let square = game_state.world_transform("square").unwrap();
+        let corners = [
+            point![0.0, 0.0, 0.0],
+            point![1.0, 0.0, 0.0],
+            point![1.0, 1.0, 0.0],
+            point![0.0, 1.0, 0.0],
+        ]
+        .map(|corner| square.transform_point(&corner));
+        let expected = BoundingBox::from_points(&corners);
+        let bounding_box = game_state.world_bounding_box_array("square").unwrap();
+        assert_eq!(bounding_box.len(), 6);
+        assert!(
+            (Vector3::from_row_slice(&bounding_box[..3]) - expected.min_pt.coords).norm() < 1e-12
+        );
+        assert!(
+            (Vector3::from_row_slice(&bounding_box[3..]) - expected.max_pt.coords).norm() < 1e-12
+        );
+    }
+
+    #[test]
+    fn test_plays_recorded_arm_motion() {
+        let mut game_state = arm_game_state();
+        let mut clip = AnimationClip::new();
+        let start = vector![1.0, 0.5, 1.0];
+        let end = vector![-0.5, 1.0, 1.0];
+        game_state.update_target(start.x, start.y, start.z);
+        game_state.record_arm_keyframe(&mut clip, 0.0, Interpolation::Linear);
+        let start_angles = game_state.arm_angles();
+        game_state.update_target(end.x, end.y, end.z);
+        game_state.record_arm_keyframe(&mut clip, 2.0, Interpolation::Linear);
+        // Not an arm joint, so it takes the whole transform
+        clip.add_translation_keyframe("light_ball", 0.0, 0.0, 0.0, 0.0, Interpolation::Linear);
+        clip.add_translation_keyframe("light_ball", 2.0, 0.0, 4.0, 0.0, Interpolation::Linear);
+        assert_eq!(clip.duration(), 2.0);
+
+        let tip = |game_state: &mut GameState| {
+            game_state.update_world_transforms();
+            game_state.end_effector_tip().unwrap().coords
+        };
+        game_state.play_animation("reach", &clip, PlaybackMode::Once, 1.0);
+        game_state.advance_animations(0.0);
+        for (angle, start_angle) in game_state.arm_angles().iter().zip(&start_angles) {
+            assert!((angle - start_angle).abs() < 1e-9);
+        }
+        assert!((tip(&mut game_state) - start).norm() < 1e-6);
+
+        game_state.advance_animations(1.0);
+        let halfway = tip(&mut game_state);
+        assert_eq!(
+            game_state.world_position("light_ball"),
+            Some(vec![0.0, 2.0, 0.0])
+        );
+        assert!((halfway - start).norm() > 0.1 && (halfway - end).norm() > 0.1);
+
+        // Played once, so it stays at the end
+        game_state.advance_animations(5.0);
+        assert!((tip(&mut game_state) - end).norm() < 1e-6);
+        assert!(game_state.stop_animation("reach"));
+        assert!(!game_state.stop_animation("reach"));
+    }
+
+    #[test]
+    fn test_planned_arm_motion() {
+        for motion_planning in [MotionPlanning::Joint, MotionPlanning::Cartesian] {
+            let mut game_state = arm_game_state();
+            game_state.update_target(1.0, 0.5, 1.0);
+            let max_velocity = 0.5;
+            for name in ["shoulder", "arm_1", "arm_2", "wrist", "end"] {
+                game_state
+                    .set_arm_joint_motion_limits(name, max_velocity, 1.0)
+                    .unwrap();
+            }
+            game_state.set_motion_planning(motion_planning);
+
+            let target = vector![-0.5, 1.0, 1.0];
+            let start = game_state.arm_angles();
+            game_state.update_target(target.x, target.y, target.z);
+            assert!(game_state.is_arm_moving());
+            assert_eq!(game_state.arm_angles(), start);
+
+            let mut frames = 0;
+            while game_state.is_arm_moving() {
+                let before = game_state.arm_angles();
+                // A whole number of steps, so every frame moves the arm the same amount of time
+                game_state.update(false, false, false, false, false, 0.0, 0.0, 48);
+                for (after, before) in game_state.arm_angles().iter().zip(&before) {
+                    // Joints without limits are wrapped to ±π
+                    let turn = Joint::new(Vector3::z_axis()).clamp(after - before);
+                    assert!(
+                        turn.abs() <= max_velocity * 0.048 + 1e-9,
+                        "{:?} turned {}",
+                        motion_planning,
+                        turn
+                    );
+                }
+                frames += 1;
+                assert!(frames < 1000, "{:?} never arrived", motion_planning);
+            }
+            assert!(frames > 1);
+            game_state.update_world_transforms();
+            let tip = game_state.end_effector_tip().unwrap();
+            assert!((tip.coords - target).norm() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_arm_moves_the_same_at_any_frame_rate() {
+        let move_arm = |frames: &[usize]| {
+            let mut game_state = arm_game_state();
+            game_state.update_target(1.0, 0.5, 1.0);
+            for name in ["shoulder", "arm_1", "arm_2", "wrist", "end"] {
+                game_state
+                    .set_arm_joint_motion_limits(name, 0.5, 1.0)
+                    .unwrap();
+            }
+            game_state.set_motion_planning(MotionPlanning::Joint);
+            game_state.update_target(-0.5, 1.0, 1.0);
+            for delta_time_ms in frames {
+                game_state.update(false, false, false, false, false, 0.0, 0.0, *delta_time_ms);
+            }
+            assert!(game_state.is_arm_moving());
+            game_state.arm_angles()
+        };
+        let at_60_fps = move_arm(&[16; 60]);
+        assert_eq!(at_60_fps, move_arm(&[8; 120]));
+        assert_eq!(at_60_fps, move_arm(&[32; 30]));
+        assert_eq!(at_60_fps, move_arm(&[40, 8, 24, 24].repeat(10)));
+    }
+
+    #[test]
+    fn test_arm_collisions() {
+        let mut game_state = arm_game_state_with_meshes();
+        game_state.free_target_orientation();
+        // Folded up, the wrist's hull overlaps the first arm's
+        let folded = [0.0; 5];
+        let contacts = game_state.arm_contacts_at(&folded);
+        assert!(contacts
+            .iter()
+            .any(|contact| contact.is_between("wrist", Some("arm_1"))));
+        assert!(contacts.iter().all(|contact| !contact.is_floor()));
+        game_state.allow_contact("wrist", Some("arm_1".to_string()));
+        assert!(game_state
+            .arm_contacts_at(&folded)
+            .iter()
+            .all(|contact| !contact.is_between("arm_1", Some("wrist"))));
+
+        // Without avoiding collisions, the IK leaves the arm running into itself
+        let target = vector![1.0, -1.0, 0.0];
+        game_state.set_arm_angles(&folded);
+        game_state.update_target(target.x, target.y, target.z);
+        assert!(!game_state.arm_collisions().is_empty());
+
+        game_state.set_collision_avoidance(true);
+        game_state.set_arm_angles(&folded);
+        game_state.update_target(target.x, target.y, target.z);
+        assert_eq!(game_state.ik_result().unwrap().status, IkStatus::Reached);
+        assert!(game_state.arm_collisions().is_empty());
+        let clear = game_state.arm_angles();
 
-    let points: Vec<_> = faces
-        .iter()
-        .flat_map(|face| face.break_into_triangles())
-        .collect();
+        // Any pose reaching below the floor would put the end through it
+        game_state.set_floor(0.0, -1.5, 0.0, 0.0, 1.0, 0.0);
+        game_state.update_target(0.0, -2.0, 0.0);
+        assert_eq!(game_state.ik_result().unwrap().status, IkStatus::Collides);
+        assert_eq!(game_state.arm_angles(), clear);
+        let contacts = game_state.arm_contacts_at(&folded);
+        assert!(contacts.iter().all(|contact| !contact.is_floor()));
+    }
+
+    #[test]
+    fn test_scene_file() {
+        let text = include_str!("scenes/robot-arm.json");
+        let game_state = GameState::from_scene(&scene_with_meshes(text)).unwrap();
+        let mut expected = arm_game_state_with_meshes();
+        expected.set_arm_angles(&[0.0; 5]);
+
+        assert_eq!(game_state.arm_angles(), vec![0.0; 5]);
+        assert!(
+            (game_state.light_position - point![400.0 * INCHES, 400.0 * INCHES, 0.0]).norm()
+                < 1e-12
+        );
+        assert_eq!(game_state.motion_planning, MotionPlanning::Joint);
+        assert!(game_state.avoid_collisions);
+        for (arm_joint, expected) in game_state.arm_joints.iter().zip(&expected.arm_joints) {
+            assert_eq!(arm_joint.name, expected.name);
+            assert_eq!(arm_joint.joint, expected.joint);
+            assert_eq!(arm_joint.limits.max_velocity, 1.5);
+        }
+        for name in ["base", "shoulder", "arm_1", "arm_2", "wrist", "end"] {
+            let difference =
+                game_state.world_transform(name).unwrap() - expected.world_transform(name).unwrap();
+            assert!(difference.amax() < 1e-9, "{} is out of place", name);
+            assert_eq!(
+                game_state.get_game_object(name).unwrap().num_points,
+                expected.get_game_object(name).unwrap().num_points
+            );
+        }
+        assert!(
+            (game_state.end_effector_tip().unwrap() - expected.end_effector_tip().unwrap()).norm()
+                < 1e-9
+        );
+
+        // The links are as long as the arm's pivots are apart in the meshes
+        let chain = game_state.arm_chain().unwrap();
+        let link_length = |i: usize| chain.links[i].offset.column(3).xyz().norm();
+        assert!((link_length(2) - 72.0 * INCHES).abs() < 1e-9);
+        assert!((link_length(4) - 30.0 * INCHES).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_scene_round_trip() {
+        let mut game_state = arm_game_state_with_meshes();
+        game_state.set_floor(0.0, -3.0, 0.0, 0.0, 1.0, 0.0);
+        game_state.allow_contact("base", None);
+        game_state.update_target(1.0, 0.5, 1.0);
+        let exported = game_state.export_scene();
+
+        let reloaded = GameState::from_scene(&scene_with_meshes(&exported)).unwrap();
+        // Splitting the transforms into a translation, rotation and scale rounds them slightly
+        let scene = reloaded.scene_description();
+        let expected = game_state.scene_description();
+        for (object, expected) in scene.objects.iter().zip(&expected.objects) {
+            assert_eq!(object.name, expected.name);
+            assert_eq!(object.parent, expected.parent);
+            assert_eq!(object.mesh, expected.mesh);
+            assert!((object.initial_transform - expected.initial_transform).amax() < 1e-12);
+            assert_eq!(object.joint, expected.joint);
+        }
+        assert_eq!(scene.objects.len(), expected.objects.len());
+        assert_eq!(scene.arm, expected.arm);
+        assert_eq!(reloaded.arm_angles(), game_state.arm_angles());
+        assert_eq!(
+            reloaded.world_transform("target"),
+            game_state.world_transform("target")
+        );
+        assert!(
+            (reloaded.end_effector_tip().unwrap() - point![1.0, 0.5, 1.0]).norm()
+                < game_state.ik_settings.tolerance * 10.0
+        );
+    }
+
+    #[test]
+    fn test_export_glb() {
+        let mut game_state = arm_game_state_with_meshes();
+        game_state.update_target(1.0, 0.5, 1.0);
+        let snapshot = game_state.get_render_snapshot();
+        let gltf = Gltf::from_bytes(&game_state.export_glb()).unwrap();
+
+        let mut world_transforms: Vec<Matrix4<f64>> = vec![Matrix4::identity(); gltf.nodes.len()];
+        for (i, parent) in gltf.depth_first() {
+            let parent_transform =
+                parent.map_or_else(Matrix4::identity, |parent| world_transforms[parent]);
+            world_transforms[i] = parent_transform * gltf.nodes[i].transform;
+        }
+        assert_eq!(gltf.nodes.len(), snapshot.objects.len());
+        for (object, (node, world_transform)) in snapshot
+            .objects
+            .iter()
+            .zip(gltf.nodes.iter().zip(&world_transforms))
+        {
+            assert_eq!(node.name.as_deref(), Some(object.name.as_str()));
+            // Stored as 32-bit floats
+            assert!((world_transform - object.transform.matrix()).amax() < 1e-5);
 
-    points
-        .iter()
-        .flat_map(|point| [point.x as _, point.y as _, point.z as _, 1.0])
-        .collect()
+            let original = game_state.get_game_object(&object.name).unwrap();
+            let primitive = &gltf.meshes[node.mesh.unwrap()].primitives[0];
+            let reimported = GameObject::from_faces(primitive.faces(), Matrix4::identity());
+            assert_eq!(
+                reimported.points_to_float32array(),
+                original.points_to_float32array()
+            );
+            // Flipping the UVs' v and renormalizing the normals can round them slightly
+            let close = |buffer: Vec<f32>, original: Vec<f32>| {
+                buffer.len() == original.len()
+                    && buffer
+                        .iter()
+                        .zip(&original)
+                        .all(|(value, original)| (value - original).abs() < 1e-6)
+            };
+            assert!(close(
+                reimported.uvs_to_float32array(),
+                original.uvs_to_float32array()
+            ));
+            assert!(close(
+                reimported.normals_to_float32array(),
+                original.normals_to_float32array()
+            ));
+            let [r, g, b, _] = gltf.materials[primitive.material.unwrap()].base_color;
+            assert_eq!([r as f32, g as f32, b as f32], object.base_color);
+        }
+    }
+
+    #[test]
+    fn test_scene_errors() {
+        let build = |text: &str| {
+            GameState::from_scene(&SceneDescription::from_json(text).unwrap())
+                .err()
+                .unwrap()
+        };
+        assert_eq!(
+            build(r#"{ "objects": [{ "name": "arm", "parent": "base" }] }"#),
+            SceneError::Object {
+                name: "arm".to_string(),
+                message: "no node named \"base\"".to_string()
+            }
+        );
+        assert_eq!(
+            build(r#"{ "objects": [{ "name": "base", "mesh": "Base.obj" }] }"#),
+            SceneError::MissingMesh("Base.obj".to_string())
+        );
+        assert_eq!(
+            build(
+                r#"{ "objects": [{ "name": "base" }, { "name": "arm", "parent": "base", "pivot": { "direction": [0, 1, 0] } }] }"#
+            ),
+            SceneError::Object {
+                name: "arm".to_string(),
+                message: "its parent has no mesh to measure its pivot from".to_string()
+            }
+        );
+        let unattached = r#"{ "objects": [
+            { "name": "a", "joint": { "axis": [0, 0, 1] } },
+            { "name": "b", "joint": { "axis": [0, 0, 1] } }
+        ] }"#;
+        assert_eq!(
+            build(unattached),
+            SceneError::Object {
+                name: "b".to_string(),
+                message: "it is not attached to a".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_obj_meshing() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nf 1/1 2/2 3/3 4/4\n";
+        let game_object = GameObject::new(obj.to_string(), TransformMatrix::identity());
+        assert_eq!(game_object.num_points, 6);
+        assert_eq!(game_object.points_to_float32array().len(), 6 * 4);
+        assert_eq!(game_object.normals_to_float32array().len(), 6 * 4);
+        assert_eq!(game_object.uvs_to_float32array().len(), 6 * 2);
+    }
+
+    fn changed_names(snapshot: &RenderSnapshot) -> Vec<String> {
+        snapshot
+            .changed_object_ids()
+            .into_iter()
+            .map(|i| snapshot.objects[i].name())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_snapshot() {
+        let mut game_state = arm_game_state();
+        let snapshot = game_state.get_render_snapshot();
+        assert_eq!(snapshot.object_ids().len(), 8);
+        assert_eq!(snapshot.changed_object_ids().len(), 8);
+        for object in &snapshot.objects {
+            let transform = game_state.world_transform(&object.name()).unwrap();
+            assert!((object.transform.matrix() - transform).norm() < 1e-10);
+        }
+
+        assert!(game_state
+            .get_render_snapshot()
+            .changed_object_ids()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_only_moved_subtrees_change() {
+        let mut game_state = arm_game_state();
+        game_state.get_render_snapshot();
+
+        game_state.set_dynamic_transform("arm_2", rotation(0.0, 0.0, 0.5));
+        let snapshot = game_state.get_render_snapshot();
+        assert_eq!(changed_names(&snapshot), vec!["arm_2", "wrist", "end"]);
+        for object in &snapshot.objects {
+            let transform = game_state.world_transform(&object.name()).unwrap();
+            assert!((object.transform.matrix() - transform).norm() < 1e-10);
+        }
+
+        // Only the light's marker moves, the arm and the target stay where they are
+        let angles = game_state.arm_angles();
+        game_state.update_light_position(1.0, 2.0, 3.0);
+        assert_eq!(
+            changed_names(&game_state.get_render_snapshot()),
+            vec!["light_ball"]
+        );
+        assert_eq!(game_state.arm_angles(), angles);
+        // Setting a transform to what it already was isn't a change
+        game_state.set_dynamic_transform("arm_2", rotation(0.0, 0.0, 0.5));
+        assert!(game_state
+            .get_render_snapshot()
+            .changed_object_ids()
+            .is_empty());
+
+        game_state
+            .set_initial_transform("wrist", TransformMatrix::translation(0.0, 1.0, 0.0))
+            .unwrap();
+        assert_eq!(
+            changed_names(&game_state.get_render_snapshot()),
+            vec!["wrist", "end"]
+        );
+    }
+
+    /// Draws the scene the way graphics.ts does with vertex-shader.glsl and fragment-shader.glsl,
+    /// with a flat gray standing in for the metal texture
+    fn render(game_state: &mut GameState, width: usize, height: usize) -> Image {
+        game_state.set_aspect_ratio(width as f64 / height as f64);
+        let camera_transform = *game_state.world_to_camera().matrix();
+        let camera_position = game_state.camera.position;
+        let light_position = game_state.light_position;
+        let mut rasterizer = Rasterizer::new(width, height, vector![0.0, 0.0, 0.0, 1.0]);
+
+        game_state.update_world_transforms();
+        for id in game_state.game_objects.depth_first() {
+            let game_object = game_state.game_objects.get(id).unwrap();
+            let transform = game_object.world_transform;
+            let points = game_object.points_to_float32array();
+            let normals = game_object.normals_to_float32array();
+            let normal_transform = transform.fixed_view::<3, 3>(0, 0).into_owned();
+            rasterizer.draw_triangles(
+                &[Attribute::new(&points, 4), Attribute::new(&normals, 4)],
+                |attributes| {
+                    let vertex = Vector4::<f32>::from_column_slice(attributes[0]).cast::<f64>();
+                    let normal =
+                        Vector3::<f32>::from_column_slice(&attributes[1][..3]).cast::<f64>();
+                    let normal = -(normal_transform * normal).normalize();
+                    let world_vertex = transform * vertex;
+                    (
+                        camera_transform * world_vertex,
+                        vec![
+                            world_vertex.x,
+                            world_vertex.y,
+                            world_vertex.z,
+                            normal.x,
+                            normal.y,
+                            normal.z,
+                        ],
+                    )
+                },
+                |varyings| {
+                    let point = point![varyings[0], varyings[1], varyings[2]];
+                    let normal = vector![varyings[3], varyings[4], varyings[5]];
+
+                    let base_color = vector![0.3, 0.3, 0.3];
+                    let l_ambient = base_color;
+                    let l_diffuse = base_color + vector![0.3, 0.3, 0.3];
+                    let l_specular = vector![0.1, 0.1, 0.1];
+                    let k_ambient = 0.5;
+                    let k_diffuse = 1.0;
+                    let k_specular = 10.0;
+                    let alpha = 10.0;
+
+                    let view = (camera_position - point).normalize();
+                    let l = (light_position - point).normalize();
+                    let f_attenuation = 1.0 / (light_position - point).norm();
+                    let cos_theta = l.dot(&normal);
+                    let i_ambient = l_ambient * k_ambient;
+                    let i = if cos_theta > 0.0 {
+                        let i_diffuse = l_diffuse * k_diffuse * cos_theta;
+                        let r = 2.0 * normal * cos_theta - l;
+                        let cos_phi = r.dot(&view).clamp(0.0, 1.0);
+                        let i_specular = l_specular * k_specular * cos_phi.powf(alpha);
+                        i_ambient + f_attenuation * (i_specular + i_diffuse)
+                    } else {
+                        i_ambient
+                    };
+
+                    let texture = 0.5;
+                    let mix = |lit: f64| lit * 0.7 + texture * 0.3;
+                    vector![mix(i.x), mix(i.y), mix(i.z), 1.0]
+                },
+            );
+        }
+        rasterizer.into_image()
+    }
+
+    #[test]
+    fn test_golden_robot_arm() {
+        let mut game_state = arm_game_state_with_meshes();
+        game_state.update_light_position(10.0, 10.0, 0.0);
+        game_state.update_target(1.0, 0.5, 1.0);
+        game_state.camera = Camera::new(
+            point![3.0, -0.5, 0.5],
+            UnitVector3::new_normalize(vector![-1.0, -0.1, 0.0]),
+        );
+        let image = render(&mut game_state, 128, 96);
+        assert_matches_golden(
+            &image,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/golden/robot-arm.png"),
+        );
+    }
 }
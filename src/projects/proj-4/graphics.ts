@@ -14,14 +14,13 @@ import skybox_back_texture from "./assets/skybox-back.png";
 import skybox_front_texture from "./assets/skybox-front.png";
 import metal_texture from "./assets/metal-texture.jpg";
 
-import obj_base from "./assets/objs/Base.obj?url";
-import obj_shoulder_1 from "./assets/objs/Shoulder 1.obj?url";
-import obj_arm_1 from "./assets/objs/Arm 1.obj?url";
-import obj_arm_2 from "./assets/objs/Arm 2.obj?url";
-import obj_wrist from "./assets/objs/Wrist.obj?url";
-import obj_end from "./assets/objs/End.obj?url";
-import obj_target_ico from "./assets/objs/Target Ico.obj?url";
-import { TransformMatrix } from "./pkg";
+import robot_arm_scene from "./scenes/robot-arm.json?raw";
+
+// Scenes name their meshes by file name
+const obj_urls = import.meta.glob("./assets/objs/*.obj", {
+  as: "url",
+  eager: true,
+}) as Record<string, string>;
 
 const load_image = (src: string) => {
   const img = new Image();
@@ -63,6 +62,7 @@ export const init_canvas = async (
     rendering_program,
     "camera_position",
   );
+  const id_base_color = gl.getUniformLocation(rendering_program, "base_color");
   const matrix_id_skybox_transform = gl.getUniformLocation(
     skybox_rendering_program,
     "transform",
@@ -125,123 +125,74 @@ export const init_canvas = async (
   const skybox_points = rust.generate_skybox_points();
   gl.bufferData(gl.ARRAY_BUFFER, skybox_points, gl.STATIC_DRAW);
 
-  interface GameObjectDescriptor {
-    parent?: GameObjectDescriptor;
-    url: string;
-    initial_transform: TransformMatrix;
-  }
+  // GPU buffers live on the JS side, keyed by the game object's name
+  const object_buffers = new Map<
+    string,
+    { vert: WebGLBuffer; normals: WebGLBuffer; uvs: WebGLBuffer }
+  >();
+  // Replaces everything in the game state with the scene, which is freed
+  const load_scene = (scene: rust.SceneDescription) => {
+    const object_names = scene.object_names();
+    game_state.rust_state.load_scene(scene);
+    scene.free();
+    for (const buffers of object_buffers.values()) {
+      gl.deleteBuffer(buffers.vert);
+      gl.deleteBuffer(buffers.normals);
+      gl.deleteBuffer(buffers.uvs);
+    }
+    object_buffers.clear();
+    for (const name of object_names) {
+      const object = game_state.rust_state.get_game_object(name);
+      if (!object) throw new Error(`missing game object ${name}`);
+      const obj_vert_buffer = gl.createBuffer();
+      if (!obj_vert_buffer) throw new Error("could not create obj_vert_buffer");
+      gl.bindBuffer(gl.ARRAY_BUFFER, obj_vert_buffer);
+      gl.bufferData(
+        gl.ARRAY_BUFFER,
+        object.points_to_float32array(),
+        gl.STATIC_DRAW,
+      );
 
-  // Onshape exports the OBJ in meters,
-  // this scales to inches to match how my model is defined in onshape
-  const INCHES = 0.0254;
+      const obj_normals_buffer = gl.createBuffer();
+      if (!obj_normals_buffer)
+        throw new Error("could not create obj_normals_buffer");
+      gl.bindBuffer(gl.ARRAY_BUFFER, obj_normals_buffer);
+      gl.bufferData(
+        gl.ARRAY_BUFFER,
+        object.normals_to_float32array(),
+        gl.STATIC_DRAW,
+      );
 
-  const light_ball: GameObjectDescriptor = {
-    url: obj_target_ico,
-    initial_transform: TransformMatrix.identity(),
-  };
-  const target: GameObjectDescriptor = {
-    url: obj_target_ico,
-    initial_transform: TransformMatrix.identity(),
-  };
-  const base: GameObjectDescriptor = {
-    url: obj_base,
-    initial_transform: TransformMatrix.rotation_euler(Math.PI / 2, 0, 0),
-  };
-  const shoulder: GameObjectDescriptor = {
-    url: obj_shoulder_1,
-    initial_transform: TransformMatrix.identity(),
-    parent: base,
-  };
-  const arm_1: GameObjectDescriptor = {
-    url: obj_arm_1,
-    initial_transform: TransformMatrix.translation(
-      8.0 * INCHES,
-      0.0,
-      10.0 * INCHES,
-    ).times(TransformMatrix.rotation_euler(0, Math.PI / 2, 0)),
-    parent: shoulder,
-  };
-  const arm_2: GameObjectDescriptor = {
-    url: obj_arm_2,
-    initial_transform: TransformMatrix.translation(
-      0.0,
-      -72.0 * INCHES,
-      0.0,
-    ).times(TransformMatrix.rotation_euler(Math.PI, 0, 0)),
-    parent: arm_1,
-  };
-  const wrist: GameObjectDescriptor = {
-    url: obj_wrist,
-    initial_transform: TransformMatrix.translation(
-      0.0,
-      -48.0 * INCHES,
-      8.0 * INCHES,
-    ).times(TransformMatrix.rotation_euler(Math.PI / 2, Math.PI / 2, 0)),
-    parent: arm_2,
-  };
-  const end: GameObjectDescriptor = {
-    url: obj_end,
-    initial_transform: TransformMatrix.translation(
-      0.0,
-      0.0,
-      30.0 * INCHES,
-    ).times(
-      TransformMatrix.rotation_euler(Math.PI / 2, Math.PI / 2, Math.PI / 2),
-    ),
-    parent: wrist,
+      const obj_uvs_buffer = gl.createBuffer();
+      if (!obj_uvs_buffer) throw new Error("could not create obj_uvs_buffer");
+      gl.bindBuffer(gl.ARRAY_BUFFER, obj_uvs_buffer);
+      gl.bufferData(
+        gl.ARRAY_BUFFER,
+        object.uvs_to_float32array(),
+        gl.STATIC_DRAW,
+      );
+      object_buffers.set(name, {
+        vert: obj_vert_buffer,
+        normals: obj_normals_buffer,
+        uvs: obj_uvs_buffer,
+      });
+      object.free();
+    }
   };
-  const game_object_descriptors: GameObjectDescriptor[] = [
-    light_ball,
-    target,
-    base,
-    shoulder,
-    arm_1,
-    arm_2,
-    wrist,
-    end,
-  ];
-  const loaded_game_object_descriptors = await Promise.all(
-    game_object_descriptors.map(async (obj_descriptor, i) => ({
-      ...obj_descriptor,
-      parent: obj_descriptor.parent
-        ? game_object_descriptors.indexOf(obj_descriptor.parent)
-        : i,
-      obj_text: await fetch(obj_descriptor.url).then((res) => res.text()),
-    })),
-  );
-
-  for (const i in loaded_game_object_descriptors) {
-    const game_object_descriptor = loaded_game_object_descriptors[i];
-    const object = new rust.GameObject(
-      game_object_descriptor.obj_text,
-      game_object_descriptor.parent,
-      game_object_descriptor.initial_transform,
-    );
-    object.obj_vert_buffer = gl.createBuffer();
-    gl.bindBuffer(gl.ARRAY_BUFFER, object.obj_vert_buffer);
-    gl.bufferData(
-      gl.ARRAY_BUFFER,
-      object.points_to_float32array(),
-      gl.STATIC_DRAW,
-    );
-
-    object.obj_normals_buffer = gl.createBuffer();
-    gl.bindBuffer(gl.ARRAY_BUFFER, object.obj_normals_buffer);
-    gl.bufferData(
-      gl.ARRAY_BUFFER,
-      object.normals_to_float32array(),
-      gl.STATIC_DRAW,
-    );
 
-    object.obj_uvs_buffer = gl.createBuffer();
-    gl.bindBuffer(gl.ARRAY_BUFFER, object.obj_uvs_buffer);
-    gl.bufferData(
-      gl.ARRAY_BUFFER,
-      object.uvs_to_float32array(),
-      gl.STATIC_DRAW,
-    );
-    game_state.rust_state.add_game_object(object);
+  // The game objects, the arm's joints and the light are all described by the scene file
+  const scene = rust.SceneDescription.parse(robot_arm_scene);
+  const obj_texts = await Promise.all(
+    scene.mesh_names().map(async (mesh) => {
+      const url = obj_urls[`./assets/objs/${mesh}`];
+      if (!url) throw new Error(`missing mesh ${mesh}`);
+      return [mesh, await fetch(url).then((res) => res.text())] as const;
+    }),
+  );
+  for (const [mesh, obj_text] of obj_texts) {
+    scene.add_mesh(mesh, obj_text);
   }
+  load_scene(scene);
 
   // Depth Test Enable (only render things 'forward' of other things)
   gl.enable(gl.DEPTH_TEST);
@@ -357,14 +308,10 @@ export const init_canvas = async (
     const render_snapshot = game_state.rust_state.get_render_snapshot();
     for (const object_id of render_snapshot.object_ids()) {
       const object_render_snapshot = render_snapshot.get_object(object_id);
-      const obj_vert_buffer = object_render_snapshot.get_obj_vert_buffer();
-      const obj_normals_buffer =
-        object_render_snapshot.get_obj_normals_buffer();
-      const obj_uvs_buffer = object_render_snapshot.get_obj_uvs_buffer();
-      if (!obj_vert_buffer) throw new Error("missing obj_vert_buffer");
-      if (!obj_normals_buffer) throw new Error("missing obj_normals_buffer");
-      if (!obj_uvs_buffer) throw new Error("missing obj_uvs_buffer");
-      gl.bindBuffer(gl.ARRAY_BUFFER, obj_vert_buffer);
+      const buffers = object_buffers.get(object_render_snapshot.name);
+      if (!buffers)
+        throw new Error(`missing buffers for ${object_render_snapshot.name}`);
+      gl.bindBuffer(gl.ARRAY_BUFFER, buffers.vert);
       gl.enableVertexAttribArray(attrib_id_obj_vertex);
       gl.vertexAttribPointer(
         attrib_id_obj_vertex, // Attribute in question
@@ -374,7 +321,7 @@ export const init_canvas = async (
         0, // No stride (steps between indexes)
         0, // initial offset
       );
-      gl.bindBuffer(gl.ARRAY_BUFFER, obj_normals_buffer);
+      gl.bindBuffer(gl.ARRAY_BUFFER, buffers.normals);
       gl.enableVertexAttribArray(attrib_id_obj_normals);
       gl.vertexAttribPointer(
         attrib_id_obj_normals, // Attribute in question
@@ -384,7 +331,7 @@ export const init_canvas = async (
         0, // No stride (steps between indexes)
         0, // initial offset
       );
-      gl.bindBuffer(gl.ARRAY_BUFFER, obj_uvs_buffer);
+      gl.bindBuffer(gl.ARRAY_BUFFER, buffers.uvs);
       gl.enableVertexAttribArray(attrib_id_obj_uvs);
       gl.vertexAttribPointer(
         attrib_id_obj_uvs, // Attribute in question
@@ -418,6 +365,10 @@ export const init_canvas = async (
         id_camera_position,
         Float32Array.from(game_state.rust_state.camera_position()),
       );
+      gl.uniform3fv(
+        id_base_color,
+        Float32Array.from(object_render_snapshot.base_color),
+      );
       gl.drawArrays(gl.TRIANGLES, 0, object_render_snapshot.num_points);
       // Reset Attribute Array
       gl.disableVertexAttribArray(attrib_id_obj_vertex);
@@ -440,6 +391,7 @@ export const init_canvas = async (
 
   return {
     render,
+    load_scene,
     cleanup() {
       // Remove all listeners, clean up webgl memory, etc.
       cancelAnimationFrame(frame_req);
@@ -0,0 +1,651 @@
+//! Inverse kinematics for a chain of hinge joints,
+//! e.g. the robot arm: given where the end of the chain should be
+//! (and optionally which way it should face), find the joint angles.
+
+use std::f64::consts::PI;
+
+use nalgebra::{Matrix3, Matrix4, Point3, Rotation3, UnitQuaternion, UnitVector3, Vector3};
+use wasm_bindgen::prelude::*;
+
+/// A joint that can only rotate about one axis, optionally between two angles
+#[derive(Debug, Clone, PartialEq)]
+pub struct Joint {
+    /// In the joint's own coordinates
+    pub axis: UnitVector3<f64>,
+    pub min_angle: f64,
+    pub max_angle: f64,
+}
+
+impl Joint {
+    /// A joint that can turn all the way around its axis
+    pub fn new(axis: UnitVector3<f64>) -> Self {
+        Self {
+            axis,
+            min_angle: f64::NEG_INFINITY,
+            max_angle: f64::INFINITY,
+        }
+    }
+
+    pub fn with_limits(mut self, min_angle: f64, max_angle: f64) -> Self {
+        self.min_angle = min_angle;
+        self.max_angle = max_angle;
+        self
+    }
+
+    #[inline]
+    pub fn is_limited(&self) -> bool {
+        self.min_angle.is_finite() || self.max_angle.is_finite()
+    }
+
+    /// Whether the angle is up against one of the joint's limits
+    pub fn is_at_limit(&self, angle: f64) -> bool {
+        const LIMIT_TOLERANCE: f64 = 1e-9;
+        self.is_limited()
+            && (angle <= self.min_angle + LIMIT_TOLERANCE
+                || angle >= self.max_angle - LIMIT_TOLERANCE)
+    }
+
+    /// Brings an angle within the joint's limits.
+    /// Joints without limits keep their angles between -π and π instead.
+    pub fn clamp(&self, angle: f64) -> f64 {
+        if self.is_limited() {
+            angle.clamp(self.min_angle, self.max_angle)
+        } else {
+            angle - (2.0 * PI) * ((angle + PI) / (2.0 * PI)).floor()
+        }
+    }
+
+    #[inline]
+    pub fn rotation(&self, angle: f64) -> Matrix4<f64> {
+        Rotation3::from_axis_angle(&self.axis, angle).to_homogeneous()
+    }
+}
+
+/// A joint and where it sits relative to the joint before it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// From the previous joint's coordinates (after its rotation),
+    /// or the chain's base for the first joint, to this joint's coordinates
+    pub offset: Matrix4<f64>,
+    pub joint: Joint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chain {
+    /// Where the chain is attached, in world coordinates
+    pub base: Matrix4<f64>,
+    pub links: Vec<Link>,
+    /// The point being moved to the target, in the last joint's coordinates
+    pub tip: Point3<f64>,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solver {
+    /// Cyclic coordinate descent: turns one joint at a time, from the tip back to the base
+    Ccd,
+    /// Forward and backward reaching: drags the joint positions towards the target
+    /// and then fits the joint angles to those positions
+    Fabrik,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    /// Only the position of the tip matters
+    Free,
+    /// Points `axis` (in the last joint's coordinates) along `direction` (in world coordinates),
+    /// leaving the roll around it free
+    Direction {
+        axis: UnitVector3<f64>,
+        direction: UnitVector3<f64>,
+    },
+    /// Turns the last joint's coordinates to exactly this rotation (in world coordinates)
+    Rotation(UnitQuaternion<f64>),
+}
+
+impl Orientation {
+    /// A full rotation that points `forward` (in the last joint's coordinates) along `direction`,
+    /// then rolls it by `roll` radians around `direction` (by the right hand rule).
+    /// With no roll, `up` (also in the last joint's coordinates) ends up as close to
+    /// `reference_up` (in world coordinates) as it can.
+    pub fn from_direction_roll(
+        forward: &UnitVector3<f64>,
+        up: &Vector3<f64>,
+        direction: &UnitVector3<f64>,
+        roll: f64,
+        reference_up: &Vector3<f64>,
+    ) -> Self {
+        let frame = |forward: &UnitVector3<f64>, up: &Vector3<f64>| {
+            let up = perpendicular_part(up, forward);
+            Rotation3::from_matrix_unchecked(Matrix3::from_columns(&[
+                forward.into_inner(),
+                up.into_inner(),
+                forward.cross(&up),
+            ]))
+        };
+        let local = frame(forward, up);
+        let world = Rotation3::from_axis_angle(direction, roll) * frame(direction, reference_up);
+        Orientation::Rotation(UnitQuaternion::from_rotation_matrix(
+            &(world * local.inverse()),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    pub position: Point3<f64>,
+    pub orientation: Orientation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolverSettings {
+    pub solver: Solver,
+    pub max_iterations: usize,
+    /// How close counts as reaching the target,
+    /// in distance for the position and radians for the orientation
+    pub tolerance: f64,
+    /// Orientation targets are solved for by also moving points this far from the tip,
+    /// so larger values make the orientation matter more relative to the position
+    pub orientation_distance: f64,
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        Self {
+            solver: Solver::Fabrik,
+            max_iterations: 500,
+            tolerance: 1e-9,
+            orientation_distance: 1.0,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IkStatus {
+    /// The tip is at the target, within the solver's tolerance
+    Reached,
+    /// The target is within the arm's reach, but the joint limits
+    /// keep the tip from getting there, so the arm stops as close as it can
+    Clamped,
+    /// The target is within the arm's reach and no joint is up against its limits,
+    /// but the solver couldn't get the tip there, or facing the right way
+    /// (e.g. an orientation the joints can't turn the tip to)
+    Unsolved,
+    /// The target is further away than the arm can stretch,
+    /// so the arm reaches towards it as far as it can
+    Unreachable,
+    /// Every pose found that gets the tip there (or as close as it can)
+    /// runs the arm into itself or the floor, so the arm carries on with what it was doing
+    Collides,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IkResult {
+    pub status: IkStatus,
+    /// Distance from the tip to the target position
+    pub position_error: f64,
+    /// Radians between the tip's orientation and the target orientation
+    pub orientation_error: f64,
+    pub iterations: usize,
+}
+
+/// A point attached to the last joint, and where it should end up
+struct Goal {
+    local: Point3<f64>,
+    world: Point3<f64>,
+}
+
+impl Chain {
+    /// The world transform of each joint, including its rotation
+    pub fn frames(&self, angles: &[f64]) -> Vec<Matrix4<f64>> {
+        assert_eq!(angles.len(), self.links.len());
+        let mut frames: Vec<Matrix4<f64>> = Vec::with_capacity(self.links.len());
+        for (link, &angle) in self.links.iter().zip(angles) {
+            let parent = frames.last().unwrap_or(&self.base);
+            frames.push(parent * link.offset * link.joint.rotation(angle));
+        }
+        frames
+    }
+
+    pub fn end_frame(&self, angles: &[f64]) -> Matrix4<f64> {
+        self.frames(angles).pop().unwrap_or(self.base)
+    }
+
+    pub fn tip_position(&self, angles: &[f64]) -> Point3<f64> {
+        self.end_frame(angles).transform_point(&self.tip)
+    }
+
+    /// How far the tip can get from the first joint, if every joint lined up
+    pub fn max_reach(&self) -> f64 {
+        let mut reach = 0.0;
+        for link in self.links.iter().skip(1) {
+            reach += link.offset.fixed_view::<3, 1>(0, 3).norm();
+        }
+        reach + self.tip.coords.norm()
+    }
+
+    /// Moves the joint angles (starting from the ones given) to bring the tip to the target.
+    /// Targets that can't be reached end up as close as the solver can get.
+    pub fn solve(
+        &self,
+        angles: &mut [f64],
+        target: &Target,
+        settings: &SolverSettings,
+    ) -> IkResult {
+        assert_eq!(angles.len(), self.links.len());
+        let goals = self.goals(target, settings.orientation_distance);
+        let is_reached = |(position_error, orientation_error): (f64, f64)| {
+            position_error <= settings.tolerance && orientation_error <= settings.tolerance
+        };
+        let mut errors = self.errors(angles, target);
+        let mut iterations = 0;
+        while iterations < settings.max_iterations && !is_reached(errors) && !self.links.is_empty()
+        {
+            match settings.solver {
+                Solver::Ccd => self.ccd_iteration(angles, &goals),
+                Solver::Fabrik => self.fabrik_iteration(angles, target, &goals),
+            }
+            iterations += 1;
+            errors = self.errors(angles, target);
+        }
+
+        let status = if is_reached(errors) {
+            IkStatus::Reached
+        } else if !self.can_reach(&target.position) {
+            IkStatus::Unreachable
+        } else if self
+            .links
+            .iter()
+            .zip(angles.iter())
+            .any(|(link, &angle)| link.joint.is_at_limit(angle))
+        {
+            IkStatus::Clamped
+        } else {
+            IkStatus::Unsolved
+        };
+        IkResult {
+            status,
+            position_error: errors.0,
+            orientation_error: errors.1,
+            iterations,
+        }
+    }
+
+    /// Whether the point is close enough to the first joint for the tip to get there,
+    /// ignoring the joint limits
+    pub fn can_reach(&self, point: &Point3<f64>) -> bool {
+        match self.links.first() {
+            Some(link) => {
+                let first_pivot = (self.base * link.offset).transform_point(&Point3::origin());
+                (point - first_pivot).norm() <= self.max_reach()
+            }
+            None => false,
+        }
+    }
+
+    /// The distance from the tip to the target position,
+    /// and the angle between the tip's orientation and the target orientation
+    fn errors(&self, angles: &[f64], target: &Target) -> (f64, f64) {
+        let end_frame = self.end_frame(angles);
+        let position_error = (end_frame.transform_point(&self.tip) - target.position).norm();
+        let orientation_error = match target.orientation {
+            Orientation::Free => 0.0,
+            Orientation::Direction { axis, direction } => end_frame
+                .transform_vector(&axis)
+                .angle(&direction.into_inner()),
+            Orientation::Rotation(rotation) => rotation_of(&end_frame).angle_to(&rotation),
+        };
+        (position_error, orientation_error)
+    }
+
+    /// The tip and, for orientation targets, points around it that all need to reach their goals
+    fn goals(&self, target: &Target, distance: f64) -> Vec<Goal> {
+        let mut goals = vec![Goal {
+            local: self.tip,
+            world: target.position,
+        }];
+        match target.orientation {
+            Orientation::Free => {}
+            Orientation::Direction { axis, direction } => goals.push(Goal {
+                local: self.tip - axis.into_inner() * distance,
+                world: target.position - direction.into_inner() * distance,
+            }),
+            Orientation::Rotation(rotation) => {
+                for axis in [Vector3::x(), Vector3::y(), Vector3::z()] {
+                    goals.push(Goal {
+                        local: self.tip + axis * distance,
+                        world: target.position + rotation * axis * distance,
+                    });
+                }
+            }
+        }
+        goals
+    }
+
+    /// Where a joint turns around, and the axis it turns around, in world coordinates
+    fn pivot(&self, frames: &[Matrix4<f64>], i: usize) -> (Point3<f64>, UnitVector3<f64>) {
+        let parent = if i == 0 { &self.base } else { &frames[i - 1] };
+        let unrotated = parent * self.links[i].offset;
+        (
+            unrotated.transform_point(&Point3::origin()),
+            UnitVector3::new_normalize(unrotated.transform_vector(&self.links[i].joint.axis)),
+        )
+    }
+
+    /// Turns joint `i` to best line up the points with their goals
+    /// (the least squares angle about the joint's axis)
+    fn turn_towards(&self, angles: &mut [f64], i: usize, points: &[(Point3<f64>, Point3<f64>)]) {
+        let frames = self.frames(angles);
+        let (pivot, axis) = self.pivot(&frames, i);
+        let project = |point: &Point3<f64>| {
+            let offset = point - pivot;
+            offset - axis.into_inner() * axis.dot(&offset)
+        };
+        let (mut sin, mut cos) = (0.0, 0.0);
+        for (point, goal) in points {
+            let from = project(point);
+            let to = project(goal);
+            sin += axis.dot(&from.cross(&to));
+            cos += from.dot(&to);
+        }
+        if sin == 0.0 && cos == 0.0 {
+            // Every point is on the axis, so turning this joint doesn't move them
+            return;
+        }
+        angles[i] = self.links[i].joint.clamp(angles[i] + sin.atan2(cos));
+    }
+
+    fn ccd_iteration(&self, angles: &mut [f64], goals: &[Goal]) {
+        for i in (0..self.links.len()).rev() {
+            let end_frame = self.end_frame(angles);
+            let points: Vec<_> = goals
+                .iter()
+                .map(|goal| (end_frame.transform_point(&goal.local), goal.world))
+                .collect();
+            self.turn_towards(angles, i, &points);
+        }
+    }
+
+    fn fabrik_iteration(&self, angles: &mut [f64], target: &Target, goals: &[Goal]) {
+        let frames = self.frames(angles);
+        let end_frame = frames[frames.len() - 1];
+        // The joints' pivots followed by the tip
+        let mut points: Vec<Point3<f64>> = (0..self.links.len())
+            .map(|i| self.pivot(&frames, i).0)
+            .chain(std::iter::once(end_frame.transform_point(&self.tip)))
+            .collect();
+        let lengths: Vec<f64> = points
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).norm())
+            .collect();
+        let root = points[0];
+        let last = points.len() - 1;
+
+        // Backwards, from the target: the tip's orientation decides where the last pivot goes
+        let tip_offset = points[last] - points[last - 1];
+        let turn = match target.orientation {
+            Orientation::Free => UnitQuaternion::identity(),
+            Orientation::Direction { axis, direction } => {
+                UnitQuaternion::rotation_between(&end_frame.transform_vector(&axis), &direction)
+                    .unwrap_or_else(UnitQuaternion::identity)
+            }
+            Orientation::Rotation(rotation) => rotation * rotation_of(&end_frame).inverse(),
+        };
+        points[last] = target.position;
+        points[last - 1] = target.position - turn * tip_offset;
+        for i in (0..last - 1).rev() {
+            points[i] = reach_towards(&points[i + 1], &points[i], lengths[i]);
+        }
+        // Forwards, from the root
+        points[0] = root;
+        for i in 0..last {
+            points[i + 1] = reach_towards(&points[i], &points[i + 1], lengths[i]);
+        }
+
+        // Fit the joint angles to the new positions, from the root out
+        for i in 0..self.links.len() {
+            let frames = self.frames(angles);
+            let end_frame = frames[frames.len() - 1];
+            let mut pairs: Vec<_> = (i + 1..self.links.len())
+                .map(|j| (self.pivot(&frames, j).0, points[j]))
+                .collect();
+            pairs.extend(
+                goals
+                    .iter()
+                    .map(|goal| (end_frame.transform_point(&goal.local), goal.world)),
+            );
+            self.turn_towards(angles, i, &pairs);
+        }
+    }
+}
+
+/// The point `length` away from `from` in the direction of `towards`
+fn reach_towards(from: &Point3<f64>, towards: &Point3<f64>, length: f64) -> Point3<f64> {
+    let offset = towards - from;
+    let distance = offset.norm();
+    if distance == 0.0 {
+        *from
+    } else {
+        from + offset * (length / distance)
+    }
+}
+
+/// The direction of the part of `vector` at right angles to `axis`,
+/// or any direction at right angles to `axis` if `vector` is parallel to it
+fn perpendicular_part(vector: &Vector3<f64>, axis: &UnitVector3<f64>) -> UnitVector3<f64> {
+    [*vector, Vector3::y(), Vector3::x()]
+        .iter()
+        .find_map(|vector| {
+            UnitVector3::try_new(vector - axis.into_inner() * axis.dot(vector), 1e-9)
+        })
+        .unwrap()
+}
+
+/// The rotation part of a transform, ignoring any scale
+pub(crate) fn rotation_of(transform: &Matrix4<f64>) -> UnitQuaternion<f64> {
+    let linear: Matrix3<f64> = transform.fixed_view::<3, 3>(0, 0).into();
+    UnitQuaternion::from_matrix(&linear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{point, vector, Translation3};
+
+    /// Three links of length 1 along x, the first turning around y and the others around z
+    fn planar_arm() -> Chain {
+        let link = |offset: f64, axis: Vector3<f64>| Link {
+            offset: Translation3::new(offset, 0.0, 0.0).to_homogeneous(),
+            joint: Joint::new(UnitVector3::new_normalize(axis)),
+        };
+        Chain {
+            base: Matrix4::identity(),
+            links: vec![
+                link(0.0, Vector3::y()),
+                link(1.0, Vector3::z()),
+                link(1.0, Vector3::z()),
+            ],
+            tip: point![1.0, 0.0, 0.0],
+        }
+    }
+
+    fn settings(solver: Solver) -> SolverSettings {
+        SolverSettings {
+            solver,
+            tolerance: 1e-8,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_forward_kinematics() {
+        let chain = planar_arm();
+        assert_eq!(chain.tip_position(&[0.0; 3]), point![3.0, 0.0, 0.0]);
+        assert_eq!(chain.max_reach(), 3.0);
+        let tip = chain.tip_position(&[0.0, PI / 2.0, 0.0]);
+        assert!((tip - point![1.0, 2.0, 0.0]).norm() < 1e-12);
+        let tip = chain.tip_position(&[PI / 2.0, 0.0, 0.0]);
+        assert!((tip - point![0.0, 0.0, -3.0]).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_reaches_position() {
+        for solver in [Solver::Ccd, Solver::Fabrik] {
+            let chain = planar_arm();
+            for target in [
+                point![1.0, 1.0, 1.0],
+                point![-2.0, 0.5, 0.0],
+                point![0.5, -1.0, -1.5],
+            ] {
+                let mut angles = [0.0; 3];
+                let target = Target {
+                    position: target,
+                    orientation: Orientation::Free,
+                };
+                let result = chain.solve(&mut angles, &target, &settings(solver));
+                assert_eq!(
+                    result.status,
+                    IkStatus::Reached,
+                    "{:?} {:?}",
+                    solver,
+                    result
+                );
+                assert!((chain.tip_position(&angles) - target.position).norm() < 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reaches_orientation() {
+        for solver in [Solver::Ccd, Solver::Fabrik] {
+            let chain = planar_arm();
+            // Tip pointing straight down, which needs the second joint at 60°
+            let target = Target {
+                position: point![1.5, 3.0f64.sqrt() / 2.0 - 1.0, 0.0],
+                orientation: Orientation::Direction {
+                    axis: Vector3::x_axis(),
+                    direction: -Vector3::y_axis(),
+                },
+            };
+            let mut angles = [0.0; 3];
+            let result = chain.solve(&mut angles, &target, &settings(solver));
+            assert_eq!(
+                result.status,
+                IkStatus::Reached,
+                "{:?} {:?}",
+                solver,
+                result
+            );
+            let end_frame = chain.end_frame(&angles);
+            let direction = end_frame.transform_vector(&Vector3::x());
+            assert!((direction - vector![0.0, -1.0, 0.0]).norm() < 1e-6);
+
+            // The same, as a full rotation
+            let target = Target {
+                orientation: Orientation::Rotation(UnitQuaternion::from_axis_angle(
+                    &Vector3::z_axis(),
+                    -PI / 2.0,
+                )),
+                ..target
+            };
+            let mut angles = [0.0; 3];
+            let result = chain.solve(&mut angles, &target, &settings(solver));
+            assert_eq!(
+                result.status,
+                IkStatus::Reached,
+                "{:?} {:?}",
+                solver,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_direction_roll() {
+        let forward = Vector3::x_axis();
+        let up = Vector3::y();
+        let direction = UnitVector3::new_normalize(vector![0.0, 0.0, -1.0]);
+        let world_up = Vector3::y();
+        let rotation = |roll: f64| match Orientation::from_direction_roll(
+            &forward, &up, &direction, roll, &world_up,
+        ) {
+            Orientation::Rotation(rotation) => rotation,
+            orientation => panic!("{:?}", orientation),
+        };
+
+        assert!((rotation(0.0) * forward.into_inner() - direction.into_inner()).norm() < 1e-12);
+        assert!((rotation(0.0) * up - world_up).norm() < 1e-12);
+        // Rolling a quarter turn clockwise, looking along the direction
+        assert!(
+            (rotation(PI / 2.0) * forward.into_inner() - direction.into_inner()).norm() < 1e-12
+        );
+        assert!((rotation(PI / 2.0) * up - vector![1.0, 0.0, 0.0]).norm() < 1e-12);
+
+        // Pointing straight up, there's no way to get closer to up so any roll is fine
+        let rotation =
+            Orientation::from_direction_roll(&forward, &up, &Vector3::y_axis(), 0.0, &world_up);
+        assert!(matches!(rotation, Orientation::Rotation(_)));
+    }
+
+    #[test]
+    fn test_out_of_reach() {
+        for solver in [Solver::Ccd, Solver::Fabrik] {
+            let chain = planar_arm();
+            let target = Target {
+                position: point![3.0, 0.0, 4.0],
+                orientation: Orientation::Free,
+            };
+            let mut angles = [0.0; 3];
+            let result = chain.solve(&mut angles, &target, &settings(solver));
+            assert_eq!(result.status, IkStatus::Unreachable);
+            // Stretched out towards the target
+            assert!((result.position_error - 2.0).abs() < 1e-6, "{:?}", result);
+            assert!(angles.iter().all(|angle| angle.is_finite()));
+        }
+    }
+
+    #[test]
+    fn test_joint_limits() {
+        let mut chain = planar_arm();
+        chain.links[1].joint = chain.links[1].joint.clone().with_limits(-0.5, 0.5);
+        let target = Target {
+            position: point![0.0, 3.0, 0.0],
+            orientation: Orientation::Free,
+        };
+        for solver in [Solver::Ccd, Solver::Fabrik] {
+            let mut angles = [0.0; 3];
+            let result = chain.solve(&mut angles, &target, &settings(solver));
+            assert_eq!(result.status, IkStatus::Clamped);
+            assert!(angles[1] <= 0.5 + 1e-12);
+            assert!(
+                (chain.tip_position(&angles) - target.position).norm() == result.position_error
+            );
+        }
+
+        let joint = Joint::new(Vector3::z_axis());
+        assert!((joint.clamp(3.0 * PI / 2.0) + PI / 2.0).abs() < 1e-12);
+        assert!(!joint.is_at_limit(PI));
+    }
+
+    #[test]
+    fn test_unsolved_orientation() {
+        // The tip's z axis only ever turns around y, so it can't point up
+        let chain = planar_arm();
+        let target = Target {
+            position: point![1.0, 1.0, 0.0],
+            orientation: Orientation::Direction {
+                axis: Vector3::z_axis(),
+                direction: Vector3::y_axis(),
+            },
+        };
+        for solver in [Solver::Ccd, Solver::Fabrik] {
+            let mut angles = [0.0; 3];
+            let result = chain.solve(&mut angles, &target, &settings(solver));
+            // None of the joints have limits to blame
+            assert_eq!(result.status, IkStatus::Unsolved, "{:?}", result);
+            assert!(result.orientation_error > 1.0, "{:?}", result);
+        }
+    }
+}
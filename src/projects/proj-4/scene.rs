@@ -0,0 +1,975 @@
+//! A scene file format for the robot arm: the game objects and their meshes, how they're attached,
+//! their initial transforms, the arm's joints and the light, written as JSON so scenes
+//! can be versioned and shared.
+//!
+//! ```json
+//! {
+//!   "light": [10, 10, 0],
+//!   "target": [1, 0.5, 1],
+//!   "objects": [
+//!     { "name": "base", "mesh": "Base.obj", "transform": { "rotation": [0.707, 0, 0, 0.707] } },
+//!     {
+//!       "name": "shoulder", "parent": "base", "mesh": "Shoulder 1.obj",
+//!       "joint": { "axis": [0, 0, 1], "min_angle": -3.14, "max_angle": 0, "angle": -1 }
+//!     }
+//!   ],
+//!   "arm": { "end_effector": { "tip": [-0.8, 0, 0] }, "target_orientation": { "direction": [0, 1, 0] } }
+//! }
+//! ```
+//!
+//! Transforms are a `translation`, a `rotation` quaternion (x, y, z, w) and a `scale`,
+//! each left out when it does nothing, or a column-major `matrix` of 16 numbers.
+//! Joints without a `min_angle` or `max_angle` can turn all the way around.
+//! Objects can have a `material` with a `base_color` (red, green, blue, alpha), `metallic` and `roughness`.
+//!
+//! Lengths are in the scene's `units` (`mm`, `cm`, `m`, `in` or `ft`, metres if left out),
+//! and the OBJ files are in its `mesh_units` (also metres if left out, which is what Onshape exports).
+//! Instead of typing in how far apart the arm's joints are, an object can have a `pivot`
+//! that measures it from its parent's mesh: it's moved along `direction` to as far as the mesh reaches,
+//! less an `inset` (e.g. the radius of the rounded end the joint turns in).
+//!
+//! Scenes can also come from glTF files, which bring their own meshes and materials but no joints.
+
+use std::{collections::HashMap, fmt};
+
+use graphics_core::{
+    face::Face,
+    gltf::Gltf,
+    json::{Json, JsonError},
+    load_obj::load_obj_in_units,
+    material::Material,
+    transform::Transform,
+    units::{scale_faces, scale_transform, LengthUnit},
+};
+use nalgebra::{Matrix4, Point3, Quaternion, Unit, UnitQuaternion, UnitVector3, Vector3};
+use wasm_bindgen::prelude::*;
+
+use collision::Floor;
+use ik::Joint;
+use trajectory::{MotionLimits, MotionPlanning};
+use TargetOrientation;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError {
+    Json(JsonError),
+    /// Where in the file (e.g. `objects[2].joint.axis`), and what should have been there
+    Invalid {
+        path: String,
+        expected: &'static str,
+    },
+    /// A mesh the scene uses that hasn't been added
+    MissingMesh(String),
+    /// An object that couldn't be added to the game state, and why
+    Object {
+        name: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Json(err) => write!(f, "{}", err),
+            SceneError::Invalid { path, expected } => {
+                write!(f, "expected {} at {}", expected, path)
+            }
+            SceneError::MissingMesh(name) => write!(f, "the mesh {:?} hasn't been added", name),
+            SceneError::Object { name, message } => {
+                write!(f, "could not add {}: {}", name, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<JsonError> for SceneError {
+    fn from(err: JsonError) -> Self {
+        SceneError::Json(err)
+    }
+}
+
+/// One game object in a scene
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectDescription {
+    pub name: String,
+    pub parent: Option<String>,
+    /// The name of the OBJ file, or nothing for an object with no faces
+    pub mesh: Option<String>,
+    pub initial_transform: Matrix4<f64>,
+    pub joint: Option<JointDescription>,
+    pub material: Option<Material>,
+    /// Moves the object to where its parent's mesh ends, on top of its initial transform
+    pub pivot: Option<PivotDescription>,
+}
+
+/// Where an object sits on its parent, measured from the parent's mesh
+#[derive(Debug, Clone, PartialEq)]
+pub struct PivotDescription {
+    /// In the parent's coordinates
+    pub direction: UnitVector3<f64>,
+    /// How far back from the end of the mesh the pivot is
+    pub inset: f64,
+}
+
+impl PivotDescription {
+    /// The offset from the parent's origin to the pivot,
+    /// or nothing if the parent doesn't have any faces to measure
+    pub fn offset(&self, parent_faces: &[Face<f64>]) -> Option<Vector3<f64>> {
+        let reach = parent_faces
+            .iter()
+            .flat_map(|face| face.points())
+            .map(|point| point.coords.dot(&self.direction))
+            .reduce(f64::max)?;
+        Some(self.direction.into_inner() * (reach - self.inset))
+    }
+}
+
+/// A game object the arm's IK turns
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointDescription {
+    pub joint: Joint,
+    pub angle: f64,
+    pub limits: MotionLimits,
+}
+
+/// How the arm's end effector and IK are set up
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArmDescription {
+    pub end_effector_tip: Point3<f64>,
+    pub end_effector_forward: UnitVector3<f64>,
+    pub end_effector_up: UnitVector3<f64>,
+    pub target_orientation: TargetOrientation,
+    pub motion_planning: MotionPlanning,
+    pub avoid_collisions: bool,
+    pub floor: Option<Floor>,
+    pub allowed_contacts: Vec<(String, Option<String>)>,
+}
+
+impl Default for ArmDescription {
+    fn default() -> Self {
+        Self {
+            end_effector_tip: Point3::origin(),
+            end_effector_forward: Vector3::x_axis(),
+            end_effector_up: Vector3::y_axis(),
+            target_orientation: TargetOrientation::Free,
+            motion_planning: MotionPlanning::Snap,
+            avoid_collisions: false,
+            floor: None,
+            allowed_contacts: vec![],
+        }
+    }
+}
+
+/// A whole scene, and the meshes it needs once they've been loaded
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct SceneDescription {
+    #[wasm_bindgen(skip)]
+    pub objects: Vec<ObjectDescription>,
+    #[wasm_bindgen(skip)]
+    pub light_position: Point3<f64>,
+    #[wasm_bindgen(skip)]
+    pub target: Point3<f64>,
+    #[wasm_bindgen(skip)]
+    pub arm: ArmDescription,
+    /// What the lengths in the scene are in
+    #[wasm_bindgen(skip)]
+    pub units: LengthUnit,
+    /// What the OBJ files are in, which they're scaled from when they're added
+    #[wasm_bindgen(skip)]
+    pub mesh_units: LengthUnit,
+    /// In the scene's units
+    meshes: HashMap<String, Vec<Face<f64>>>,
+}
+
+#[wasm_bindgen]
+impl SceneDescription {
+    pub fn parse(text: &str) -> Result<SceneDescription, JsError> {
+        Self::from_json(text).map_err(|err| JsError::new(&err.to_string()))
+    }
+
+    /// Reads a .glb file, or a .gltf file with its buffers inside it as data URIs.
+    /// `units` is what the file was exported in, if it isn't metres like it should be.
+    #[wasm_bindgen(js_name = from_gltf)]
+    pub fn from_gltf_bytes(
+        bytes: &[u8],
+        units: Option<String>,
+    ) -> Result<SceneDescription, JsError> {
+        let mut gltf = Gltf::from_bytes(bytes).map_err(|err| JsError::new(&err.to_string()))?;
+        if let Some(units) = units {
+            let units: LengthUnit = units.parse()?;
+            gltf.convert_units(units, LengthUnit::Metres);
+        }
+        Ok(Self::from_gltf(&gltf))
+    }
+
+    /// The meshes the scene uses, each named once, in the order they're first used
+    pub fn mesh_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for mesh in self
+            .objects
+            .iter()
+            .filter_map(|object| object.mesh.as_ref())
+        {
+            if !names.contains(mesh) {
+                names.push(mesh.clone());
+            }
+        }
+        names
+    }
+
+    /// The game objects in the scene, in the order they're added
+    pub fn object_names(&self) -> Vec<String> {
+        self.objects
+            .iter()
+            .map(|object| object.name.clone())
+            .collect()
+    }
+
+    /// Loads one of the meshes the scene uses, scaling it from the mesh units into the scene's units
+    pub fn add_mesh(&mut self, name: &str, obj_text: &str) {
+        let faces = load_obj_in_units(obj_text, self.mesh_units, self.units);
+        self.add_mesh_faces(name, faces);
+    }
+
+    #[wasm_bindgen(js_name = to_json)]
+    pub fn to_json_js(&self) -> String {
+        self.to_json().to_string_pretty()
+    }
+}
+
+impl SceneDescription {
+    pub fn new(
+        objects: Vec<ObjectDescription>,
+        light_position: Point3<f64>,
+        target: Point3<f64>,
+        arm: ArmDescription,
+    ) -> Self {
+        Self {
+            objects,
+            light_position,
+            target,
+            arm,
+            units: LengthUnit::Metres,
+            mesh_units: LengthUnit::Metres,
+            meshes: HashMap::new(),
+        }
+    }
+
+    pub fn with_units(mut self, units: LengthUnit, mesh_units: LengthUnit) -> Self {
+        self.units = units;
+        self.mesh_units = mesh_units;
+        self
+    }
+
+    /// The same scene with all of its lengths (including the meshes that have been added)
+    /// scaled into `target` units
+    pub fn in_units(&self, target: LengthUnit) -> Self {
+        let scale = self.units.scale_to(target);
+        let mut scene = self.clone();
+        scene.units = target;
+        if scale == 1.0 {
+            return scene;
+        }
+        for object in &mut scene.objects {
+            object.initial_transform = scale_transform(&object.initial_transform, scale);
+            if let Some(pivot) = &mut object.pivot {
+                pivot.inset *= scale;
+            }
+        }
+        scene.light_position *= scale;
+        scene.target *= scale;
+        scene.arm.end_effector_tip *= scale;
+        if let Some(floor) = &mut scene.arm.floor {
+            floor.point *= scale;
+        }
+        for faces in scene.meshes.values_mut() {
+            *faces = scale_faces(faces, scale);
+        }
+        scene
+    }
+
+    /// The nodes under a glTF file's scene as game objects, with their meshes already added.
+    /// Nodes are named after themselves (made unique where they need to be),
+    /// or `node 3` if they have no name.
+    /// A mesh with more than one primitive puts each one in a game object of its own,
+    /// attached to the node's and named `node[1]`, `node[2]`, etc.
+    pub fn from_gltf(gltf: &Gltf) -> Self {
+        let mut scene = Self::new(
+            vec![],
+            Point3::origin(),
+            Point3::origin(),
+            Default::default(),
+        );
+        let mut names: Vec<Option<String>> = vec![None; gltf.nodes.len()];
+        for (node_index, parent) in gltf.depth_first() {
+            let node = &gltf.nodes[node_index];
+            let base_name = node
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("node {}", node_index));
+            let name = scene.unique_name(&base_name);
+            names[node_index] = Some(name.clone());
+            let parent = parent.and_then(|parent| names[parent].clone());
+
+            let primitives = match node.mesh {
+                Some(mesh) => gltf.meshes[mesh]
+                    .primitives
+                    .iter()
+                    .enumerate()
+                    .map(|(i, primitive)| {
+                        let mesh_name = format!("meshes[{}].primitives[{}]", mesh, i);
+                        scene.add_mesh_faces(&mesh_name, primitive.faces());
+                        let material = primitive
+                            .material
+                            .map(|material| gltf.materials[material].clone());
+                        (mesh_name, material)
+                    })
+                    .collect(),
+                None => vec![],
+            };
+            let (mesh, material) = match primitives.as_slice() {
+                [(mesh, material)] => (Some(mesh.clone()), material.clone()),
+                _ => (None, None),
+            };
+            scene.objects.push(ObjectDescription {
+                name: name.clone(),
+                parent,
+                mesh,
+                initial_transform: node.transform,
+                joint: None,
+                material,
+                pivot: None,
+            });
+            if primitives.len() > 1 {
+                for (i, (mesh, material)) in primitives.into_iter().enumerate() {
+                    let part_name = scene.unique_name(&format!("{}[{}]", name, i));
+                    scene.objects.push(ObjectDescription {
+                        name: part_name,
+                        parent: Some(name.clone()),
+                        mesh: Some(mesh),
+                        initial_transform: Matrix4::identity(),
+                        joint: None,
+                        material,
+                        pivot: None,
+                    });
+                }
+            }
+        }
+        scene
+    }
+
+    /// `name`, or `name 2`, `name 3`, etc. if an object already has it
+    fn unique_name(&self, name: &str) -> String {
+        let taken = |candidate: &str| self.objects.iter().any(|object| object.name == candidate);
+        if !taken(name) {
+            return name.to_string();
+        }
+        (2..)
+            .map(|n| format!("{} {}", name, n))
+            .find(|candidate| !taken(candidate))
+            .unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, SceneError> {
+        let json = Json::parse(text)?;
+        let units = optional(&json, "units", "", unit)?.unwrap_or_default();
+        let mesh_units = optional(&json, "mesh_units", "", unit)?.unwrap_or_default();
+        let objects = required(&json, "objects", "")?
+            .as_array()
+            .ok_or_else(|| invalid("objects", "an array"))?
+            .iter()
+            .enumerate()
+            .map(|(i, object)| read_object(object, &format!("objects[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let light_position = optional(&json, "light", "", point)?.unwrap_or_else(Point3::origin);
+        let target = optional(&json, "target", "", point)?.unwrap_or_else(Point3::origin);
+        let arm = match json.get("arm") {
+            Some(arm) => read_arm(arm, "arm")?,
+            None => ArmDescription::default(),
+        };
+        Ok(Self::new(objects, light_position, target, arm).with_units(units, mesh_units))
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("units", Some(Json::from(self.units.symbol()))),
+            ("mesh_units", Some(Json::from(self.mesh_units.symbol()))),
+            ("light", Some(point_json(&self.light_position))),
+            ("target", Some(point_json(&self.target))),
+            (
+                "objects",
+                Some(Json::Array(self.objects.iter().map(object_json).collect())),
+            ),
+            ("arm", Some(arm_json(&self.arm))),
+        ])
+    }
+
+    /// Adds a mesh that's already in the scene's units
+    pub fn add_mesh_faces(&mut self, name: &str, faces: Vec<Face<f64>>) {
+        self.meshes.insert(name.to_string(), faces);
+    }
+
+    /// The faces of a mesh that has been added
+    pub fn mesh(&self, name: &str) -> Result<&[Face<f64>], SceneError> {
+        self.meshes
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| SceneError::MissingMesh(name.to_string()))
+    }
+}
+
+fn invalid(path: &str, expected: &'static str) -> SceneError {
+    SceneError::Invalid {
+        path: path.to_string(),
+        expected,
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn required<'a>(json: &'a Json, key: &str, path: &str) -> Result<&'a Json, SceneError> {
+    json.get(key)
+        .ok_or_else(|| invalid(&join(path, key), "a value"))
+}
+
+/// Reads a value that can be left out (or set to null)
+fn optional<T>(
+    json: &Json,
+    key: &str,
+    path: &str,
+    read: impl Fn(&Json, &str) -> Result<T, SceneError>,
+) -> Result<Option<T>, SceneError> {
+    match json.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(value) => read(value, &join(path, key)).map(Some),
+    }
+}
+
+fn number(json: &Json, path: &str) -> Result<f64, SceneError> {
+    json.as_f64().ok_or_else(|| invalid(path, "a number"))
+}
+
+fn boolean(json: &Json, path: &str) -> Result<bool, SceneError> {
+    json.as_bool().ok_or_else(|| invalid(path, "true or false"))
+}
+
+fn string(json: &Json, path: &str) -> Result<String, SceneError> {
+    json.as_str()
+        .map(String::from)
+        .ok_or_else(|| invalid(path, "a string"))
+}
+
+fn vector(json: &Json, path: &str) -> Result<Vector3<f64>, SceneError> {
+    json.as_f64_array()
+        .map(Vector3::from)
+        .ok_or_else(|| invalid(path, "3 numbers"))
+}
+
+fn point(json: &Json, path: &str) -> Result<Point3<f64>, SceneError> {
+    vector(json, path).map(Point3::from)
+}
+
+fn direction(json: &Json, path: &str) -> Result<UnitVector3<f64>, SceneError> {
+    Unit::try_new(vector(json, path)?, 1e-12).ok_or_else(|| invalid(path, "a non-zero vector"))
+}
+
+fn unit(json: &Json, path: &str) -> Result<LengthUnit, SceneError> {
+    json.as_str()
+        .and_then(|symbol| symbol.parse().ok())
+        .ok_or_else(|| invalid(path, "mm, cm, m, in or ft"))
+}
+
+fn quaternion(json: &Json, path: &str) -> Result<UnitQuaternion<f64>, SceneError> {
+    let [x, y, z, w] = json
+        .as_f64_array()
+        .ok_or_else(|| invalid(path, "4 numbers (x, y, z, w)"))?;
+    let quaternion = Quaternion::new(w, x, y, z);
+    if quaternion.norm() < 1e-12 {
+        return Err(invalid(path, "a non-zero quaternion"));
+    }
+    Ok(UnitQuaternion::from_quaternion(quaternion))
+}
+
+fn transform(json: &Json, path: &str) -> Result<Matrix4<f64>, SceneError> {
+    if let Some(matrix) = optional(json, "matrix", path, |json, path| {
+        json.as_f64_array::<16>()
+            .map(|values| Matrix4::from_column_slice(&values))
+            .ok_or_else(|| invalid(path, "16 numbers"))
+    })? {
+        return Ok(matrix);
+    }
+    let transform = Transform::new(
+        optional(json, "translation", path, vector)?.unwrap_or_else(Vector3::zeros),
+        optional(json, "rotation", path, quaternion)?.unwrap_or_else(UnitQuaternion::identity),
+        optional(json, "scale", path, vector)?.unwrap_or_else(|| Vector3::repeat(1.0)),
+    );
+    Ok(transform.to_homogeneous())
+}
+
+fn read_object(json: &Json, path: &str) -> Result<ObjectDescription, SceneError> {
+    Ok(ObjectDescription {
+        name: string(required(json, "name", path)?, &join(path, "name"))?,
+        parent: optional(json, "parent", path, string)?,
+        mesh: optional(json, "mesh", path, string)?,
+        initial_transform: optional(json, "transform", path, transform)?
+            .unwrap_or_else(Matrix4::identity),
+        joint: optional(json, "joint", path, read_joint)?,
+        material: optional(json, "material", path, read_material)?,
+        pivot: optional(json, "pivot", path, read_pivot)?,
+    })
+}
+
+fn read_pivot(json: &Json, path: &str) -> Result<PivotDescription, SceneError> {
+    Ok(PivotDescription {
+        direction: direction(required(json, "direction", path)?, &join(path, "direction"))?,
+        inset: optional(json, "inset", path, number)?.unwrap_or(0.0),
+    })
+}
+
+fn read_material(json: &Json, path: &str) -> Result<Material, SceneError> {
+    let defaults = Material::default();
+    Ok(Material {
+        name: optional(json, "name", path, string)?,
+        base_color: optional(json, "base_color", path, |json, path| {
+            json.as_f64_array()
+                .ok_or_else(|| invalid(path, "4 numbers (red, green, blue, alpha)"))
+        })?
+        .unwrap_or(defaults.base_color),
+        metallic: optional(json, "metallic", path, number)?.unwrap_or(defaults.metallic),
+        roughness: optional(json, "roughness", path, number)?.unwrap_or(defaults.roughness),
+        base_color_texture: None,
+        double_sided: optional(json, "double_sided", path, boolean)?
+            .unwrap_or(defaults.double_sided),
+    })
+}
+
+fn read_joint(json: &Json, path: &str) -> Result<JointDescription, SceneError> {
+    let defaults = MotionLimits::default();
+    let joint = Joint::new(direction(
+        required(json, "axis", path)?,
+        &join(path, "axis"),
+    )?)
+    .with_limits(
+        optional(json, "min_angle", path, number)?.unwrap_or(f64::NEG_INFINITY),
+        optional(json, "max_angle", path, number)?.unwrap_or(f64::INFINITY),
+    );
+    Ok(JointDescription {
+        joint,
+        angle: optional(json, "angle", path, number)?.unwrap_or(0.0),
+        limits: MotionLimits {
+            max_velocity: optional(json, "max_velocity", path, number)?
+                .unwrap_or(defaults.max_velocity),
+            max_acceleration: optional(json, "max_acceleration", path, number)?
+                .unwrap_or(defaults.max_acceleration),
+        },
+    })
+}
+
+fn read_arm(json: &Json, path: &str) -> Result<ArmDescription, SceneError> {
+    let defaults = ArmDescription::default();
+    let end_effector = json.get("end_effector").unwrap_or(&Json::Null);
+    let end_effector_path = join(path, "end_effector");
+    let motion_planning = |json: &Json, path: &str| match json.as_str() {
+        Some("snap") => Ok(MotionPlanning::Snap),
+        Some("joint") => Ok(MotionPlanning::Joint),
+        Some("cartesian") => Ok(MotionPlanning::Cartesian),
+        _ => Err(invalid(path, "\"snap\", \"joint\" or \"cartesian\"")),
+    };
+    let floor = |json: &Json, path: &str| {
+        Ok(Floor {
+            point: point(required(json, "point", path)?, &join(path, "point"))?,
+            normal: direction(required(json, "normal", path)?, &join(path, "normal"))?,
+        })
+    };
+    let allowed_contacts = |json: &Json, path: &str| {
+        let pairs = json.as_array().ok_or_else(|| invalid(path, "an array"))?;
+        pairs
+            .iter()
+            .enumerate()
+            .map(|(i, pair)| {
+                let path = format!("{}[{}]", path, i);
+                match pair.as_array() {
+                    Some([first, second]) => Ok((
+                        string(first, &path)?,
+                        if second.is_null() {
+                            None
+                        } else {
+                            Some(string(second, &path)?)
+                        },
+                    )),
+                    _ => Err(invalid(&path, "a pair of names (or a name and null)")),
+                }
+            })
+            .collect()
+    };
+    Ok(ArmDescription {
+        end_effector_tip: optional(end_effector, "tip", &end_effector_path, point)?
+            .unwrap_or(defaults.end_effector_tip),
+        end_effector_forward: optional(end_effector, "forward", &end_effector_path, direction)?
+            .unwrap_or(defaults.end_effector_forward),
+        end_effector_up: optional(end_effector, "up", &end_effector_path, direction)?
+            .unwrap_or(defaults.end_effector_up),
+        target_orientation: optional(json, "target_orientation", path, target_orientation)?
+            .unwrap_or(defaults.target_orientation),
+        motion_planning: optional(json, "motion_planning", path, motion_planning)?
+            .unwrap_or(defaults.motion_planning),
+        avoid_collisions: optional(json, "avoid_collisions", path, boolean)?
+            .unwrap_or(defaults.avoid_collisions),
+        floor: optional(json, "floor", path, floor)?,
+        allowed_contacts: optional(json, "allowed_contacts", path, allowed_contacts)?
+            .unwrap_or_default(),
+    })
+}
+
+fn target_orientation(json: &Json, path: &str) -> Result<TargetOrientation, SceneError> {
+    if let Some(rotation) = optional(json, "rotation", path, quaternion)? {
+        return Ok(TargetOrientation::Rotation(rotation));
+    }
+    let direction = match optional(json, "direction", path, direction)? {
+        Some(direction) => direction,
+        None => return Ok(TargetOrientation::Free),
+    };
+    Ok(match optional(json, "roll", path, number)? {
+        Some(roll) => TargetOrientation::DirectionRoll(direction, roll),
+        None => TargetOrientation::Direction(direction),
+    })
+}
+
+fn vector_json(vector: &Vector3<f64>) -> Json {
+    Json::from([vector.x, vector.y, vector.z])
+}
+
+fn point_json(point: &Point3<f64>) -> Json {
+    vector_json(&point.coords)
+}
+
+fn quaternion_json(rotation: &UnitQuaternion<f64>) -> Json {
+    Json::from([rotation.i, rotation.j, rotation.k, rotation.w])
+}
+
+/// As a translation, rotation and scale if it splits into them exactly, otherwise as a matrix
+fn transform_json(matrix: &Matrix4<f64>) -> Json {
+    match Transform::decompose(matrix) {
+        Ok(transform) if (transform.to_homogeneous() - matrix).amax() < 1e-12 => {
+            // Rounding error shouldn't add a scale of 1.0000000000000002 to every object
+            let translation =
+                (transform.translation.amax() > 1e-12).then(|| vector_json(&transform.translation));
+            let rotation =
+                (transform.rotation.angle() > 1e-12).then(|| quaternion_json(&transform.rotation));
+            let scale = ((transform.scale - Vector3::repeat(1.0)).amax() > 1e-12)
+                .then(|| vector_json(&transform.scale));
+            Json::object([
+                ("translation", translation),
+                ("rotation", rotation),
+                ("scale", scale),
+            ])
+        }
+        _ => Json::object([("matrix", Some(Json::from(matrix.as_slice().to_vec())))]),
+    }
+}
+
+fn object_json(object: &ObjectDescription) -> Json {
+    let joint = object.joint.as_ref().map(|joint| {
+        let finite = |value: f64| value.is_finite().then(|| Json::from(value));
+        Json::object([
+            ("axis", Some(vector_json(&joint.joint.axis))),
+            ("min_angle", finite(joint.joint.min_angle)),
+            ("max_angle", finite(joint.joint.max_angle)),
+            ("angle", Some(Json::from(joint.angle))),
+            ("max_velocity", finite(joint.limits.max_velocity)),
+            ("max_acceleration", finite(joint.limits.max_acceleration)),
+        ])
+    });
+    let transform = (object.initial_transform != Matrix4::identity())
+        .then(|| transform_json(&object.initial_transform));
+    let pivot = object.pivot.as_ref().map(|pivot| {
+        Json::object([
+            ("direction", Some(vector_json(&pivot.direction))),
+            ("inset", Some(Json::from(pivot.inset))),
+        ])
+    });
+    Json::object([
+        ("name", Some(Json::from(object.name.as_str()))),
+        ("parent", object.parent.as_deref().map(Json::from)),
+        ("mesh", object.mesh.as_deref().map(Json::from)),
+        ("transform", transform),
+        ("joint", joint),
+        ("material", object.material.as_ref().map(material_json)),
+        ("pivot", pivot),
+    ])
+}
+
+/// Textures are left out, since they're images in the file the material came from
+fn material_json(material: &Material) -> Json {
+    Json::object([
+        ("name", material.name.as_deref().map(Json::from)),
+        ("base_color", Some(Json::from(material.base_color))),
+        ("metallic", Some(Json::from(material.metallic))),
+        ("roughness", Some(Json::from(material.roughness))),
+        (
+            "double_sided",
+            material.double_sided.then(|| Json::from(true)),
+        ),
+    ])
+}
+
+fn arm_json(arm: &ArmDescription) -> Json {
+    let target_orientation = match arm.target_orientation {
+        TargetOrientation::Free => None,
+        TargetOrientation::Direction(direction) => {
+            Some(Json::object([("direction", Some(vector_json(&direction)))]))
+        }
+        TargetOrientation::DirectionRoll(direction, roll) => Some(Json::object([
+            ("direction", Some(vector_json(&direction))),
+            ("roll", Some(Json::from(roll))),
+        ])),
+        TargetOrientation::Rotation(rotation) => Some(Json::object([(
+            "rotation",
+            Some(quaternion_json(&rotation)),
+        )])),
+    };
+    let motion_planning = match arm.motion_planning {
+        MotionPlanning::Snap => "snap",
+        MotionPlanning::Joint => "joint",
+        MotionPlanning::Cartesian => "cartesian",
+    };
+    let floor = arm.floor.map(|floor| {
+        Json::object([
+            ("point", Some(point_json(&floor.point))),
+            ("normal", Some(vector_json(&floor.normal))),
+        ])
+    });
+    let allowed_contacts = (!arm.allowed_contacts.is_empty()).then(|| {
+        Json::Array(
+            arm.allowed_contacts
+                .iter()
+                .map(|(first, second)| {
+                    Json::Array(vec![
+                        Json::from(first.as_str()),
+                        second.as_deref().map_or(Json::Null, Json::from),
+                    ])
+                })
+                .collect(),
+        )
+    });
+    Json::object([
+        (
+            "end_effector",
+            Some(Json::object([
+                ("tip", Some(point_json(&arm.end_effector_tip))),
+                ("forward", Some(vector_json(&arm.end_effector_forward))),
+                ("up", Some(vector_json(&arm.end_effector_up))),
+            ])),
+        ),
+        ("target_orientation", target_orientation),
+        ("motion_planning", Some(Json::from(motion_planning))),
+        ("avoid_collisions", Some(Json::from(arm.avoid_collisions))),
+        ("floor", floor),
+        ("allowed_contacts", allowed_contacts),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{point, vector};
+    use GameState;
+
+    #[test]
+    fn test_round_trip() {
+        let text = r#"{
+            "light": [10, 10, 0],
+            "objects": [
+                { "name": "base", "mesh": "Base.obj", "transform": { "rotation": [0, 0, 1, 1] } },
+                {
+                    "name": "shoulder",
+                    "parent": "base",
+                    "transform": { "translation": [0, 1, 0], "scale": [2, 2, 2] },
+                    "joint": { "axis": [0, 0, 2], "max_angle": 1, "angle": 0.5, "max_velocity": 2 }
+                },
+                { "name": "sheared", "transform": { "matrix": [1, 0, 0, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1] } }
+            ],
+            "arm": {
+                "end_effector": { "tip": [1, 0, 0] },
+                "target_orientation": { "direction": [0, 1, 0], "roll": 0.25 },
+                "motion_planning": "cartesian",
+                "floor": { "point": [0, -1, 0], "normal": [0, 1, 0] },
+                "allowed_contacts": [["base", null], ["base", "sheared"]]
+            }
+        }"#;
+        let scene = SceneDescription::from_json(text).unwrap();
+        assert_eq!(scene.light_position, point![10.0, 10.0, 0.0]);
+        assert_eq!(scene.target, Point3::origin());
+        assert_eq!(scene.mesh_names(), vec!["Base.obj".to_string()]);
+
+        let shoulder = &scene.objects[1];
+        assert_eq!(shoulder.parent.as_deref(), Some("base"));
+        assert_eq!(shoulder.mesh, None);
+        assert_eq!(
+            shoulder
+                .initial_transform
+                .transform_point(&point![1.0, 0.0, 0.0]),
+            point![2.0, 1.0, 0.0]
+        );
+        let joint = shoulder.joint.as_ref().unwrap();
+        assert_eq!(joint.joint.axis, Vector3::z_axis());
+        assert_eq!(joint.joint.min_angle, f64::NEG_INFINITY);
+        assert_eq!(joint.joint.max_angle, 1.0);
+        assert_eq!(joint.angle, 0.5);
+        assert_eq!(joint.limits.max_velocity, 2.0);
+        assert_eq!(joint.limits.max_acceleration, f64::INFINITY);
+
+        assert_eq!(scene.arm.end_effector_up, Vector3::y_axis());
+        assert_eq!(scene.arm.motion_planning, MotionPlanning::Cartesian);
+        assert_eq!(
+            scene.arm.allowed_contacts,
+            vec![
+                ("base".to_string(), None),
+                ("base".to_string(), Some("sheared".to_string()))
+            ]
+        );
+
+        let exported = scene.to_json().to_string_pretty();
+        let reloaded = SceneDescription::from_json(&exported).unwrap();
+        assert_eq!(reloaded.objects.len(), scene.objects.len());
+        for (reloaded, object) in reloaded.objects.iter().zip(&scene.objects) {
+            assert!((reloaded.initial_transform - object.initial_transform).amax() < 1e-12);
+            assert_eq!(reloaded.joint, object.joint);
+        }
+        assert_eq!(reloaded.arm, scene.arm);
+        assert!(exported.contains("\"matrix\""));
+    }
+
+    #[test]
+    fn test_from_gltf() {
+        // Two nodes called "part", the second with a mesh of two triangles in different materials
+        let text = r#"{
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [
+                { "name": "part", "translation": [0, 1, 0], "children": [1] },
+                { "name": "part", "mesh": 0, "scale": [2, 2, 2] }
+            ],
+            "meshes": [{
+                "primitives": [
+                    { "attributes": { "POSITION": 0 }, "material": 0 },
+                    { "attributes": { "POSITION": 0 } }
+                ]
+            }],
+            "materials": [{ "name": "red", "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1] } }],
+            "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+            "buffers": [{
+                "byteLength": 36,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+            }]
+        }"#;
+        let scene = SceneDescription::from_gltf(&Gltf::from_bytes(text.as_bytes()).unwrap());
+        assert_eq!(
+            scene.object_names(),
+            vec!["part", "part 2", "part 2[0]", "part 2[1]"]
+        );
+        assert_eq!(scene.objects[1].parent.as_deref(), Some("part"));
+        assert_eq!(scene.objects[1].mesh, None);
+        assert_eq!(scene.objects[2].parent.as_deref(), Some("part 2"));
+        let red = scene.objects[2].material.as_ref().unwrap();
+        assert_eq!(red.base_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(scene.objects[3].material, None);
+
+        let game_state = GameState::from_scene(&scene).unwrap();
+        let corner = game_state
+            .world_transform("part 2[1]")
+            .unwrap()
+            .transform_point(&point![1.0, 0.0, 0.0]);
+        assert_eq!(corner, point![2.0, 1.0, 0.0]);
+        assert_eq!(
+            game_state.world_bounding_box("part 2[0]").unwrap().max_pt,
+            point![2.0, 3.0, 0.0]
+        );
+
+        // Materials are kept when the scene is written back out
+        let exported = SceneDescription::from_json(&game_state.export_scene()).unwrap();
+        assert_eq!(exported.objects[2].material.as_ref(), Some(red));
+        assert_eq!(
+            exported.objects[2].mesh.as_deref(),
+            Some("meshes[0].primitives[0]")
+        );
+    }
+
+    #[test]
+    fn test_units() {
+        let text = r#"{
+            "units": "in",
+            "mesh_units": "mm",
+            "target": [10, 0, 0],
+            "objects": [
+                { "name": "base", "mesh": "Base.obj", "transform": { "translation": [0, 2, 0] } },
+                { "name": "arm", "parent": "base", "pivot": { "direction": [1, 0, 0], "inset": 1 } }
+            ],
+            "arm": { "end_effector": { "tip": [-3, 0, 0] } }
+        }"#;
+        let mut scene = SceneDescription::from_json(text).unwrap();
+        assert_eq!(scene.units, LengthUnit::Inches);
+        assert_eq!(scene.mesh_units, LengthUnit::Millimetres);
+        scene.add_mesh("Base.obj", "v 0 0 0\nv 127 0 0\nv 0 127 0\nf 1// 2// 3//");
+        assert_eq!(
+            scene.mesh("Base.obj").unwrap()[0].bounding_box().max_pt,
+            point![5.0, 5.0, 0.0]
+        );
+
+        let metres = scene.in_units(LengthUnit::Metres);
+        let inch = LengthUnit::Inches.metres();
+        assert!((metres.target - point![10.0 * inch, 0.0, 0.0]).norm() < 1e-12);
+        assert!((metres.arm.end_effector_tip - point![-3.0 * inch, 0.0, 0.0]).norm() < 1e-12);
+        assert!((metres.objects[0].initial_transform[(1, 3)] - 2.0 * inch).abs() < 1e-12);
+        let base = metres.mesh("Base.obj").unwrap();
+        let pivot = metres.objects[1].pivot.as_ref().unwrap();
+        assert!((pivot.offset(base).unwrap() - vector![4.0 * inch, 0.0, 0.0]).norm() < 1e-12);
+
+        let game_state = GameState::from_scene(&scene).unwrap();
+        let arm = game_state
+            .world_transform("arm")
+            .unwrap()
+            .transform_point(&Point3::origin());
+        assert!((arm - point![4.0 * inch, 2.0 * inch, 0.0]).norm() < 1e-12);
+
+        // Written back out as it was read in
+        let reloaded = SceneDescription::from_json(&scene.to_json().to_string_pretty()).unwrap();
+        assert_eq!(reloaded.units, LengthUnit::Inches);
+        assert_eq!(reloaded.objects[1].pivot, scene.objects[1].pivot);
+        assert_eq!(
+            SceneDescription::from_json(r#"{ "units": "yd", "objects": [] }"#).unwrap_err(),
+            invalid("units", "mm, cm, m, in or ft")
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| SceneDescription::from_json(text).unwrap_err();
+        assert!(matches!(error("{"), SceneError::Json(_)));
+        assert_eq!(error("{}"), invalid("objects", "a value"));
+        assert_eq!(
+            error(r#"{ "objects": [{ "name": "a", "joint": { "axis": [0, 0] } }] }"#),
+            invalid("objects[0].joint.axis", "3 numbers")
+        );
+        assert_eq!(
+            error(r#"{ "objects": [], "arm": { "motion_planning": "fast" } }"#),
+            invalid(
+                "arm.motion_planning",
+                "\"snap\", \"joint\" or \"cartesian\""
+            )
+        );
+        let scene = SceneDescription::from_json(r#"{ "objects": [] }"#).unwrap();
+        assert_eq!(
+            scene.mesh("Base.obj").unwrap_err(),
+            SceneError::MissingMesh("Base.obj".to_string())
+        );
+    }
+}
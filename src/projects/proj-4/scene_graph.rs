@@ -0,0 +1,375 @@
+use std::{collections::HashMap, fmt};
+
+use wasm_bindgen::prelude::*;
+
+/// A handle to a node in a `SceneGraph`.
+/// Handles stay valid when other nodes are added or removed,
+/// and a handle to a removed node never refers to a node added later.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneGraphError {
+    UnknownNode(NodeId),
+    UnknownName(String),
+    DuplicateName(String),
+    /// Making the node a child of one of its own descendants (or itself)
+    Cycle(NodeId),
+}
+
+impl fmt::Display for SceneGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneGraphError::UnknownNode(id) => write!(f, "no node with id {:?}", id),
+            SceneGraphError::UnknownName(name) => write!(f, "no node named {:?}", name),
+            SceneGraphError::DuplicateName(name) => {
+                write!(f, "there is already a node named {:?}", name)
+            }
+            SceneGraphError::Cycle(id) => {
+                write!(f, "node {:?} can't be its own ancestor", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneGraphError {}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    name: String,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// Whether anything this node's derived values (like its world transform) depend on
+    /// has changed since the last `take_dirty`
+    dirty: bool,
+    value: T,
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    generation: u32,
+    node: Option<Node<T>>,
+}
+
+/// A tree (or forest) of named nodes, each holding a `T`
+#[derive(Debug, Clone)]
+pub struct SceneGraph<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<usize>,
+    names: HashMap<String, NodeId>,
+    roots: Vec<NodeId>,
+}
+
+impl<T> Default for SceneGraph<T> {
+    fn default() -> Self {
+        Self {
+            slots: vec![],
+            free_slots: vec![],
+            names: HashMap::new(),
+            roots: vec![],
+        }
+    }
+}
+
+impl<T> SceneGraph<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node, as a root if `parent` is `None`
+    pub fn add(
+        &mut self,
+        name: &str,
+        parent: Option<NodeId>,
+        value: T,
+    ) -> Result<NodeId, SceneGraphError> {
+        if self.names.contains_key(name) {
+            return Err(SceneGraphError::DuplicateName(name.to_string()));
+        }
+        if let Some(parent) = parent {
+            self.node(parent)?;
+        }
+
+        let node = Node {
+            name: name.to_string(),
+            parent,
+            children: vec![],
+            dirty: true,
+            value,
+        };
+        let id = if let Some(index) = self.free_slots.pop() {
+            let slot = &mut self.slots[index];
+            slot.node = Some(node);
+            NodeId {
+                index,
+                generation: slot.generation,
+            }
+        } else {
+            self.slots.push(Slot {
+                generation: 0,
+                node: Some(node),
+            });
+            NodeId {
+                index: self.slots.len() - 1,
+                generation: 0,
+            }
+        };
+
+        self.names.insert(name.to_string(), id);
+        self.siblings_mut(parent).push(id);
+        Ok(id)
+    }
+
+    /// Removes a node and all of its descendants,
+    /// returning the removed node's value
+    pub fn remove(&mut self, id: NodeId) -> Result<T, SceneGraphError> {
+        let parent = self.node(id)?.parent;
+        self.siblings_mut(parent).retain(|&sibling| sibling != id);
+
+        let mut to_remove = self.node(id)?.children.clone();
+        while let Some(descendant) = to_remove.pop() {
+            let node = self.take(descendant);
+            to_remove.extend(node.children);
+        }
+        Ok(self.take(id).value)
+    }
+
+    /// Moves a node (along with its descendants) to a new parent,
+    /// or makes it a root if `new_parent` is `None`
+    pub fn reparent(
+        &mut self,
+        id: NodeId,
+        new_parent: Option<NodeId>,
+    ) -> Result<(), SceneGraphError> {
+        let old_parent = self.node(id)?.parent;
+        if let Some(new_parent) = new_parent {
+            self.node(new_parent)?;
+            if new_parent == id || self.ancestors(new_parent).any(|ancestor| ancestor == id) {
+                return Err(SceneGraphError::Cycle(id));
+            }
+        }
+
+        self.siblings_mut(old_parent)
+            .retain(|&sibling| sibling != id);
+        self.siblings_mut(new_parent).push(id);
+        let node = self.node_mut(id)?;
+        node.parent = new_parent;
+        node.dirty = true;
+        Ok(())
+    }
+
+    /// Flags a node (and so its descendants) as needing its derived values recomputed
+    pub fn mark_dirty(&mut self, id: NodeId) -> Result<(), SceneGraphError> {
+        self.node_mut(id)?.dirty = true;
+        Ok(())
+    }
+
+    /// Every node that was marked dirty, or has a dirty ancestor,
+    /// with each parent coming before its children.
+    /// Clears the dirty flags.
+    pub fn take_dirty(&mut self) -> Vec<NodeId> {
+        let mut dirty = vec![];
+        let mut stack: Vec<(NodeId, bool)> =
+            self.roots.iter().rev().map(|&root| (root, false)).collect();
+        while let Some((id, parent_dirty)) = stack.pop() {
+            let node = self.node_mut(id).unwrap();
+            let is_dirty = parent_dirty || node.dirty;
+            node.dirty = false;
+            if is_dirty {
+                dirty.push(id);
+            }
+            stack.extend(node.children.iter().rev().map(|&child| (child, is_dirty)));
+        }
+        dirty
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.names.get(name).copied()
+    }
+
+    /// Looks up a node by name, for when the node is expected to be there
+    pub fn find_existing(&self, name: &str) -> Result<NodeId, SceneGraphError> {
+        self.find(name)
+            .ok_or_else(|| SceneGraphError::UnknownName(name.to_string()))
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.node(id).is_ok()
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.node(id).ok().map(|node| &node.value)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.node_mut(id).ok().map(|node| &mut node.value)
+    }
+
+    pub fn name(&self, id: NodeId) -> Option<&str> {
+        self.node(id).ok().map(|node| node.name.as_str())
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).ok().and_then(|node| node.parent)
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        self.node(id)
+            .map(|node| node.children.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The node's parent, grandparent, and so on up to the root
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |&ancestor| self.parent(ancestor))
+    }
+
+    /// Every node, with each parent coming before its children
+    pub fn depth_first(&self) -> Vec<NodeId> {
+        let mut order = Vec::with_capacity(self.len());
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.children(id).iter().rev());
+        }
+        order
+    }
+
+    fn node(&self, id: NodeId) -> Result<&Node<T>, SceneGraphError> {
+        self.slots
+            .get(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+            .ok_or(SceneGraphError::UnknownNode(id))
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> Result<&mut Node<T>, SceneGraphError> {
+        self.slots
+            .get_mut(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+            .ok_or(SceneGraphError::UnknownNode(id))
+    }
+
+    fn siblings_mut(&mut self, parent: Option<NodeId>) -> &mut Vec<NodeId> {
+        match parent {
+            Some(parent) => &mut self.node_mut(parent).unwrap().children,
+            None => &mut self.roots,
+        }
+    }
+
+    /// Empties a slot so it can be reused, without touching the parent's children
+    fn take(&mut self, id: NodeId) -> Node<T> {
+        let slot = &mut self.slots[id.index];
+        let node = slot.node.take().unwrap();
+        slot.generation += 1;
+        self.free_slots.push(id.index);
+        self.names.remove(&node.name);
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(graph: &SceneGraph<i32>, ids: &[NodeId]) -> Vec<String> {
+        ids.iter()
+            .map(|&id| graph.name(id).unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_add_and_find() {
+        let mut graph = SceneGraph::new();
+        let base = graph.add("base", None, 1).unwrap();
+        let shoulder = graph.add("shoulder", Some(base), 2).unwrap();
+        assert_eq!(graph.find("shoulder"), Some(shoulder));
+        assert_eq!(graph.get(shoulder), Some(&2));
+        assert_eq!(graph.parent(shoulder), Some(base));
+        assert_eq!(graph.children(base), &[shoulder]);
+        assert_eq!(
+            graph.add("base", None, 3),
+            Err(SceneGraphError::DuplicateName("base".to_string()))
+        );
+        assert_eq!(
+            graph.find_existing("elbow"),
+            Err(SceneGraphError::UnknownName("elbow".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_remove_subtree() {
+        let mut graph = SceneGraph::new();
+        let base = graph.add("base", None, 1).unwrap();
+        let shoulder = graph.add("shoulder", Some(base), 2).unwrap();
+        let arm = graph.add("arm", Some(shoulder), 3).unwrap();
+        let light = graph.add("light", None, 4).unwrap();
+
+        assert_eq!(graph.remove(shoulder), Ok(2));
+        assert!(!graph.contains(shoulder));
+        assert!(!graph.contains(arm));
+        assert_eq!(graph.find("arm"), None);
+        assert!(graph.children(base).is_empty());
+        assert_eq!(graph.len(), 2);
+
+        // Reusing the slot doesn't make the old handle valid again
+        let new_node = graph.add("arm", Some(light), 5).unwrap();
+        assert_eq!(graph.get(arm), None);
+        assert_eq!(graph.get(new_node), Some(&5));
+        assert_eq!(graph.remove(arm), Err(SceneGraphError::UnknownNode(arm)));
+    }
+
+    #[test]
+    fn test_reparent() {
+        let mut graph = SceneGraph::new();
+        let a = graph.add("a", None, 1).unwrap();
+        let b = graph.add("b", Some(a), 2).unwrap();
+        let c = graph.add("c", Some(b), 3).unwrap();
+
+        assert_eq!(graph.reparent(a, Some(c)), Err(SceneGraphError::Cycle(a)));
+        assert_eq!(graph.reparent(a, Some(a)), Err(SceneGraphError::Cycle(a)));
+
+        graph.reparent(c, None).unwrap();
+        graph.reparent(a, Some(c)).unwrap();
+        assert_eq!(graph.roots(), &[c]);
+        assert_eq!(graph.ancestors(b).collect::<Vec<_>>(), vec![a, c]);
+        assert_eq!(names(&graph, &graph.depth_first()), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_dirty_subtrees() {
+        let mut graph = SceneGraph::new();
+        let base = graph.add("base", None, 1).unwrap();
+        let shoulder = graph.add("shoulder", Some(base), 2).unwrap();
+        let arm = graph.add("arm", Some(shoulder), 3).unwrap();
+        let light = graph.add("light", None, 4).unwrap();
+
+        // New nodes start out dirty
+        assert_eq!(graph.take_dirty(), vec![base, shoulder, arm, light]);
+        assert!(graph.take_dirty().is_empty());
+
+        graph.mark_dirty(shoulder).unwrap();
+        assert_eq!(graph.take_dirty(), vec![shoulder, arm]);
+
+        graph.reparent(arm, Some(light)).unwrap();
+        graph.mark_dirty(light).unwrap();
+        assert_eq!(graph.take_dirty(), vec![light, arm]);
+    }
+}
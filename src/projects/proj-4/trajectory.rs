@@ -0,0 +1,401 @@
+//! Moving the arm to a new pose over time, the way a real machine would:
+//! every joint speeds up, cruises and slows down within its own limits,
+//! and they all set off and arrive together.
+
+use nalgebra::{Point3, UnitQuaternion};
+use wasm_bindgen::prelude::*;
+
+use ik::{rotation_of, Chain, IkResult, Joint, Orientation, SolverSettings, Target};
+
+/// How the arm gets to a new target
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionPlanning {
+    /// Jumps straight to the new pose
+    Snap,
+    /// Turns every joint steadily from its old angle to its new one.
+    /// The tip takes whatever path that gives it.
+    Joint,
+    /// Moves the tip in a straight line to the target,
+    /// turning it steadily to the target orientation on the way
+    Cartesian,
+}
+
+/// How fast one joint can turn, in radians per second (squared)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionLimits {
+    pub max_velocity: f64,
+    pub max_acceleration: f64,
+}
+
+impl Default for MotionLimits {
+    /// No limits at all: the joint gets there instantly
+    fn default() -> Self {
+        Self {
+            max_velocity: f64::INFINITY,
+            max_acceleration: f64::INFINITY,
+        }
+    }
+}
+
+/// How far along a path (from 0 to 1) something is over time,
+/// speeding up and slowing down at a constant rate with a steady speed in between
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Profile {
+    acceleration: f64,
+    /// The steady speed, which may never be reached for short moves
+    peak_velocity: f64,
+    /// How long speeding up (and slowing down) takes
+    ramp_time: f64,
+    duration: f64,
+}
+
+impl Profile {
+    fn new(max_velocity: f64, max_acceleration: f64) -> Self {
+        let ramp_time = max_velocity / max_acceleration;
+        let (peak_velocity, ramp_time) = if max_acceleration.is_infinite() {
+            (max_velocity, 0.0)
+        } else if max_velocity.is_infinite() || max_velocity * ramp_time >= 1.0 {
+            // Has to slow down again before reaching full speed
+            let ramp_time = max_acceleration.recip().sqrt();
+            (max_acceleration * ramp_time, ramp_time)
+        } else {
+            (max_velocity, ramp_time)
+        };
+        let cruise_time = (1.0 - peak_velocity * ramp_time) / peak_velocity;
+        Self {
+            acceleration: max_acceleration,
+            peak_velocity,
+            ramp_time,
+            duration: 2.0 * ramp_time + cruise_time.max(0.0),
+        }
+    }
+
+    fn position(&self, time: f64) -> f64 {
+        if time >= self.duration {
+            return 1.0;
+        }
+        if time <= 0.0 {
+            return 0.0;
+        }
+        if self.ramp_time == 0.0 {
+            return time / self.duration;
+        }
+        let ramp_distance = 0.5 * self.acceleration * self.ramp_time * self.ramp_time;
+        if time < self.ramp_time {
+            0.5 * self.acceleration * time * time
+        } else if time < self.duration - self.ramp_time {
+            ramp_distance + self.peak_velocity * (time - self.ramp_time)
+        } else {
+            let remaining = self.duration - time;
+            1.0 - 0.5 * self.acceleration * remaining * remaining
+        }
+    }
+}
+
+/// A timed path through joint space, starting and ending at rest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    /// Poses (one angle per joint) spaced evenly along the path, including both ends
+    waypoints: Vec<Vec<f64>>,
+    profile: Profile,
+    /// Seconds since the start
+    time: f64,
+}
+
+impl Trajectory {
+    /// Turns each joint of the chain straight from `start` to `end`.
+    /// Joints without limits go the short way around.
+    pub fn joint_space(chain: &Chain, start: &[f64], end: &[f64], limits: &[MotionLimits]) -> Self {
+        Self::through(chain, vec![start.to_vec(), end.to_vec()], limits)
+    }
+
+    /// Moves the tip of the chain in a straight line from where `start` puts it to the target,
+    /// solving the IK at `segments` points on the way.
+    /// Also returns how close the end of the path gets to the target.
+    ///
+    /// The joints turn steadily between those points, so their speeds stay within their limits,
+    /// but where the path bends at each point they change speed at once.
+    /// More segments make for smaller jolts.
+    pub fn cartesian(
+        chain: &Chain,
+        start: &[f64],
+        target: &Target,
+        settings: &SolverSettings,
+        limits: &[MotionLimits],
+        segments: usize,
+    ) -> (Self, IkResult) {
+        let segments = segments.max(1);
+        let start_frame = chain.end_frame(start);
+        let start_position = start_frame.transform_point(&chain.tip);
+        let start_rotation = rotation_of(&start_frame);
+        let mut waypoints = vec![start.to_vec()];
+        let mut angles = start.to_vec();
+        let mut result = None;
+        for segment in 1..=segments {
+            let s = segment as f64 / segments as f64;
+            let waypoint = if segment == segments {
+                *target
+            } else {
+                Target {
+                    position: Point3::from(start_position.coords.lerp(&target.position.coords, s)),
+                    orientation: partway(&start_rotation, &target.orientation, s),
+                }
+            };
+            result = Some(chain.solve(&mut angles, &waypoint, settings));
+            waypoints.push(angles.clone());
+        }
+        (Self::through(chain, waypoints, limits), result.unwrap())
+    }
+
+    /// Times a path so that no joint goes over its limits
+    fn through(chain: &Chain, mut waypoints: Vec<Vec<f64>>, limits: &[MotionLimits]) -> Self {
+        assert_eq!(limits.len(), chain.links.len());
+        for i in 1..waypoints.len() {
+            let (before, after) = waypoints.split_at_mut(i);
+            let previous = before.last().unwrap();
+            for ((angle, &previous), link) in after[0].iter_mut().zip(previous).zip(&chain.links) {
+                *angle = nearest_turn(&link.joint, previous, *angle);
+            }
+        }
+        // Every joint follows the same profile, scaled by how far it turns in each segment,
+        // so the slowest joint sets the pace for all of them
+        let segments = (waypoints.len() - 1) as f64;
+        let (mut max_velocity, mut max_acceleration) = (f64::INFINITY, f64::INFINITY);
+        for (joint, limits) in limits.iter().enumerate() {
+            let turn_per_path = waypoints
+                .windows(2)
+                .map(|pair| (pair[1][joint] - pair[0][joint]).abs() * segments)
+                .fold(0.0, f64::max);
+            if turn_per_path > 0.0 {
+                max_velocity = max_velocity.min(limits.max_velocity / turn_per_path);
+                max_acceleration = max_acceleration.min(limits.max_acceleration / turn_per_path);
+            }
+        }
+        Self {
+            waypoints,
+            profile: Profile::new(max_velocity, max_acceleration),
+            time: 0.0,
+        }
+    }
+
+    /// How long the whole move takes, in seconds
+    #[inline]
+    pub fn duration(&self) -> f64 {
+        self.profile.duration
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.duration()
+    }
+
+    /// The pose at the end of the path
+    pub fn end(&self) -> &[f64] {
+        self.waypoints.last().unwrap()
+    }
+
+    /// The pose some number of seconds after the start
+    pub fn angles_at(&self, time: f64) -> Vec<f64> {
+        let segments = self.waypoints.len() - 1;
+        let s = self.profile.position(time) * segments as f64;
+        let segment = (s.floor() as usize).min(segments - 1);
+        let t = s - segment as f64;
+        self.waypoints[segment]
+            .iter()
+            .zip(&self.waypoints[segment + 1])
+            .map(|(from, to)| from + (to - from) * t)
+            .collect()
+    }
+
+    /// Moves on by `dt` seconds and returns the new pose
+    pub fn advance(&mut self, dt: f64) -> Vec<f64> {
+        self.time += dt;
+        self.angles_at(self.time)
+    }
+}
+
+/// The angle a joint reaches `to` at, turning as little as it can from `from`
+fn nearest_turn(joint: &Joint, from: f64, to: f64) -> f64 {
+    if joint.is_limited() {
+        to
+    } else {
+        from + joint.clamp(to - from)
+    }
+}
+
+/// The orientation `s` of the way (0 to 1) from `start` to `target`
+fn partway(start: &UnitQuaternion<f64>, target: &Orientation, s: f64) -> Orientation {
+    match *target {
+        Orientation::Free => Orientation::Free,
+        Orientation::Direction { axis, direction } => {
+            let from = start * axis;
+            let turn = UnitQuaternion::rotation_between_axis(&from, &direction)
+                .map_or(UnitQuaternion::identity(), |turn| turn.powf(s));
+            Orientation::Direction {
+                axis,
+                direction: turn * from,
+            }
+        }
+        Orientation::Rotation(rotation) => {
+            Orientation::Rotation(start.try_slerp(&rotation, s, 1e-12).unwrap_or(rotation))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ik::{IkStatus, Link};
+    use nalgebra::{point, vector, Matrix4, Translation3, UnitVector3, Vector3};
+    use std::f64::consts::PI;
+
+    /// Two links of length 1 along x, both turning around z
+    fn planar_arm() -> Chain {
+        let link = |offset: f64| Link {
+            offset: Translation3::new(offset, 0.0, 0.0).to_homogeneous(),
+            joint: Joint::new(Vector3::z_axis()),
+        };
+        Chain {
+            base: Matrix4::identity(),
+            links: vec![link(0.0), link(1.0)],
+            tip: point![1.0, 0.0, 0.0],
+        }
+    }
+
+    fn limits() -> Vec<MotionLimits> {
+        vec![
+            MotionLimits {
+                max_velocity: 1.0,
+                max_acceleration: 2.0,
+            },
+            MotionLimits {
+                max_velocity: 0.5,
+                max_acceleration: 4.0,
+            },
+        ]
+    }
+
+    /// Checks the joints never turn faster (or, if `check_acceleration`, speed up faster)
+    /// than their limits
+    fn assert_within_limits(
+        trajectory: &Trajectory,
+        limits: &[MotionLimits],
+        check_acceleration: bool,
+    ) {
+        let dt = 1e-3;
+        let steps = (trajectory.duration() / dt).ceil() as usize + 2;
+        let poses: Vec<Vec<f64>> = (0..steps)
+            .map(|step| trajectory.angles_at(step as f64 * dt))
+            .collect();
+        for window in poses.windows(3) {
+            for (joint, limits) in limits.iter().enumerate() {
+                let velocity = (window[1][joint] - window[0][joint]) / dt;
+                let acceleration =
+                    (window[2][joint] - 2.0 * window[1][joint] + window[0][joint]) / (dt * dt);
+                assert!(velocity.abs() <= limits.max_velocity + 1e-9);
+                if check_acceleration {
+                    assert!(acceleration.abs() <= limits.max_acceleration + 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_profile() {
+        // Reaches full speed after half a second
+        let trapezoid = Profile::new(1.0, 2.0);
+        assert_eq!(trapezoid.duration, 1.5);
+        assert_eq!(trapezoid.position(0.5), 0.25);
+        assert_eq!(trapezoid.position(0.75), 0.5);
+        assert_eq!(trapezoid.position(1.5), 1.0);
+
+        // Has to slow down before reaching full speed
+        let triangle = Profile::new(10.0, 4.0);
+        assert_eq!(triangle.duration, 1.0);
+        assert_eq!(triangle.position(0.5), 0.5);
+
+        assert_eq!(Profile::new(2.0, f64::INFINITY).position(0.25), 0.5);
+        assert_eq!(Profile::new(f64::INFINITY, f64::INFINITY).duration, 0.0);
+    }
+
+    #[test]
+    fn test_joint_space() {
+        let chain = planar_arm();
+        let limits = limits();
+        let start = [0.0, 3.0];
+        // The second joint goes the short way around, through π
+        let end = [1.0, -3.0];
+        let mut trajectory = Trajectory::joint_space(&chain, &start, &end, &limits);
+        assert!((trajectory.end()[1] - (2.0 * PI - 3.0)).abs() < 1e-12);
+        assert_within_limits(&trajectory, &limits, true);
+        // The second joint only turns 2π - 6 = 0.28 radians, so the first one sets the pace
+        assert_eq!(trajectory.duration(), 1.5);
+
+        assert_eq!(trajectory.advance(0.0), start);
+        let halfway = trajectory.advance(0.75);
+        assert!((halfway[0] - 0.5).abs() < 1e-12);
+        assert!(!trajectory.is_finished());
+        assert_eq!(trajectory.advance(1.0), trajectory.end());
+        assert!(trajectory.is_finished());
+
+        let instant = Trajectory::joint_space(&chain, &start, &end, &[MotionLimits::default(); 2]);
+        assert!(instant.is_finished());
+    }
+
+    #[test]
+    fn test_cartesian() {
+        let chain = planar_arm();
+        let limits = limits();
+        let start = [0.0, PI / 2.0];
+        let target = Target {
+            position: point![1.0, -1.0, 0.0],
+            orientation: Orientation::Free,
+        };
+        let settings = SolverSettings {
+            tolerance: 1e-9,
+            ..Default::default()
+        };
+        let (trajectory, result) =
+            Trajectory::cartesian(&chain, &start, &target, &settings, &limits, 16);
+        assert_eq!(result.status, IkStatus::Reached);
+        assert_within_limits(&trajectory, &limits, false);
+
+        // The IK puts each waypoint on the straight line from (1, 1) to (1, -1),
+        // and the tip doesn't stray far from it in between
+        for waypoint in &trajectory.waypoints {
+            assert!((chain.tip_position(waypoint).x - 1.0).abs() < 1e-6);
+        }
+        let steps = 100;
+        for step in 0..=steps {
+            let time = trajectory.duration() * step as f64 / steps as f64;
+            let tip = chain.tip_position(&trajectory.angles_at(time));
+            assert!((tip.x - 1.0).abs() < 0.05, "{} at {}", tip, time);
+        }
+        let end = chain.tip_position(trajectory.end());
+        assert!((end - target.position).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_partway_orientation() {
+        let start = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), PI / 2.0);
+        let direction = Orientation::Direction {
+            axis: Vector3::x_axis(),
+            direction: UnitVector3::new_normalize(vector![0.0, 0.0, 1.0]),
+        };
+        // From +y (where the start rotation turns x to) halfway up to +z
+        match partway(&start, &direction, 0.5) {
+            Orientation::Direction { direction, .. } => {
+                let expected = vector![0.0, 1.0, 1.0].normalize();
+                assert!((direction.into_inner() - expected).norm() < 1e-12);
+            }
+            orientation => panic!("{:?}", orientation),
+        }
+
+        let rotation = Orientation::Rotation(UnitQuaternion::identity());
+        match partway(&start, &rotation, 0.5) {
+            Orientation::Rotation(rotation) => assert!((rotation.angle() - PI / 4.0).abs() < 1e-12),
+            orientation => panic!("{:?}", orientation),
+        }
+    }
+}